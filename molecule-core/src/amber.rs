@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::invalid_data;
+use crate::structure::{Atom, AtomIndex, BondOrder, Element, Molecule, UnitCell};
+use crate::trajectory::Frame;
+
+// prmtop charges are stored premultiplied by sqrt(332.0522173) to give kcal/mol
+const AMBER_CHARGE_SCALE: f32 = 18.2223;
+// inpcrd velocities are in Å per 1/20.455 ps
+const AMBER_VELOCITY_SCALE: f32 = 20.455;
+
+/// Reader for AMBER parameter/topology (prmtop) files.
+///
+/// The molecule gets the explicit bond list from the topology rather than
+/// distance-inferred bonds, along with per-atom charges, masses, atom types and
+/// Lennard-Jones parameters. Coordinates are zero until a frame from an
+/// inpcrd or trajectory file is applied.
+pub struct PrmtopParser;
+
+impl Default for PrmtopParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrmtopParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<Molecule> {
+        let reader = BufReader::new(content.as_bytes());
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Molecule> {
+        let sections = read_sections(reader)?;
+
+        let pointers = integer_section(&sections, "POINTERS")?;
+        if pointers.len() < 12 {
+            return Err(invalid_data("POINTERS section is too short"));
+        }
+        let atom_count = pointers[0] as usize;
+        let type_count = pointers[1] as usize;
+        let residue_count = pointers[11] as usize;
+
+        let names = required_section(&sections, "ATOM_NAME", atom_count)?;
+        let charges = float_section(&sections, "CHARGE")?;
+        let masses = float_section(&sections, "MASS")?;
+        let atom_types = sections.get("AMBER_ATOM_TYPE");
+        let atomic_numbers = sections.get("ATOMIC_NUMBER").map(|_| integer_section(&sections, "ATOMIC_NUMBER")).transpose()?;
+        let residue_labels = required_section(&sections, "RESIDUE_LABEL", residue_count)?;
+        let residue_pointers = integer_section(&sections, "RESIDUE_POINTER")?;
+        if charges.len() < atom_count || masses.len() < atom_count || residue_pointers.len() < residue_count {
+            return Err(invalid_data("per-atom sections are shorter than the atom count"));
+        }
+
+        // Residues start at 1-based atom offsets, in order and inside the atom range
+        let residue_pointers = &residue_pointers[..residue_count];
+        if residue_pointers.first().is_some_and(|&first| first != 1)
+            || residue_pointers.windows(2).any(|pair| pair[0] >= pair[1])
+            || residue_pointers.last().is_some_and(|&last| last as usize > atom_count)
+        {
+            return Err(invalid_data("RESIDUE_POINTER must increase from 1 within the atom count"));
+        }
+
+        let lennard_jones = lennard_jones_parameters(&sections, atom_count, type_count)?;
+
+        let mut molecule = Molecule::new();
+        for residue in 0..residue_count {
+            let first = residue_pointers[residue] as usize - 1;
+            let last = residue_pointers.get(residue + 1).map_or(atom_count, |&next| next as usize - 1);
+
+            for index in first..last {
+                let name = names[index].trim().to_string();
+                let mass = masses[index];
+                let element = match atomic_numbers.as_ref().and_then(|numbers| numbers.get(index)) {
+                    Some(&number) if number > 0 => Element::from_atomic_number(number as u8),
+                    _ => Element::from_mass(mass),
+                };
+                let (lj_sigma, lj_epsilon) = lennard_jones.as_ref().map_or((None, None), |lj| lj[index]);
+
+                molecule.add_atom(Atom {
+                    id: index + 1,
+                    name,
+                    element,
+                    residue_id: residue + 1,
+                    residue_name: residue_labels[residue].trim().to_string(),
+                    charge: Some(charges[index] / AMBER_CHARGE_SCALE),
+                    mass: Some(mass),
+                    atom_type: atom_types.and_then(|types| types.get(index)).map(|t| t.trim().to_string()),
+                    lj_sigma,
+                    lj_epsilon,
+                    ..Atom::default()
+                });
+            }
+        }
+
+        if molecule.atoms.len() != atom_count {
+            return Err(invalid_data(&format!(
+                "residues cover {} of the {} atoms in POINTERS",
+                molecule.atoms.len(),
+                atom_count
+            )));
+        }
+
+        for flag in ["BONDS_INC_HYDROGEN", "BONDS_WITHOUT_HYDROGEN"] {
+            let bonds = integer_section(&sections, flag)?;
+            // Triplets of (3 * atom index, 3 * atom index, bond type index)
+            for triplet in bonds.chunks_exact(3) {
+                let atom1 = (triplet[0] / 3) as usize;
+                let atom2 = (triplet[1] / 3) as usize;
+                if atom1 >= atom_count || atom2 >= atom_count {
+                    return Err(invalid_data(&format!("{} references a missing atom", flag)));
+                }
+                molecule.add_bond(AtomIndex(atom1), AtomIndex(atom2), BondOrder::Single);
+            }
+        }
+
+        Ok(molecule)
+    }
+}
+
+// Per-atom (sigma, epsilon) from the self-interaction A and B coefficients
+type LennardJones = Vec<(Option<f32>, Option<f32>)>;
+
+fn lennard_jones_parameters(
+    sections: &HashMap<String, Vec<String>>,
+    atom_count: usize,
+    type_count: usize,
+) -> io::Result<Option<LennardJones>> {
+    let required = ["ATOM_TYPE_INDEX", "NONBONDED_PARM_INDEX", "LENNARD_JONES_ACOEF", "LENNARD_JONES_BCOEF"];
+    if !required.iter().all(|flag| sections.contains_key(*flag)) {
+        return Ok(None);
+    }
+
+    let type_index = integer_section(sections, "ATOM_TYPE_INDEX")?;
+    let parameter_index = integer_section(sections, "NONBONDED_PARM_INDEX")?;
+    let a_coefficients = float_section(sections, "LENNARD_JONES_ACOEF")?;
+    let b_coefficients = float_section(sections, "LENNARD_JONES_BCOEF")?;
+
+    let parameters = (0..atom_count)
+        .map(|atom| {
+            let atom_type = *type_index.get(atom)? as usize;
+            let pair = atom_type.checked_sub(1)? * type_count + atom_type - 1;
+            let coefficient = (*parameter_index.get(pair)?).checked_sub(1)? as usize;
+            let (a, b) = (*a_coefficients.get(coefficient)?, *b_coefficients.get(coefficient)?);
+            if a <= 0.0 || b <= 0.0 {
+                // Typically hydrogens on polar atoms, which have no LJ sphere
+                return Some((Some(0.0), Some(0.0)));
+            }
+            // A = 4 eps sigma^12, B = 4 eps sigma^6
+            Some((Some((a / b).powf(1.0 / 6.0)), Some(b * b / (4.0 * a))))
+        })
+        .map(|parameters| parameters.unwrap_or((None, None)))
+        .collect();
+
+    Ok(Some(parameters))
+}
+
+// Splits a prmtop file into its %FLAG sections, cutting each line into the
+// fixed-width fields given by the section's %FORMAT
+fn read_sections<R: BufRead>(reader: R) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut sections = HashMap::new();
+    let mut current: Option<(String, usize)> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if let Some(flag) = line.strip_prefix("%FLAG") {
+            let flag = flag.trim().to_string();
+            sections.insert(flag.clone(), Vec::new());
+            current = Some((flag, 0));
+        } else if let Some(format) = line.strip_prefix("%FORMAT") {
+            if let Some((_, width)) = current.as_mut() {
+                *width = parse_format_width(format)
+                    .ok_or_else(|| invalid_data(&format!("unsupported format {}", format.trim())))?;
+            }
+        } else if line.starts_with('%') {
+            // %VERSION, %COMMENT
+            continue;
+        } else if let Some((flag, width)) = &current {
+            let fields = sections.get_mut(flag).unwrap();
+            let width = if *width == 0 { line.len().max(1) } else { *width };
+            let mut start = 0;
+            while start < line.len() {
+                let end = (start + width).min(line.len());
+                fields.push(line[start..end].to_string());
+                start = end;
+            }
+        }
+    }
+
+    Ok(sections)
+}
+
+// "(20a4)", "(10I8)", "(5E16.8)" -> field width
+fn parse_format_width(format: &str) -> Option<usize> {
+    let format = format.trim().trim_start_matches('(').trim_end_matches(')');
+    let position = format.find(|c: char| c.is_ascii_alphabetic())?;
+    let width = format[position + 1..].split('.').next()?;
+    width.parse().ok()
+}
+
+fn required_section<'a>(
+    sections: &'a HashMap<String, Vec<String>>,
+    flag: &str,
+    count: usize,
+) -> io::Result<&'a [String]> {
+    let fields = sections
+        .get(flag)
+        .ok_or_else(|| invalid_data(&format!("missing %FLAG {}", flag)))?;
+    if fields.len() < count {
+        return Err(invalid_data(&format!("%FLAG {} has too few entries", flag)));
+    }
+    Ok(fields)
+}
+
+fn integer_section(sections: &HashMap<String, Vec<String>>, flag: &str) -> io::Result<Vec<i64>> {
+    required_section(sections, flag, 0)?
+        .iter()
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| {
+            field
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data(&format!("invalid integer {:?} in {}", field, flag)))
+        })
+        .collect()
+}
+
+fn float_section(sections: &HashMap<String, Vec<String>>, flag: &str) -> io::Result<Vec<f32>> {
+    required_section(sections, flag, 0)?
+        .iter()
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| {
+            field
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data(&format!("invalid number {:?} in {}", field, flag)))
+        })
+        .collect()
+}
+
+/// Reader for AMBER coordinate/restart (inpcrd, rst7) files. The result is a
+/// `Frame` to apply onto a topology from `PrmtopParser`.
+pub struct InpcrdParser;
+
+impl Default for InpcrdParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InpcrdParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Frame> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<Frame> {
+        let reader = BufReader::new(content.as_bytes());
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Frame> {
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        let header = lines.get(1).ok_or_else(|| invalid_data("missing atom count line"))?;
+        let mut header_fields = header.split_whitespace();
+        let atom_count: usize = header_fields
+            .next()
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| invalid_data("invalid atom count"))?;
+        let time = header_fields.next().and_then(|time| time.parse().ok());
+
+        // Values are written as 6F12.7 and may run into each other, so they are
+        // read by column rather than split on whitespace
+        let rows = lines[2..]
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut row = Vec::new();
+                let mut start = 0;
+                while start + 12 <= line.len() {
+                    let field = line[start..start + 12].trim();
+                    row.push(field.parse::<f32>().map_err(|_| invalid_data(&format!("invalid coordinate {:?}", field)))?);
+                    start += 12;
+                }
+                Ok(row)
+            })
+            .collect::<io::Result<Vec<Vec<f32>>>>()?;
+
+        // Coordinates and velocities take the same number of lines and the box
+        // one more, so the line count says which sections are present. A lone
+        // extra line must also end in three angles, which tells it from a
+        // velocity line with one or two atoms; Amber velocities are far below 10.
+        let section_lines = (3 * atom_count).div_ceil(6);
+        let (has_velocities, has_box) = match rows.len().checked_sub(section_lines) {
+            Some(0) => (false, false),
+            Some(1) if looks_like_box(&rows[rows.len() - 1]) => (false, true),
+            Some(extra) if extra == section_lines => (true, false),
+            Some(extra) if extra == section_lines + 1 => (true, true),
+            _ => {
+                return Err(invalid_data(&format!(
+                    "expected {} or {} coordinate lines for {} atoms, found {}",
+                    section_lines,
+                    2 * section_lines,
+                    atom_count,
+                    rows.len()
+                )));
+            }
+        };
+
+        let section = |rows: &[Vec<f32>], scale: f32| -> io::Result<Vec<[f32; 3]>> {
+            let values: Vec<f32> = rows.iter().flatten().copied().collect();
+            if values.len() != 3 * atom_count {
+                return Err(invalid_data("wrong number of values in coordinate section"));
+            }
+            Ok(values.chunks(3).map(|c| [c[0] * scale, c[1] * scale, c[2] * scale]).collect())
+        };
+
+        let positions = section(&rows[..section_lines], 1.0)?;
+        let velocities = if has_velocities {
+            Some(section(&rows[section_lines..2 * section_lines], AMBER_VELOCITY_SCALE)?)
+        } else {
+            None
+        };
+        let unit_cell = if has_box {
+            match rows[rows.len() - 1][..] {
+                [a, b, c, alpha, beta, gamma] => Some(UnitCell::from_parameters(a, b, c, alpha, beta, gamma)),
+                _ => return Err(invalid_data("box line must hold six values")),
+            }
+        } else {
+            None
+        };
+
+        Ok(Frame {
+            index: 0,
+            step: None,
+            time,
+            positions,
+            velocities,
+            unit_cell,
+        })
+    }
+}
+
+fn looks_like_box(row: &[f32]) -> bool {
+    row.len() == 6 && row[3..].iter().all(|angle| (10.0..=180.0).contains(angle))
+}
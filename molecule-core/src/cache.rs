@@ -0,0 +1,585 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{invalid_data, invalid_input};
+use crate::structure::{
+    Atom, AtomIndex, BondDirection, BondOrder, Chain, ChainIndex, Chirality, Element, Metadata, Model, ModelIndex,
+    Molecule, Residue, ResidueIndex, UnitCell,
+};
+
+const MAGIC: &[u8; 4] = b"MOLB";
+/// Bumped whenever the binary layout changes; older files are rejected.
+pub const BINARY_FORMAT_VERSION: u32 = 4;
+
+const HEADER_SIZE: usize = 44;
+const ATOM_RECORD_SIZE: usize = 80;
+const NO_STRING: u32 = u32::MAX;
+const NO_MODEL: u32 = u32::MAX;
+
+const HAS_UNIT_CELL: u32 = 1;
+
+// Per-atom flag bits
+const HETATM: u8 = 1 << 0;
+const HAS_VELOCITY: u8 = 1 << 1;
+const HAS_CHARGE: u8 = 1 << 2;
+const HAS_RADIUS: u8 = 1 << 3;
+const HAS_MASS: u8 = 1 << 4;
+const HAS_LJ_SIGMA: u8 = 1 << 5;
+const HAS_LJ_EPSILON: u8 = 1 << 6;
+
+/// Writer for the molecule-rs binary format, a compact snapshot of a parsed
+/// `Molecule` including bonds, the model/chain/residue hierarchy and metadata.
+///
+/// All values are little-endian and 4-byte aligned. After a 44-byte header
+/// the file holds fixed-size sections (unit cell, positions, atom records,
+/// bonds, models, chains, residues, metadata) followed by a string table.
+pub struct BinaryWriter;
+
+impl Default for BinaryWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinaryWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        fs::write(path, self.write_bytes(molecule)?)
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.write_bytes(molecule)?)
+    }
+
+    pub fn write_bytes(&self, molecule: &Molecule) -> io::Result<Vec<u8>> {
+        let mut strings = StringTable::default();
+        let mut out = Vec::new();
+
+        let model_atom_count: usize = molecule.models.iter().map(|model| model.atoms.len()).sum();
+        let chain_residue_count: usize = molecule.chains.iter().map(|chain| chain.residues.len()).sum();
+        let flags = if molecule.unit_cell.is_some() { HAS_UNIT_CELL } else { 0 };
+
+        out.extend_from_slice(MAGIC);
+        put_u32(&mut out, BINARY_FORMAT_VERSION);
+        put_u32(&mut out, to_u32(molecule.atoms.len())?);
+        put_u32(&mut out, to_u32(molecule.bonds.len())?);
+        put_u32(&mut out, to_u32(molecule.models.len())?);
+        put_u32(&mut out, to_u32(model_atom_count)?);
+        put_u32(&mut out, to_u32(molecule.chains.len())?);
+        put_u32(&mut out, to_u32(chain_residue_count)?);
+        put_u32(&mut out, to_u32(molecule.residues.len())?);
+        put_u32(&mut out, flags);
+        let strings_offset_at = out.len();
+        put_u32(&mut out, 0); // String table offset, patched below
+
+        let vectors = molecule.unit_cell.map_or([[0.0; 3]; 3], |cell| cell.vectors);
+        for value in vectors.as_flattened() {
+            put_f32(&mut out, *value);
+        }
+
+        for atom in &molecule.atoms {
+            for value in atom.position {
+                put_f32(&mut out, value);
+            }
+        }
+
+        for atom in &molecule.atoms {
+            let mut atom_flags = 0;
+            for (present, flag) in [
+                (atom.is_hetatm, HETATM),
+                (atom.velocity.is_some(), HAS_VELOCITY),
+                (atom.charge.is_some(), HAS_CHARGE),
+                (atom.radius.is_some(), HAS_RADIUS),
+                (atom.mass.is_some(), HAS_MASS),
+                (atom.lj_sigma.is_some(), HAS_LJ_SIGMA),
+                (atom.lj_epsilon.is_some(), HAS_LJ_EPSILON),
+            ] {
+                if present {
+                    atom_flags |= flag;
+                }
+            }
+
+            put_u32(&mut out, to_u32(atom.id)?);
+            put_u32(&mut out, to_u32(atom.residue_id)?);
+            put_u32(&mut out, strings.insert(&atom.name)?);
+            put_u32(&mut out, strings.insert(&atom.residue_name)?);
+            put_u32(&mut out, strings.insert_optional(atom.atom_type.as_deref())?);
+            put_u32(&mut out, atom.chain_id as u32);
+            put_u32(&mut out, atom.alt_loc as u32);
+            put_u32(&mut out, atom.ins_code as u32);
+            put_f32(&mut out, atom.b_factor);
+            put_f32(&mut out, atom.occupancy);
+            for value in [atom.charge, atom.radius, atom.mass, atom.lj_sigma, atom.lj_epsilon] {
+                put_f32(&mut out, value.unwrap_or(0.0));
+            }
+            for value in atom.velocity.unwrap_or([0.0; 3]) {
+                put_f32(&mut out, value);
+            }
+            out.extend_from_slice(&[atom.element.atomic_number(), atom_flags, atom.formal_charge as u8, 0]);
+            out.extend_from_slice(&atom.isotope.unwrap_or(0).to_le_bytes());
+            out.extend_from_slice(&[
+                match atom.chirality {
+                    Chirality::None => 0,
+                    Chirality::CounterClockwise => 1,
+                    Chirality::Clockwise => 2,
+                },
+                0,
+            ]);
+        }
+
+        for bond in &molecule.bonds {
+            put_u32(&mut out, to_u32(bond.atom1.0)?);
+            put_u32(&mut out, to_u32(bond.atom2.0)?);
+            let order = match bond.order {
+                BondOrder::Single => 1,
+                BondOrder::Double => 2,
+                BondOrder::Triple => 3,
+                BondOrder::Aromatic => 4,
+            };
+            let direction = match bond.direction {
+                BondDirection::None => 0,
+                BondDirection::Up => 1,
+                BondDirection::Down => 2,
+            };
+            put_u32(&mut out, order | direction << 8);
+        }
+
+        for model in &molecule.models {
+            put_u32(&mut out, to_u32(model.id)?);
+            put_u32(&mut out, to_u32(model.atoms.len())?);
+        }
+        for model in &molecule.models {
+            for atom in &model.atoms {
+                put_u32(&mut out, to_u32(atom.0)?);
+            }
+        }
+
+        for chain in &molecule.chains {
+            put_u32(&mut out, chain.id as u32);
+            put_u32(&mut out, chain.model.map_or(Ok(NO_MODEL), |model| to_u32(model.0))?);
+            put_u32(&mut out, to_u32(chain.residues.len())?);
+        }
+        for chain in &molecule.chains {
+            for residue in &chain.residues {
+                put_u32(&mut out, to_u32(residue.0)?);
+            }
+        }
+
+        // Residue atoms are not listed: every atom names its residue
+        for residue in &molecule.residues {
+            put_u32(&mut out, to_u32(residue.id)?);
+            put_u32(&mut out, strings.insert(&residue.name)?);
+            put_u32(&mut out, residue.ins_code as u32);
+            put_u32(&mut out, to_u32(residue.chain.0)?);
+        }
+        for atom in &molecule.atoms {
+            put_u32(&mut out, to_u32(atom.residue.0)?);
+        }
+
+        let metadata = &molecule.metadata;
+        for value in [
+            &metadata.id_code,
+            &metadata.classification,
+            &metadata.deposition_date,
+            &metadata.title,
+            &metadata.space_group,
+        ] {
+            put_u32(&mut out, strings.insert_optional(value.as_deref())?);
+        }
+        put_u32(&mut out, to_u32(metadata.properties.len())?);
+        for (name, value) in &metadata.properties {
+            put_u32(&mut out, strings.insert(name)?);
+            put_u32(&mut out, strings.insert(value)?);
+        }
+
+        let strings_offset = to_u32(out.len())?;
+        out[strings_offset_at..strings_offset_at + 4].copy_from_slice(&strings_offset.to_le_bytes());
+        out.extend_from_slice(&strings.bytes);
+
+        Ok(out)
+    }
+}
+
+/// Reader for files produced by `BinaryWriter`.
+pub struct BinaryParser;
+
+impl Default for BinaryParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinaryParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        self.parse_bytes(&fs::read(path)?)
+    }
+
+    pub fn parse_reader<R: Read>(&self, mut reader: R) -> io::Result<Molecule> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        self.parse_bytes(&bytes)
+    }
+
+    /// Parses a binary snapshot, for example from a memory-mapped file.
+    pub fn parse_bytes(&self, bytes: &[u8]) -> io::Result<Molecule> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(invalid_data("not a molecule-rs binary file"));
+        }
+
+        let mut header = Cursor::new(bytes, 4);
+        let version = header.u32()?;
+        if version != BINARY_FORMAT_VERSION {
+            return Err(invalid_data(&format!(
+                "binary format version {} is not supported (expected {})",
+                version, BINARY_FORMAT_VERSION
+            )));
+        }
+        let atom_count = header.u32()? as usize;
+        let bond_count = header.u32()? as usize;
+        let model_count = header.u32()? as usize;
+        let model_atom_count = header.u32()? as usize;
+        let chain_count = header.u32()? as usize;
+        let chain_residue_count = header.u32()? as usize;
+        let residue_count = header.u32()? as usize;
+        let flags = header.u32()?;
+        let strings_offset = header.u32()? as usize;
+
+        let strings = bytes
+            .get(strings_offset..)
+            .ok_or_else(|| invalid_data("string table offset is past the end of the file"))?;
+        let string = |offset: u32| -> io::Result<String> {
+            read_string(strings, offset)?.ok_or_else(|| invalid_data("missing required string"))
+        };
+        let optional_string = |offset: u32| read_string(strings, offset);
+
+        let mut cursor = Cursor::new(&bytes[..strings_offset], HEADER_SIZE);
+        let mut vectors = [[0.0; 3]; 3];
+        for value in vectors.as_flattened_mut() {
+            *value = cursor.f32()?;
+        }
+
+        let mut positions = Vec::with_capacity(atom_count.min(bytes.len() / 12));
+        for _ in 0..atom_count {
+            positions.push([cursor.f32()?, cursor.f32()?, cursor.f32()?]);
+        }
+
+        let mut molecule = Molecule::new();
+        molecule.unit_cell = (flags & HAS_UNIT_CELL != 0).then(|| UnitCell::new(vectors));
+
+        for position in positions {
+            let record = cursor.take(ATOM_RECORD_SIZE)?;
+            let mut fields = Cursor::new(record, 0);
+            let id = fields.u32()? as usize;
+            let residue_id = fields.u32()? as usize;
+            let name = string(fields.u32()?)?;
+            let residue_name = string(fields.u32()?)?;
+            let atom_type = optional_string(fields.u32()?)?;
+            let chain_id = fields.char()?;
+            let alt_loc = fields.char()?;
+            let ins_code = fields.char()?;
+            let b_factor = fields.f32()?;
+            let occupancy = fields.f32()?;
+            let [charge, radius, mass, lj_sigma, lj_epsilon] =
+                [fields.f32()?, fields.f32()?, fields.f32()?, fields.f32()?, fields.f32()?];
+            let velocity = [fields.f32()?, fields.f32()?, fields.f32()?];
+            let tail: [u8; 4] = fields.take(4)?.try_into().unwrap();
+            let [element, atom_flags, formal_charge, _] = tail;
+            let isotope = u16::from_le_bytes(fields.take(2)?.try_into().unwrap());
+            let chirality = match fields.take(2)?[0] {
+                0 => Chirality::None,
+                1 => Chirality::CounterClockwise,
+                2 => Chirality::Clockwise,
+                other => return Err(invalid_data(&format!("invalid chirality {}", other))),
+            };
+
+            molecule.atoms.push(Atom {
+                id,
+                name,
+                element: Element::from_atomic_number(element),
+                position,
+                residue_id,
+                chain_id,
+                b_factor,
+                occupancy,
+                residue_name,
+                alt_loc,
+                ins_code,
+                is_hetatm: atom_flags & HETATM != 0,
+                velocity: flagged(atom_flags, HAS_VELOCITY, velocity),
+                charge: flagged(atom_flags, HAS_CHARGE, charge),
+                radius: flagged(atom_flags, HAS_RADIUS, radius),
+                atom_type,
+                mass: flagged(atom_flags, HAS_MASS, mass),
+                lj_sigma: flagged(atom_flags, HAS_LJ_SIGMA, lj_sigma),
+                lj_epsilon: flagged(atom_flags, HAS_LJ_EPSILON, lj_epsilon),
+                formal_charge: formal_charge as i8,
+                isotope: (isotope != 0).then_some(isotope),
+                chirality,
+                ..Atom::default()
+            });
+        }
+
+        for _ in 0..bond_count {
+            let atom1 = cursor.atom_index(atom_count)?;
+            let atom2 = cursor.atom_index(atom_count)?;
+            let value = cursor.u32()?;
+            let order = match value & 0xff {
+                1 => BondOrder::Single,
+                2 => BondOrder::Double,
+                3 => BondOrder::Triple,
+                4 => BondOrder::Aromatic,
+                other => return Err(invalid_data(&format!("invalid bond order {}", other))),
+            };
+            let direction = match value >> 8 {
+                0 => BondDirection::None,
+                1 => BondDirection::Up,
+                2 => BondDirection::Down,
+                other => return Err(invalid_data(&format!("invalid bond direction {}", other))),
+            };
+            molecule.add_bond(atom1, atom2, order);
+            if let Some(bond) = molecule.bonds.last_mut() {
+                bond.direction = direction;
+            }
+        }
+
+        let mut model_sizes = Vec::with_capacity(model_count.min(bytes.len() / 8));
+        for _ in 0..model_count {
+            model_sizes.push((cursor.u32()? as usize, cursor.u32()? as usize));
+        }
+        if model_sizes.iter().map(|&(_, size)| size).sum::<usize>() != model_atom_count {
+            return Err(invalid_data("model sizes do not add up"));
+        }
+        for (id, size) in model_sizes {
+            let atoms = (0..size).map(|_| cursor.atom_index(atom_count)).collect::<io::Result<_>>()?;
+            molecule.models.push(Model { id, atoms });
+        }
+
+        let mut chain_records = Vec::with_capacity(chain_count.min(bytes.len() / 12));
+        for _ in 0..chain_count {
+            let id = cursor.char()?;
+            let model = match cursor.u32()? {
+                NO_MODEL => None,
+                index if (index as usize) < model_count => Some(ModelIndex(index as usize)),
+                index => return Err(invalid_data(&format!("model index {} is out of range", index))),
+            };
+            chain_records.push((id, model, cursor.u32()? as usize));
+        }
+        if chain_records.iter().map(|&(_, _, size)| size).sum::<usize>() != chain_residue_count {
+            return Err(invalid_data("chain sizes do not add up"));
+        }
+        for (id, model, size) in chain_records {
+            let residues = (0..size)
+                .map(|_| Ok(ResidueIndex(cursor.index(residue_count, "residue")?)))
+                .collect::<io::Result<_>>()?;
+            molecule.chains.push(Chain { id, residues, model });
+        }
+
+        for _ in 0..residue_count {
+            let id = cursor.u32()? as usize;
+            let name = string(cursor.u32()?)?;
+            let ins_code = cursor.char()?;
+            let chain = ChainIndex(cursor.index(chain_count, "chain")?);
+            molecule.residues.push(Residue { id, name, atoms: Vec::new(), ins_code, chain });
+        }
+        for index in 0..atom_count {
+            let residue = ResidueIndex(cursor.index(residue_count, "residue")?);
+            molecule.residues[residue.0].atoms.push(AtomIndex(index));
+            molecule.atoms[index].residue = residue;
+        }
+
+        let [id_code, classification, deposition_date, title, space_group] =
+            [cursor.u32()?, cursor.u32()?, cursor.u32()?, cursor.u32()?, cursor.u32()?];
+        let property_count = cursor.u32()? as usize;
+        let mut properties = Vec::with_capacity(property_count.min(bytes.len() / 8));
+        for _ in 0..property_count {
+            properties.push((string(cursor.u32()?)?, string(cursor.u32()?)?));
+        }
+        molecule.metadata = Metadata {
+            id_code: optional_string(id_code)?,
+            classification: optional_string(classification)?,
+            deposition_date: optional_string(deposition_date)?,
+            title: optional_string(title)?,
+            space_group: optional_string(space_group)?,
+            properties,
+        };
+
+        Ok(molecule)
+    }
+}
+
+/// 64-bit FNV-1a hash of a source file, mixed with the binary format version
+/// so that cached snapshots are invalidated when the layout changes.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    fnv1a(&[&BINARY_FORMAT_VERSION.to_le_bytes(), bytes])
+}
+
+// The key is length-prefixed so that key and source bytes cannot run into each other
+fn keyed_hash(key: &str, source: &[u8]) -> u64 {
+    fnv1a(&[&BINARY_FORMAT_VERSION.to_le_bytes(), &(key.len() as u64).to_le_bytes(), key.as_bytes(), source])
+}
+
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// Directory of binary snapshots keyed by the content hash of the source
+/// file they were parsed from, together with a caller-chosen key naming the
+/// parser and options that turned it into a `Molecule`, such as `"pdb"` or
+/// `"pdb+templates"`.
+pub struct StructureCache {
+    directory: PathBuf,
+}
+
+impl StructureCache {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path_for(&self, key: &str, source: &[u8]) -> PathBuf {
+        self.directory.join(format!("{:016x}.molb", keyed_hash(key, source)))
+    }
+
+    /// Returns the molecule cached for `source` under `key`, or `None` if there
+    /// is no usable snapshot. Unreadable or outdated snapshots count as misses.
+    pub fn load(&self, key: &str, source: &[u8]) -> Option<Molecule> {
+        BinaryParser::new().parse_file(self.path_for(key, source)).ok()
+    }
+
+    pub fn store(&self, key: &str, source: &[u8], molecule: &Molecule) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        // Write to a temporary name first so readers never see a partial file
+        let path = self.path_for(key, source);
+        let temporary = path.with_extension("tmp");
+        let mut file = File::create(&temporary)?;
+        BinaryWriter::new().write(molecule, &mut file)?;
+        file.sync_all()?;
+        fs::rename(temporary, path)
+    }
+
+    /// Loads `source` from the cache, or parses it with `parse` and stores the
+    /// result under `key` for next time. Use a different key for every parser
+    /// or set of options whose output can differ.
+    pub fn load_or_parse<F>(&self, key: &str, source: &[u8], parse: F) -> io::Result<Molecule>
+    where
+        F: FnOnce(&[u8]) -> io::Result<Molecule>,
+    {
+        if let Some(molecule) = self.load(key, source) {
+            return Ok(molecule);
+        }
+
+        let molecule = parse(source)?;
+        self.store(key, source, &molecule)?;
+        Ok(molecule)
+    }
+}
+
+#[derive(Default)]
+struct StringTable {
+    bytes: Vec<u8>,
+}
+
+impl StringTable {
+    // Strings are stored as a u32 length followed by UTF-8 bytes, padded to 4 bytes
+    fn insert(&mut self, value: &str) -> io::Result<u32> {
+        let offset = to_u32(self.bytes.len())?;
+        put_u32(&mut self.bytes, to_u32(value.len())?);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.resize(self.bytes.len().next_multiple_of(4), 0);
+        Ok(offset)
+    }
+
+    fn insert_optional(&mut self, value: Option<&str>) -> io::Result<u32> {
+        value.map_or(Ok(NO_STRING), |value| self.insert(value))
+    }
+}
+
+fn read_string(strings: &[u8], offset: u32) -> io::Result<Option<String>> {
+    if offset == NO_STRING {
+        return Ok(None);
+    }
+
+    let mut cursor = Cursor::new(strings, offset as usize);
+    let length = cursor.u32()? as usize;
+    let bytes = cursor.take(length)?;
+    String::from_utf8(bytes.to_vec())
+        .map(Some)
+        .map_err(|_| invalid_data("string table holds invalid UTF-8"))
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8], position: usize) -> Self {
+        Self { bytes, position }
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let end = self.position.checked_add(length).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| invalid_data("binary file is truncated"))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn atom_index(&mut self, atom_count: usize) -> io::Result<AtomIndex> {
+        Ok(AtomIndex(self.index(atom_count, "atom")?))
+    }
+
+    fn index(&mut self, count: usize, kind: &str) -> io::Result<usize> {
+        let index = self.u32()? as usize;
+        if index >= count {
+            return Err(invalid_data(&format!("{} index {} is out of range", kind, index)));
+        }
+        Ok(index)
+    }
+
+    fn char(&mut self) -> io::Result<char> {
+        char::from_u32(self.u32()?).ok_or_else(|| invalid_data("invalid character code"))
+    }
+}
+
+fn flagged<T>(flags: u8, flag: u8, value: T) -> Option<T> {
+    (flags & flag != 0).then_some(value)
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_f32(out: &mut Vec<u8>, value: f32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn to_u32(value: usize) -> io::Result<u32> {
+    u32::try_from(value)
+        .map_err(|_| invalid_input(&format!("{} does not fit the binary format", value)))
+}
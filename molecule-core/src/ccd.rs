@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::cif::{CifBlock, CifBlocks};
+use crate::error::invalid_data;
+use crate::structure::{AtomIndex, BondOrder, Element, Molecule, ResidueIndex};
+use crate::templates::residue_template;
+
+/// An entry of the wwPDB Chemical Component Dictionary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChemicalComponent {
+    pub id: String,
+    pub name: String,
+    pub atoms: Vec<ComponentAtom>,
+    pub bonds: Vec<ComponentBond>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentAtom {
+    pub name: String,
+    pub element: Element,
+    pub formal_charge: i8,
+    /// Set for atoms lost when the component links to another, e.g. OXT.
+    pub leaving: bool,
+}
+
+/// A bond between two named atoms. Bonds flagged aromatic in the dictionary
+/// use `BondOrder::Aromatic`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentBond {
+    pub atom1: String,
+    pub atom2: String,
+    pub order: BondOrder,
+}
+
+/// Components by their three-letter code.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentDictionary {
+    components: HashMap<String, ChemicalComponent>,
+}
+
+/// How a residue compared with its dictionary component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentReport {
+    pub residue: ResidueIndex,
+    /// Non-leaving heavy atoms of the component absent from the residue.
+    pub missing: Vec<String>,
+    /// Non-leaving hydrogens absent from the residue, kept apart because
+    /// most crystal structures have none.
+    pub missing_hydrogens: Vec<String>,
+    /// Residue atoms whose names the component does not know.
+    pub unknown: Vec<AtomIndex>,
+}
+
+impl ComponentDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ChemicalComponent> {
+        self.components.get(id)
+    }
+
+    pub fn insert(&mut self, component: ChemicalComponent) {
+        self.components.insert(component.id.clone(), component);
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+/// Parser for the Chemical Component Dictionary, either the complete
+/// `components.cif` or a directory of single-entry files such as `ATP.cif`.
+pub struct CcdParser;
+
+impl Default for CcdParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CcdParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<ComponentDictionary> {
+        let file = File::open(path)?;
+        self.parse_reader(BufReader::new(file))
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<ComponentDictionary> {
+        self.parse_reader(BufReader::new(content.as_bytes()))
+    }
+
+    /// Parses one `data_` block at a time, keeping only the components.
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<ComponentDictionary> {
+        let mut dictionary = ComponentDictionary::new();
+        for block in CifBlocks::new(reader) {
+            dictionary.insert(self.parse_block(&block?)?);
+        }
+        Ok(dictionary)
+    }
+
+    /// Loads every `.cif` file in a directory into one dictionary.
+    pub fn parse_directory<P: AsRef<Path>>(&self, directory: P) -> io::Result<ComponentDictionary> {
+        let mut dictionary = ComponentDictionary::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cif")) {
+                for component in self.parse_file(&path)?.components.into_values() {
+                    dictionary.insert(component);
+                }
+            }
+        }
+        Ok(dictionary)
+    }
+
+    fn parse_block(&self, block: &CifBlock) -> io::Result<ChemicalComponent> {
+        let id = block.item("_chem_comp.id").unwrap_or(&block.name).to_string();
+        let name = block.item("_chem_comp.name").filter(|name| *name != "?").unwrap_or("").to_string();
+
+        let mut atoms = Vec::new();
+        for row in block.rows("_chem_comp_atom") {
+            let Some(name) = row.get("atom_id") else {
+                return Err(invalid_data(&format!("component {} has an atom without atom_id", id)));
+            };
+            atoms.push(ComponentAtom {
+                name: name.clone(),
+                element: row.get("type_symbol").map_or(Element::Unknown, |symbol| Element::from_symbol(symbol)),
+                formal_charge: row.get("charge").and_then(|charge| charge.parse().ok()).unwrap_or(0),
+                leaving: row.get("pdbx_leaving_atom_flag").is_some_and(|flag| flag.eq_ignore_ascii_case("Y")),
+            });
+        }
+
+        let mut bonds = Vec::new();
+        for row in block.rows("_chem_comp_bond") {
+            let (Some(atom1), Some(atom2)) = (row.get("atom_id_1"), row.get("atom_id_2")) else {
+                return Err(invalid_data(&format!("component {} has a bond without both atoms", id)));
+            };
+            let aromatic = row.get("pdbx_aromatic_flag").is_some_and(|flag| flag.eq_ignore_ascii_case("Y"));
+            let order = match row.get("value_order").map(|order| order.to_ascii_uppercase()).as_deref() {
+                _ if aromatic => BondOrder::Aromatic,
+                Some("DOUB") => BondOrder::Double,
+                Some("TRIP") => BondOrder::Triple,
+                Some("AROM") => BondOrder::Aromatic,
+                _ => BondOrder::Single,
+            };
+            bonds.push(ComponentBond {
+                atom1: atom1.clone(),
+                atom2: atom2.clone(),
+                order,
+            });
+        }
+
+        Ok(ChemicalComponent { id, name, atoms, bonds })
+    }
+}
+
+impl Molecule {
+    /// Assigns bonds and formal charges from the dictionary to residues
+    /// without a built-in template, matching atoms by name.
+    ///
+    /// Component bonds replace whatever bonds the matched atoms had between
+    /// them within the residue, so distance-based guesses are corrected.
+    /// Returns one report per residue that had a component.
+    pub fn apply_components(&mut self, dictionary: &ComponentDictionary) -> Vec<ComponentReport> {
+        let mut reports = Vec::new();
+        let mut member_of = vec![None; self.atoms.len()];
+        let mut new_bonds = Vec::new();
+
+        for residue in 0..self.residues.len() {
+            let name = self.residues[residue].name.as_str();
+            if residue_template(name).is_some() {
+                continue;
+            }
+            let Some(component) = dictionary.get(name) else {
+                continue;
+            };
+
+            let atoms = self.residues[residue].atoms.clone();
+            let by_name: HashMap<&str, &ComponentAtom> =
+                component.atoms.iter().map(|atom| (atom.name.as_str(), atom)).collect();
+            let mut matched: HashMap<String, Vec<AtomIndex>> = HashMap::new();
+            let mut unknown = Vec::new();
+            for &atom in &atoms {
+                match by_name.get(self[atom].name.as_str()) {
+                    Some(definition) => {
+                        self[atom].formal_charge = definition.formal_charge;
+                        matched.entry(definition.name.clone()).or_default().push(atom);
+                    }
+                    None => unknown.push(atom),
+                }
+            }
+
+            for &atom in matched.values().flatten() {
+                member_of[atom.0] = Some(residue);
+            }
+            for bond in &component.bonds {
+                let (Some(atoms1), Some(atoms2)) = (matched.get(&bond.atom1), matched.get(&bond.atom2)) else {
+                    continue;
+                };
+                for &atom1 in atoms1 {
+                    for &atom2 in atoms2 {
+                        let (alt1, alt2) = (self[atom1].alt_loc, self[atom2].alt_loc);
+                        if alt1 == ' ' || alt2 == ' ' || alt1 == alt2 {
+                            new_bonds.push((atom1.min(atom2), atom1.max(atom2), bond.order));
+                        }
+                    }
+                }
+            }
+
+            let (missing_hydrogens, missing): (Vec<&ComponentAtom>, Vec<&ComponentAtom>) = component
+                .atoms
+                .iter()
+                .filter(|atom| !atom.leaving && !matched.contains_key(&atom.name))
+                .partition(|atom| atom.element == Element::H);
+            let names = |atoms: Vec<&ComponentAtom>| atoms.into_iter().map(|atom| atom.name.clone()).collect();
+            reports.push(ComponentReport {
+                residue: ResidueIndex(residue),
+                missing: names(missing),
+                missing_hydrogens: names(missing_hydrogens),
+                unknown,
+            });
+        }
+
+        // Dictionary bonds replace earlier bonds between atoms of the same residue
+        self.bonds.retain(|bond| {
+            let residue = member_of[bond.atom1.0];
+            residue.is_none() || residue != member_of[bond.atom2.0]
+        });
+        for (atom1, atom2, order) in new_bonds {
+            self.add_bond(atom1, atom2, order);
+        }
+
+        reports
+    }
+}
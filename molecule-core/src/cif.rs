@@ -0,0 +1,202 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead};
+
+use crate::error::invalid_data;
+
+/// One `data_` block of a CIF file: single items plus loops.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CifBlock {
+    pub name: String,
+    items: Vec<(String, String)>,
+    loops: Vec<CifLoop>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CifLoop {
+    tags: Vec<String>,
+    values: Vec<String>,
+}
+
+impl CifBlock {
+    /// The value of a single item such as `_chem_comp.name`.
+    pub fn item(&self, tag: &str) -> Option<&str> {
+        self.items
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(tag))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Rows of a category, keyed by the item names after the dot. Categories
+    /// with one row are often written as single items rather than a loop;
+    /// both forms are returned the same way.
+    pub fn rows(&self, category: &str) -> Vec<HashMap<String, String>> {
+        let prefix = format!("{}.", category.to_ascii_lowercase());
+        let field = |tag: &str| tag.to_ascii_lowercase().strip_prefix(&prefix).map(str::to_string);
+
+        if let Some(table) = self.loops.iter().find(|table| table.tags.first().is_some_and(|tag| field(tag).is_some())) {
+            let fields: Vec<String> = table.tags.iter().map(|tag| field(tag).unwrap_or_default()).collect();
+            return table
+                .values
+                .chunks(fields.len())
+                .map(|row| fields.iter().cloned().zip(row.iter().cloned()).collect())
+                .collect();
+        }
+
+        let row: HashMap<String, String> = self
+            .items
+            .iter()
+            .filter_map(|(tag, value)| field(tag).map(|name| (name, value.clone())))
+            .collect();
+        if row.is_empty() { Vec::new() } else { vec![row] }
+    }
+}
+
+/// Reads a CIF file one `data_` block at a time, so a large dictionary such
+/// as `components.cif` never has to be held in memory as a whole. Handles
+/// loops, quoted values and semicolon-delimited text fields; save frames and
+/// nested loops are not supported.
+pub(crate) struct CifBlocks<R> {
+    lines: io::Lines<R>,
+    tokens: VecDeque<Token>,
+}
+
+impl<R: BufRead> CifBlocks<R> {
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines(), tokens: VecDeque::new() }
+    }
+
+    fn next_block(&mut self) -> io::Result<Option<CifBlock>> {
+        let Some(token) = self.next_if(|_| true)? else {
+            return Ok(None);
+        };
+        if !token.is_data() {
+            return Err(invalid_data(&format!("'{}' appears before any data_ block", token.text)));
+        }
+        let mut block = CifBlock {
+            name: token.text[5..].to_string(),
+            ..CifBlock::default()
+        };
+
+        // The block ends where the next one starts, which stays queued
+        while let Some(token) = self.next_if(|next| !next.is_data())? {
+            let keyword = !token.quoted;
+            if keyword && token.text.eq_ignore_ascii_case("loop_") {
+                let mut table = CifLoop::default();
+                while let Some(tag) = self.next_if(Token::is_tag)? {
+                    table.tags.push(tag.text);
+                }
+                while let Some(value) = self.next_if(Token::is_value)? {
+                    table.values.push(value.text);
+                }
+                if table.tags.is_empty() || table.values.len() % table.tags.len() != 0 {
+                    return Err(invalid_data("loop_ values do not fill whole rows"));
+                }
+                block.loops.push(table);
+            } else if token.is_tag() {
+                match self.next_if(Token::is_value)? {
+                    Some(value) => block.items.push((token.text, value.text)),
+                    None => return Err(invalid_data(&format!("item {} has no value", token.text))),
+                }
+            } else {
+                return Err(invalid_data(&format!("unexpected value '{}'", token.text)));
+            }
+        }
+
+        Ok(Some(block))
+    }
+
+    // Takes the next token if it passes `accept`, reading lines as needed
+    fn next_if(&mut self, accept: impl Fn(&Token) -> bool) -> io::Result<Option<Token>> {
+        while self.tokens.is_empty() {
+            match self.lines.next() {
+                Some(line) => self.tokenize(line?)?,
+                None => return Ok(None),
+            }
+        }
+        if self.tokens.front().is_some_and(accept) {
+            Ok(self.tokens.pop_front())
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn tokenize(&mut self, line: String) -> io::Result<()> {
+        // Text fields run from a line starting with ';' to the next such line
+        if let Some(first) = line.strip_prefix(';') {
+            let mut text = first.to_string();
+            loop {
+                match self.lines.next().transpose()? {
+                    Some(next) if next.starts_with(';') => break,
+                    Some(next) => {
+                        text.push('\n');
+                        text.push_str(&next);
+                    }
+                    None => return Err(invalid_data("unterminated text field")),
+                }
+            }
+            self.tokens.push_back(Token { text: text.trim().to_string(), quoted: true });
+            return Ok(());
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+            } else if c == '#' {
+                break;
+            } else if c == '\'' || c == '"' {
+                // A quote only closes when followed by whitespace, so O5' style names survive
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && !(chars[end] == c && chars.get(end + 1).is_none_or(|next| next.is_whitespace())) {
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    return Err(invalid_data(&format!("unterminated quote in line '{}'", line)));
+                }
+                self.tokens.push_back(Token { text: chars[start..end].iter().collect(), quoted: true });
+                i = end + 1;
+            } else {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                self.tokens.push_back(Token { text: chars[start..i].iter().collect(), quoted: false });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for CifBlocks<R> {
+    type Item = io::Result<CifBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+impl Token {
+    fn is_data(&self) -> bool {
+        !self.quoted && self.text.get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("data_"))
+    }
+
+    fn is_tag(&self) -> bool {
+        !self.quoted && self.text.starts_with('_')
+    }
+
+    fn is_value(&self) -> bool {
+        self.quoted
+            || !(self.text.starts_with('_')
+                || self.text.eq_ignore_ascii_case("loop_")
+                || self.is_data())
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::structure::{ChainIndex, Molecule};
+use crate::view::{ChainView, ResidueView};
+
+/// What a residue, or a chain, is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResidueKind {
+    Protein,
+    Dna,
+    Rna,
+    Water,
+    Ion,
+    Carbohydrate,
+    Ligand,
+}
+
+impl ResidueKind {
+    /// Protein and nucleic acid residues, which link into chains.
+    pub fn is_polymer(self) -> bool {
+        matches!(self, ResidueKind::Protein | ResidueKind::Dna | ResidueKind::Rna)
+    }
+}
+
+/// Chains that share a kind and a residue sequence, e.g. the copies of a
+/// homodimer or the same chain across models.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entity {
+    pub kind: ResidueKind,
+    pub sequence: Vec<String>,
+    pub chains: Vec<ChainIndex>,
+}
+
+// Standard amino acids, common modified ones and force-field protonation variants
+const PROTEIN: &[&str] = &[
+    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS", "ILE", "LEU", "LYS", "MET", "PHE", "PRO", "SER",
+    "THR", "TRP", "TYR", "VAL", "SEC", "PYL", "MSE", "HYP", "SEP", "TPO", "PTR", "MLY", "CSO", "KCX", "ASX", "GLX",
+    "UNK", "HID", "HIE", "HIP", "HSD", "HSE", "HSP", "CYX", "CYM", "ASH", "GLH", "LYN", "ACE", "NME", "NH2",
+];
+const DNA: &[&str] = &["DA", "DC", "DG", "DT", "DI", "DU", "DA5", "DC5", "DG5", "DT5", "DA3", "DC3", "DG3", "DT3", "DN"];
+const RNA: &[&str] = &[
+    "A", "C", "G", "U", "I", "N", "PSU", "RA", "RC", "RG", "RU", "A5", "C5", "G5", "U5", "A3", "C3", "G3", "U3",
+];
+const WATER: &[&str] = &["HOH", "WAT", "DOD", "H2O", "SOL", "TIP", "TIP3", "TIP4", "TIP5", "SPC", "T3P", "T4P"];
+const IONS: &[&str] = &[
+    "LI", "NA", "K", "RB", "CS", "MG", "CA", "SR", "BA", "ZN", "FE", "FE2", "MN", "MN3", "CO", "3CO", "NI", "CU",
+    "CU1", "CD", "HG", "PB", "AL", "GA", "AG", "AU", "PT", "F", "CL", "BR", "IOD", "NA+", "K+", "CL-", "SOD", "POT",
+    "CLA", "CAL",
+];
+const CARBOHYDRATE: &[&str] = &[
+    "GLC", "BGC", "NAG", "NDG", "MAN", "BMA", "GAL", "GLA", "FUC", "FUL", "SIA", "XYS", "XYP", "FRU", "A2G", "NGA",
+    "GCS", "RIB", "ARA", "RAM", "GCU", "BDP", "IDS", "SGN",
+];
+
+/// Classifies a residue by name against the standard-residue tables.
+/// Anything not listed is a ligand.
+pub fn classify_residue(name: &str) -> ResidueKind {
+    let name = name.trim();
+    if PROTEIN.contains(&name) {
+        ResidueKind::Protein
+    } else if DNA.contains(&name) {
+        ResidueKind::Dna
+    } else if RNA.contains(&name) {
+        ResidueKind::Rna
+    } else if WATER.contains(&name) {
+        ResidueKind::Water
+    } else if IONS.contains(&name) {
+        ResidueKind::Ion
+    } else if CARBOHYDRATE.contains(&name) {
+        ResidueKind::Carbohydrate
+    } else {
+        ResidueKind::Ligand
+    }
+}
+
+impl ResidueView<'_> {
+    pub fn kind(self) -> ResidueKind {
+        classify_residue(&self.get().name)
+    }
+}
+
+impl ChainView<'_> {
+    /// The most common polymer kind among the chain's residues, so ligands
+    /// and waters listed under a protein chain do not change its kind.
+    /// Chains without polymer residues take the most common kind overall.
+    pub fn kind(self) -> ResidueKind {
+        let kinds: Vec<ResidueKind> = self.residues().map(ResidueView::kind).collect();
+        let polymer: Vec<ResidueKind> = kinds.iter().copied().filter(|kind| kind.is_polymer()).collect();
+
+        most_common(if polymer.is_empty() { &kinds } else { &polymer }).unwrap_or(ResidueKind::Ligand)
+    }
+
+    /// Residue names of the polymer part of the chain, or of the whole chain
+    /// when it has no polymer residues.
+    pub fn sequence(self) -> Vec<String> {
+        let polymer = self.kind().is_polymer();
+        self.residues()
+            .filter(|residue| !polymer || residue.kind().is_polymer())
+            .map(|residue| residue.get().name.clone())
+            .collect()
+    }
+}
+
+impl Molecule {
+    /// Groups chains with the same kind and sequence, in order of first
+    /// appearance.
+    pub fn entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = Vec::new();
+        let mut lookup: HashMap<(ResidueKind, Vec<String>), usize> = HashMap::new();
+
+        for chain in self.chains() {
+            let key = (chain.kind(), chain.sequence());
+            match lookup.get(&key) {
+                Some(&entity) => entities[entity].chains.push(chain.index()),
+                None => {
+                    lookup.insert(key.clone(), entities.len());
+                    entities.push(Entity {
+                        kind: key.0,
+                        sequence: key.1,
+                        chains: vec![chain.index()],
+                    });
+                }
+            }
+        }
+
+        entities
+    }
+}
+
+// Ties go to the kind seen first
+fn most_common(kinds: &[ResidueKind]) -> Option<ResidueKind> {
+    let mut counts: Vec<(ResidueKind, usize)> = Vec::new();
+    for &kind in kinds {
+        match counts.iter_mut().find(|(seen, _)| *seen == kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((kind, 1)),
+        }
+    }
+    counts.iter().rev().max_by_key(|(_, count)| *count).map(|(kind, _)| *kind)
+}
@@ -0,0 +1,316 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::error::{invalid_data, invalid_input};
+use crate::structure::UnitCell;
+use crate::trajectory::Frame;
+
+// CHARMM stores the timestep in AKMA time units
+const AKMA_TO_PS: f32 = 0.048_888_21;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn i32(self, bytes: &[u8]) -> i32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Endian::Little => i32::from_le_bytes(bytes),
+            Endian::Big => i32::from_be_bytes(bytes),
+        }
+    }
+
+    fn f32(self, bytes: &[u8]) -> f32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Endian::Little => f32::from_le_bytes(bytes),
+            Endian::Big => f32::from_be_bytes(bytes),
+        }
+    }
+
+    fn f64(self, bytes: &[u8]) -> f64 {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(&bytes[..8]);
+        match self {
+            Endian::Little => f64::from_le_bytes(buffer),
+            Endian::Big => f64::from_be_bytes(buffer),
+        }
+    }
+}
+
+/// Reader for CHARMM/NAMD/OpenMM DCD trajectories.
+///
+/// Both byte orders are detected from the first record marker. Frames are
+/// located by offset, so any frame can be read without scanning the file.
+pub struct DcdReader<R> {
+    reader: R,
+    endian: Endian,
+    title: Vec<String>,
+    n_atoms: usize,
+    n_frames: usize,
+    first_step: i64,
+    step_interval: i64,
+    timestep: Option<f32>,
+    has_unit_cell: bool,
+    has_fourth_dimension: bool,
+    free_atoms: Option<Vec<usize>>,
+    fixed_positions: Option<Vec<[f32; 3]>>,
+    header_size: u64,
+}
+
+impl DcdReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> DcdReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut marker = [0u8; 4];
+        reader.read_exact(&mut marker)?;
+        let endian = if i32::from_le_bytes(marker) == 84 {
+            Endian::Little
+        } else if i32::from_be_bytes(marker) == 84 {
+            Endian::Big
+        } else {
+            return Err(invalid_data("not a DCD file: unexpected first record marker"));
+        };
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let header = read_record(&mut reader, endian, 84)?;
+        if header.len() != 84 || &header[0..4] != b"CORD" {
+            return Err(invalid_data("not a DCD file: missing CORD signature"));
+        }
+        let control: Vec<i32> = header[4..84].chunks(4).map(|bytes| endian.i32(bytes)).collect();
+
+        // A non-zero CHARMM version marks the CHARMM flavour; X-PLOR files
+        // store the timestep as a double and have no extra blocks.
+        let is_charmm = control[19] != 0;
+        let delta = if is_charmm {
+            endian.f32(&header[40..44])
+        } else {
+            endian.f64(&header[40..48]) as f32
+        };
+        let has_unit_cell = is_charmm && control[10] != 0;
+        let has_fourth_dimension = is_charmm && control[11] != 0;
+        let fixed_count = control[8].max(0) as usize;
+
+        let title_record = read_record(&mut reader, endian, file_size as usize)?;
+        if title_record.len() < 4 {
+            return Err(invalid_data("truncated title record"));
+        }
+        let title_count = endian.i32(&title_record[0..4]).max(0) as usize;
+        let title = title_record[4..]
+            .chunks(80)
+            .take(title_count)
+            .map(|line| String::from_utf8_lossy(line).trim_end_matches(['\0', ' ']).to_string())
+            .collect();
+
+        let atom_record = read_record(&mut reader, endian, 4)?;
+        if atom_record.len() != 4 {
+            return Err(invalid_data("malformed atom count record"));
+        }
+        let n_atoms = endian.i32(&atom_record[0..4]).max(0) as usize;
+
+        let free_atoms = if fixed_count > 0 {
+            let limit = n_atoms.saturating_mul(4).min(file_size as usize);
+            let record = read_record(&mut reader, endian, limit)?;
+            let indices = record
+                .chunks(4)
+                .map(|bytes| endian.i32(bytes) as usize)
+                .map(|index| index.checked_sub(1).filter(|&i| i < n_atoms))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid_data("free atom index out of range"))?;
+            Some(indices)
+        } else {
+            None
+        };
+
+        let header_size = reader.stream_position()?;
+
+        let mut dcd = Self {
+            reader,
+            endian,
+            title,
+            n_atoms,
+            n_frames: 0,
+            first_step: control[1] as i64,
+            step_interval: control[2] as i64,
+            timestep: (delta > 0.0).then_some(delta * AKMA_TO_PS),
+            has_unit_cell,
+            has_fourth_dimension,
+            free_atoms,
+            fixed_positions: None,
+            header_size,
+        };
+
+        // The frame count in the header is unreliable for files that were still
+        // being written, so it is derived from the file size instead.
+        let first_frame_size = dcd.frame_size(dcd.n_atoms);
+        let frame_size = dcd.frame_size(dcd.free_atom_count());
+        dcd.n_frames = if frame_size == 0 || file_size < header_size + first_frame_size {
+            0
+        } else {
+            1 + ((file_size - header_size - first_frame_size) / frame_size) as usize
+        };
+
+        Ok(dcd)
+    }
+
+    pub fn n_atoms(&self) -> usize {
+        self.n_atoms
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    pub fn title(&self) -> &[String] {
+        &self.title
+    }
+
+    pub fn has_unit_cell(&self) -> bool {
+        self.has_unit_cell
+    }
+
+    /// Number of atoms whose coordinates stay fixed after the first frame.
+    pub fn fixed_atom_count(&self) -> usize {
+        self.n_atoms - self.free_atom_count()
+    }
+
+    /// Reads the frame at `index` (0-based).
+    pub fn read_frame(&mut self, index: usize) -> io::Result<Frame> {
+        if index >= self.n_frames {
+            return Err(invalid_input(&format!("frame {} out of range ({} frames)", index, self.n_frames)));
+        }
+
+        // Fixed atoms are only stored in the first frame
+        if index > 0 && self.free_atoms.is_some() && self.fixed_positions.is_none() {
+            let first = self.read_frame(0)?;
+            self.fixed_positions = Some(first.positions);
+        }
+
+        let offset = if index == 0 {
+            self.header_size
+        } else {
+            self.header_size
+                + self.frame_size(self.n_atoms)
+                + (index as u64 - 1) * self.frame_size(self.free_atom_count())
+        };
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let unit_cell = if self.has_unit_cell {
+            let record = read_record(&mut self.reader, self.endian, 48)?;
+            Some(self.parse_unit_cell(&record)?)
+        } else {
+            None
+        };
+
+        let count = if index == 0 { self.n_atoms } else { self.free_atom_count() };
+        let x = self.read_coordinates(count)?;
+        let y = self.read_coordinates(count)?;
+        let z = self.read_coordinates(count)?;
+        if self.has_fourth_dimension {
+            read_record(&mut self.reader, self.endian, 4 * count)?;
+        }
+
+        let positions = match (&self.free_atoms, &self.fixed_positions) {
+            (Some(free_atoms), Some(fixed_positions)) if index > 0 => {
+                let mut positions = fixed_positions.clone();
+                for (k, &atom) in free_atoms.iter().enumerate() {
+                    positions[atom] = [x[k], y[k], z[k]];
+                }
+                positions
+            }
+            _ => (0..count).map(|i| [x[i], y[i], z[i]]).collect(),
+        };
+
+        let step = self.first_step + index as i64 * self.step_interval;
+
+        Ok(Frame {
+            index,
+            step: Some(step),
+            time: self.timestep.map(|dt| step as f32 * dt),
+            positions,
+            velocities: None,
+            unit_cell,
+        })
+    }
+
+    /// Iterates over all frames in file order.
+    pub fn frames(&mut self) -> impl Iterator<Item = io::Result<Frame>> + '_ {
+        (0..self.n_frames).map(move |index| self.read_frame(index))
+    }
+
+    fn free_atom_count(&self) -> usize {
+        self.free_atoms.as_ref().map_or(self.n_atoms, Vec::len)
+    }
+
+    fn frame_size(&self, atom_count: usize) -> u64 {
+        let coordinate_record = 4 * atom_count as u64 + 8;
+        let dimensions = if self.has_fourth_dimension { 4 } else { 3 };
+        let cell_record = if self.has_unit_cell { 48 + 8 } else { 0 };
+
+        cell_record + dimensions * coordinate_record
+    }
+
+    fn read_coordinates(&mut self, count: usize) -> io::Result<Vec<f32>> {
+        let record = read_record(&mut self.reader, self.endian, 4 * count)?;
+        if record.len() != 4 * count {
+            return Err(invalid_data("coordinate record has the wrong size"));
+        }
+
+        Ok(record.chunks(4).map(|bytes| self.endian.f32(bytes)).collect())
+    }
+
+    fn parse_unit_cell(&self, record: &[u8]) -> io::Result<UnitCell> {
+        if record.len() != 48 {
+            return Err(invalid_data("unit cell record has the wrong size"));
+        }
+        // Stored as A, gamma, B, beta, alpha, C
+        let values: Vec<f32> = record.chunks(8).map(|bytes| self.endian.f64(bytes) as f32).collect();
+        let (a, b, c) = (values[0], values[2], values[5]);
+        let mut angles = [values[4], values[3], values[1]];
+
+        // Newer CHARMM and NAMD versions store the cosines of the angles
+        if angles.iter().all(|angle| angle.abs() <= 1.0) {
+            angles = angles.map(|cosine| cosine.acos().to_degrees());
+        }
+
+        Ok(UnitCell::from_parameters(a, b, c, angles[0], angles[1], angles[2]))
+    }
+}
+
+// Reads one Fortran unformatted record, checking the leading and trailing length markers.
+// Lengths above `limit` are rejected, and the buffer only grows with the bytes actually read.
+fn read_record<R: Read>(reader: &mut R, endian: Endian, limit: usize) -> io::Result<Vec<u8>> {
+    let mut marker = [0u8; 4];
+    reader.read_exact(&mut marker)?;
+    let length = endian.i32(&marker);
+    if length < 0 {
+        return Err(invalid_data("negative record length"));
+    }
+    if length as usize > limit {
+        return Err(invalid_data(&format!("record of {} bytes where at most {} were expected", length, limit)));
+    }
+
+    let mut record = Vec::new();
+    reader.by_ref().take(length as u64).read_to_end(&mut record)?;
+    if record.len() != length as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated DCD record"));
+    }
+
+    reader.read_exact(&mut marker)?;
+    if endian.i32(&marker) != length {
+        return Err(invalid_data("mismatched record markers"));
+    }
+
+    Ok(record)
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use std::io;
+
+use crate::error::invalid_input;
+use crate::structure::{AtomIndex, Bond, ChainIndex, Element, ModelIndex, Molecule, ResidueIndex};
+
+// Replacement chain identifiers, in the order they are handed out by `merge`
+const CHAIN_IDS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+impl Molecule {
+    /// Removes atoms together with their bonds. Residues and chains left
+    /// without atoms are removed too; models are kept even when emptied.
+    /// Remaining atoms, residues and chains keep their relative order, so
+    /// indices taken before the call are invalidated.
+    pub fn remove_atoms(&mut self, atoms: &[AtomIndex]) -> io::Result<()> {
+        let mut removed = vec![false; self.atoms.len()];
+        for index in atoms {
+            match removed.get_mut(index.0) {
+                Some(flag) => *flag = true,
+                None => {
+                    return Err(invalid_input(&format!(
+                        "atom index {} is outside the molecule ({} atoms)",
+                        index.0,
+                        self.atoms.len()
+                    )))
+                }
+            }
+        }
+
+        let atom_map = compact_map(&removed);
+        retain_kept(&mut self.atoms, &removed);
+        self.bonds.retain_mut(|bond| match (atom_map[bond.atom1.0], atom_map[bond.atom2.0]) {
+            (Some(atom1), Some(atom2)) => {
+                bond.atom1 = AtomIndex(atom1);
+                bond.atom2 = AtomIndex(atom2);
+                true
+            }
+            _ => false,
+        });
+        for model in &mut self.models {
+            model.atoms = model.atoms.iter().filter_map(|atom| atom_map[atom.0].map(AtomIndex)).collect();
+        }
+
+        for residue in &mut self.residues {
+            residue.atoms = residue.atoms.iter().filter_map(|atom| atom_map[atom.0].map(AtomIndex)).collect();
+        }
+        let empty: Vec<bool> = self.residues.iter().map(|residue| residue.atoms.is_empty()).collect();
+        let residue_map = compact_map(&empty);
+        retain_kept(&mut self.residues, &empty);
+        for atom in &mut self.atoms {
+            atom.residue = ResidueIndex(residue_map[atom.residue.0].expect("atom belongs to a kept residue"));
+        }
+
+        for chain in &mut self.chains {
+            chain.residues = chain
+                .residues
+                .iter()
+                .filter_map(|residue| residue_map[residue.0].map(ResidueIndex))
+                .collect();
+        }
+        let empty: Vec<bool> = self.chains.iter().map(|chain| chain.residues.is_empty()).collect();
+        let chain_map = compact_map(&empty);
+        retain_kept(&mut self.chains, &empty);
+        for residue in &mut self.residues {
+            residue.chain = ChainIndex(chain_map[residue.chain.0].expect("residue belongs to a kept chain"));
+        }
+
+        Ok(())
+    }
+
+    /// Changes a chain identifier, updating the chain's atoms. Fails if
+    /// another chain of the same model already uses `id`, since the two
+    /// chains could no longer be told apart.
+    pub fn rename_chain(&mut self, chain: ChainIndex, id: char) -> io::Result<()> {
+        let model = self.chains[chain.0].model;
+        let taken = self
+            .chains
+            .iter()
+            .enumerate()
+            .any(|(index, other)| index != chain.0 && other.model == model && other.id == id);
+        if taken {
+            return Err(invalid_input(&format!("chain '{}' already exists in this model", id)));
+        }
+
+        self.chains[chain.0].id = id;
+        for residue in self.chains[chain.0].residues.clone() {
+            for &atom in &self.residues[residue.0].atoms {
+                self.atoms[atom.0].chain_id = id;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes a residue name, updating the residue's atoms.
+    pub fn rename_residue(&mut self, residue: ResidueIndex, name: &str) {
+        let residue = &mut self.residues[residue.0];
+        residue.name = name.to_string();
+        for &atom in &residue.atoms {
+            self.atoms[atom.0].residue_name = name.to_string();
+        }
+    }
+
+    /// Changes a residue number and insertion code, updating the residue's atoms.
+    pub fn renumber_residue(&mut self, residue: ResidueIndex, id: usize, ins_code: char) {
+        let residue = &mut self.residues[residue.0];
+        residue.id = id;
+        residue.ins_code = ins_code;
+        for &atom in &residue.atoms {
+            self.atoms[atom.0].residue_id = id;
+            self.atoms[atom.0].ins_code = ins_code;
+        }
+    }
+
+    /// Numbers the residues of a chain consecutively from `start`, clearing
+    /// insertion codes.
+    pub fn renumber_chain(&mut self, chain: ChainIndex, start: usize) {
+        for (offset, residue) in self.chains[chain.0].residues.clone().into_iter().enumerate() {
+            self.renumber_residue(residue, start + offset, ' ');
+        }
+    }
+
+    pub fn set_element(&mut self, atom: AtomIndex, element: Element) {
+        self.atoms[atom.0].element = element;
+    }
+
+    pub fn set_position(&mut self, atom: AtomIndex, position: [f32; 3]) {
+        self.atoms[atom.0].position = position;
+    }
+
+    /// Appends the atoms, bonds and hierarchy of `other`.
+    ///
+    /// Atoms of a model join the model with the same MODEL serial, which is
+    /// created if missing; atoms outside any model stay outside. Chain
+    /// identifiers of `other` that are already used here are replaced by the
+    /// first free identifier, and the replacements are returned as
+    /// `(old, new)` pairs. Metadata and the unit cell of `self` are kept.
+    pub fn merge(&mut self, other: &Molecule) -> io::Result<Vec<(char, char)>> {
+        let mut used: HashSet<char> = self.chains.iter().map(|chain| chain.id).collect();
+        let mut renames = Vec::new();
+        let mut kept = HashSet::new();
+        for chain in &other.chains {
+            if kept.contains(&chain.id) || renames.iter().any(|&(old, _)| old == chain.id) {
+                continue;
+            }
+            if used.insert(chain.id) {
+                kept.insert(chain.id);
+                continue;
+            }
+            let Some(id) = CHAIN_IDS.chars().find(|id| !used.contains(id)) else {
+                return Err(invalid_input(&format!("no free chain identifier for chain '{}'", chain.id)));
+            };
+            used.insert(id);
+            renames.push((chain.id, id));
+        }
+
+        let previous_model = self.current_model;
+        let offset = self.atoms.len();
+        for atom in other.atoms.iter() {
+            let model = other.chains[other.residues[atom.residue.0].chain.0].model;
+            self.current_model = model.map(|model| self.model_with_id(other[model].id));
+
+            let mut atom = atom.clone();
+            if let Some(&(_, id)) = renames.iter().find(|&&(old, _)| old == atom.chain_id) {
+                atom.chain_id = id;
+            }
+            self.add_atom(atom);
+        }
+        self.current_model = previous_model;
+
+        for bond in &other.bonds {
+            self.bonds.push(Bond {
+                atom1: AtomIndex(bond.atom1.0 + offset),
+                atom2: AtomIndex(bond.atom2.0 + offset),
+                ..bond.clone()
+            });
+        }
+
+        Ok(renames)
+    }
+
+    fn model_with_id(&mut self, id: usize) -> ModelIndex {
+        match self.models.iter().position(|model| model.id == id) {
+            Some(index) => ModelIndex(index),
+            None => {
+                self.start_model(id);
+                ModelIndex(self.models.len() - 1)
+            }
+        }
+    }
+}
+
+fn retain_kept<T>(items: &mut Vec<T>, removed: &[bool]) {
+    let mut removed = removed.iter();
+    items.retain(|_| !removed.next().copied().unwrap_or(false));
+}
+
+// Maps old positions to new ones after dropping the flagged entries
+fn compact_map(removed: &[bool]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    removed
+        .iter()
+        .map(|&removed| {
+            if removed {
+                None
+            } else {
+                next += 1;
+                Some(next - 1)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,11 @@
+use std::io;
+
+// Malformed file contents
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Arguments or structures a caller should not have passed
+pub(crate) fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::error::invalid_input;
+use crate::structure::{AtomIndex, Bond, ModelIndex, Molecule};
+
+/// Controls how `Molecule::extract` numbers the atoms and residues it copies.
+/// By default the original serial and residue numbers are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractOptions {
+    renumber_atoms: bool,
+    renumber_residues: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self {
+            renumber_atoms: false,
+            renumber_residues: false,
+        }
+    }
+
+    /// Numbers atom serials from 1 within each model.
+    pub fn renumber_atoms(mut self, renumber: bool) -> Self {
+        self.renumber_atoms = renumber;
+        self
+    }
+
+    /// Numbers residues from 1 within each chain and clears insertion codes.
+    pub fn renumber_residues(mut self, renumber: bool) -> Self {
+        self.renumber_residues = renumber;
+        self
+    }
+}
+
+impl Molecule {
+    /// Copies the given atoms into a standalone molecule, in file order.
+    ///
+    /// Bonds with both atoms selected are kept, as are the residue, chain and
+    /// model hierarchy, the unit cell and the metadata. Duplicate indices are
+    /// ignored; an index outside the molecule is an error.
+    pub fn extract(&self, atoms: &[AtomIndex], options: ExtractOptions) -> io::Result<Molecule> {
+        if let Some(index) = atoms.iter().find(|index| index.0 >= self.atoms.len()) {
+            return Err(invalid_input(&format!(
+                "atom index {} is outside the molecule ({} atoms)",
+                index.0, self.atoms.len()
+            )));
+        }
+
+        let mut selected = atoms.to_vec();
+        selected.sort_unstable();
+        selected.dedup();
+
+        let mut extracted = Molecule::new();
+        extracted.unit_cell = self.unit_cell;
+        extracted.metadata = self.metadata.clone();
+
+        let mut mapping = vec![None; self.atoms.len()];
+        let mut serials: HashMap<Option<ModelIndex>, usize> = HashMap::new();
+        for &index in &selected {
+            // Models whose atoms come in several runs resume their model
+            let model = self.atom(index).model().index();
+            extracted.current_model = model.map(|model| {
+                let id = self[model].id;
+                match extracted.models.iter().position(|existing| existing.id == id) {
+                    Some(existing) => ModelIndex(existing),
+                    None => {
+                        extracted.start_model(id);
+                        ModelIndex(extracted.models.len() - 1)
+                    }
+                }
+            });
+
+            let mut atom = self[index].clone();
+            let serial = serials.entry(model).or_default();
+            *serial += 1;
+            if options.renumber_atoms {
+                atom.id = *serial;
+            }
+            mapping[index.0] = Some(extracted.add_atom(atom));
+        }
+        extracted.end_model();
+
+        for bond in &self.bonds {
+            if let (Some(atom1), Some(atom2)) = (mapping[bond.atom1.0], mapping[bond.atom2.0]) {
+                extracted.bonds.push(Bond { atom1, atom2, ..bond.clone() });
+            }
+        }
+
+        if options.renumber_residues {
+            for chain in 0..extracted.chains.len() {
+                for (number, residue) in extracted.chains[chain].residues.clone().into_iter().enumerate() {
+                    let residue = &mut extracted.residues[residue.0];
+                    residue.id = number + 1;
+                    residue.ins_code = ' ';
+                    for &atom in &residue.atoms {
+                        extracted.atoms[atom.0].residue_id = number + 1;
+                        extracted.atoms[atom.0].ins_code = ' ';
+                    }
+                }
+            }
+        }
+
+        Ok(extracted)
+    }
+}
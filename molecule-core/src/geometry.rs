@@ -0,0 +1,43 @@
+// Small vector helpers on plain `[f32; 3]` positions
+
+pub(crate) fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+// Zero-length vectors get an arbitrary unit direction rather than NaNs
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    if length < 1e-6 { [1.0, 0.0, 0.0] } else { scale(a, 1.0 / length) }
+}
+
+pub(crate) fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = sub(a, b);
+    dot(d, d).sqrt()
+}
+
+// Dihedral angle a-b-c-d in degrees, from -180 to 180
+pub(crate) fn torsion(a: [f32; 3], b: [f32; 3], c: [f32; 3], d: [f32; 3]) -> f32 {
+    let (b1, b2, b3) = (sub(b, a), sub(c, b), sub(d, c));
+    let (n1, n2) = (cross(b1, b2), cross(b2, b3));
+    let m = cross(n1, b2);
+    let length = dot(b2, b2).sqrt();
+    let x = dot(n1, n2);
+    let y = dot(m, n2) / length;
+    y.atan2(x).to_degrees()
+}
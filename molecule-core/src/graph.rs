@@ -0,0 +1,319 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::structure::{AtomIndex, BondIndex, Molecule};
+
+/// Adjacency view of a molecule's bonds, built once by `Molecule::graph`.
+/// The graph is a snapshot: it does not follow later edits to the molecule.
+#[derive(Debug, Clone, Default)]
+pub struct MolecularGraph {
+    adjacency: Vec<Vec<(AtomIndex, BondIndex)>>,
+}
+
+impl Molecule {
+    pub fn graph(&self) -> MolecularGraph {
+        let mut adjacency = vec![Vec::new(); self.atoms.len()];
+        for (index, bond) in self.bonds.iter().enumerate() {
+            let (atom1, atom2) = (bond.atom1, bond.atom2);
+            if atom1 == atom2 || atom1.0 >= adjacency.len() || atom2.0 >= adjacency.len() {
+                continue;
+            }
+            // Duplicate bonds would otherwise show up as two-membered rings
+            if adjacency[atom1.0].iter().any(|&(other, _)| other == atom2) {
+                continue;
+            }
+            adjacency[atom1.0].push((atom2, BondIndex(index)));
+            adjacency[atom2.0].push((atom1, BondIndex(index)));
+        }
+        MolecularGraph { adjacency }
+    }
+}
+
+impl MolecularGraph {
+    /// Number of atoms, bonded or not.
+    pub fn len(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adjacency.is_empty()
+    }
+
+    pub fn neighbors(&self, atom: AtomIndex) -> impl Iterator<Item = AtomIndex> + '_ {
+        self.adjacency[atom.0].iter().map(|&(other, _)| other)
+    }
+
+    /// Neighbors together with the bond leading to each.
+    pub fn edges(&self, atom: AtomIndex) -> &[(AtomIndex, BondIndex)] {
+        &self.adjacency[atom.0]
+    }
+
+    pub fn degree(&self, atom: AtomIndex) -> usize {
+        self.adjacency[atom.0].len()
+    }
+
+    pub fn bond_between(&self, atom1: AtomIndex, atom2: AtomIndex) -> Option<BondIndex> {
+        self.adjacency[atom1.0].iter().find(|&&(other, _)| other == atom2).map(|&(_, bond)| bond)
+    }
+
+    /// Connected fragments, each sorted, ordered by their first atom.
+    /// Unbonded atoms such as ions and waters without hydrogens form
+    /// fragments of their own.
+    pub fn fragments(&self) -> Vec<Vec<AtomIndex>> {
+        let mut fragment_of = vec![usize::MAX; self.len()];
+        let mut fragments = Vec::new();
+
+        for start in 0..self.len() {
+            if fragment_of[start] != usize::MAX {
+                continue;
+            }
+            let mut fragment = vec![AtomIndex(start)];
+            fragment_of[start] = fragments.len();
+            let mut next = 0;
+            while next < fragment.len() {
+                let atom = fragment[next];
+                next += 1;
+                for other in self.neighbors(atom) {
+                    if fragment_of[other.0] == usize::MAX {
+                        fragment_of[other.0] = fragments.len();
+                        fragment.push(other);
+                    }
+                }
+            }
+            fragment.sort_unstable();
+            fragments.push(fragment);
+        }
+
+        fragments
+    }
+
+    /// Number of bonds on the shortest path between two atoms, or `None`
+    /// when they are in different fragments.
+    pub fn distance(&self, from: AtomIndex, to: AtomIndex) -> Option<usize> {
+        self.distances(from)[to.0]
+    }
+
+    /// Topological distance from one atom to every other atom.
+    pub fn distances(&self, from: AtomIndex) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.len()];
+        distances[from.0] = Some(0);
+        let mut queue = VecDeque::from([from]);
+        while let Some(atom) = queue.pop_front() {
+            let depth = distances[atom.0].unwrap_or(0);
+            for other in self.neighbors(atom) {
+                if distances[other.0].is_none() {
+                    distances[other.0] = Some(depth + 1);
+                    queue.push_back(other);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Atoms on a shortest path, including both ends. Ties go to the
+    /// neighbor listed first.
+    pub fn shortest_path(&self, from: AtomIndex, to: AtomIndex) -> Option<Vec<AtomIndex>> {
+        let previous = self.search(from, |_| true);
+        if from != to && previous[to.0].is_none() {
+            return None;
+        }
+        Some(trace(&previous, to))
+    }
+
+    /// Smallest set of smallest rings. Each ring lists its atoms in order
+    /// around the ring; rings come smallest first. The number of rings is
+    /// the cycle rank of the graph, so naphthalene gives two six-membered
+    /// rings and not the outer ten-membered one.
+    pub fn rings(&self) -> Vec<Vec<AtomIndex>> {
+        let ring_bonds = self.ring_bonds();
+        let mut rings = Vec::new();
+
+        for system in self.ring_systems(&ring_bonds) {
+            let bonds: Vec<BondIndex> = {
+                let mut bonds: Vec<BondIndex> = system
+                    .iter()
+                    .flat_map(|&atom| self.edges(atom))
+                    .filter(|(_, bond)| ring_bonds.contains(bond))
+                    .map(|&(_, bond)| bond)
+                    .collect();
+                bonds.sort_unstable();
+                bonds.dedup();
+                bonds
+            };
+            let rank = bonds.len() + 1 - system.len();
+
+            // Horton's candidates: for every atom and ring bond, the cycle
+            // made of the two shortest paths from the atom to the bond ends
+            let mut candidates: Vec<Vec<AtomIndex>> = Vec::new();
+            let mut seen = HashSet::new();
+            for &root in &system {
+                let previous = self.search(root, |bond| ring_bonds.contains(&bond));
+                for &atom in &system {
+                    for &(other, bond) in self.edges(atom) {
+                        if other < atom || !ring_bonds.contains(&bond) {
+                            continue;
+                        }
+                        let first = trace(&previous, atom);
+                        let second = trace(&previous, other);
+                        if first.len() + second.len() < 4
+                            || first[1..].iter().any(|step| second[1..].contains(step))
+                        {
+                            continue;
+                        }
+                        let mut cycle = first;
+                        cycle.extend(second[1..].iter().rev());
+                        let mut key = cycle.clone();
+                        key.sort_unstable();
+                        if seen.insert(key) {
+                            candidates.push(cycle);
+                        }
+                    }
+                }
+            }
+            candidates.sort_by_key(|cycle| cycle.len());
+
+            // Keep the shortest cycles that are independent over GF(2)
+            let column = |bond: BondIndex| bonds.binary_search(&bond).ok();
+            let mut basis: Vec<(usize, Vec<u64>)> = Vec::new();
+            for cycle in candidates {
+                if basis.len() == rank {
+                    break;
+                }
+                let mut vector = vec![0u64; bonds.len().div_ceil(64)];
+                for (i, &atom) in cycle.iter().enumerate() {
+                    let next = cycle[(i + 1) % cycle.len()];
+                    if let Some(column) = self.bond_between(atom, next).and_then(column) {
+                        vector[column / 64] ^= 1 << (column % 64);
+                    }
+                }
+                for (pivot, row) in &basis {
+                    if vector[pivot / 64] & (1 << (pivot % 64)) != 0 {
+                        for (word, bits) in vector.iter_mut().zip(row) {
+                            *word ^= bits;
+                        }
+                    }
+                }
+                let Some(pivot) = (0..bonds.len()).find(|&bit| vector[bit / 64] & (1 << (bit % 64)) != 0) else {
+                    continue;
+                };
+                // Keep the basis reduced so each pivot appears in one row only
+                for (_, row) in basis.iter_mut() {
+                    if row[pivot / 64] & (1 << (pivot % 64)) != 0 {
+                        for (word, bits) in row.iter_mut().zip(&vector) {
+                            *word ^= bits;
+                        }
+                    }
+                }
+                basis.push((pivot, vector));
+                rings.push(cycle);
+            }
+        }
+
+        rings.sort_by_key(|ring| ring.len());
+        rings
+    }
+
+    // Breadth-first parents from `root`, following only bonds that pass the filter
+    fn search(&self, root: AtomIndex, follow: impl Fn(BondIndex) -> bool) -> Vec<Option<AtomIndex>> {
+        let mut previous = vec![None; self.len()];
+        let mut visited = vec![false; self.len()];
+        visited[root.0] = true;
+        let mut queue = VecDeque::from([root]);
+        while let Some(atom) = queue.pop_front() {
+            for &(other, bond) in self.edges(atom) {
+                if !visited[other.0] && follow(bond) {
+                    visited[other.0] = true;
+                    previous[other.0] = Some(atom);
+                    queue.push_back(other);
+                }
+            }
+        }
+        previous
+    }
+
+    // Bonds that lie on some cycle, i.e. everything except bridges
+    fn ring_bonds(&self) -> HashSet<BondIndex> {
+        let mut order = vec![usize::MAX; self.len()];
+        let mut low = vec![0; self.len()];
+        let mut bridges = HashSet::new();
+        let mut counter = 0;
+
+        // Iterative Tarjan, so long chains cannot overflow the stack
+        for start in 0..self.len() {
+            if order[start] != usize::MAX {
+                continue;
+            }
+            order[start] = counter;
+            low[start] = counter;
+            counter += 1;
+            let mut stack: Vec<(usize, Option<BondIndex>, usize)> = vec![(start, None, 0)];
+            while let Some(&(atom, parent_bond, next)) = stack.last() {
+                if let Some(&(other, bond)) = self.adjacency[atom].get(next) {
+                    stack.last_mut().unwrap().2 += 1;
+                    if Some(bond) == parent_bond {
+                        continue;
+                    }
+                    if order[other.0] == usize::MAX {
+                        order[other.0] = counter;
+                        low[other.0] = counter;
+                        counter += 1;
+                        stack.push((other.0, Some(bond), 0));
+                    } else {
+                        low[atom] = low[atom].min(order[other.0]);
+                    }
+                } else {
+                    stack.pop();
+                    if let (Some(bond), Some(&(parent, _, _))) = (parent_bond, stack.last()) {
+                        low[parent] = low[parent].min(low[atom]);
+                        if low[atom] > order[parent] {
+                            bridges.insert(bond);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.adjacency
+            .iter()
+            .flatten()
+            .map(|&(_, bond)| bond)
+            .filter(|bond| !bridges.contains(bond))
+            .collect()
+    }
+
+    // Atoms joined by ring bonds, one sorted list per fused or isolated ring system
+    fn ring_systems(&self, ring_bonds: &HashSet<BondIndex>) -> Vec<Vec<AtomIndex>> {
+        let mut visited = vec![false; self.len()];
+        let mut systems = Vec::new();
+        for start in 0..self.len() {
+            if visited[start] || !self.adjacency[start].iter().any(|(_, bond)| ring_bonds.contains(bond)) {
+                continue;
+            }
+            visited[start] = true;
+            let mut system = vec![AtomIndex(start)];
+            let mut next = 0;
+            while next < system.len() {
+                let atom = system[next];
+                next += 1;
+                for &(other, bond) in self.edges(atom) {
+                    if !visited[other.0] && ring_bonds.contains(&bond) {
+                        visited[other.0] = true;
+                        system.push(other);
+                    }
+                }
+            }
+            system.sort_unstable();
+            systems.push(system);
+        }
+        systems
+    }
+}
+
+// Path from the search root to `atom`
+fn trace(previous: &[Option<AtomIndex>], atom: AtomIndex) -> Vec<AtomIndex> {
+    let mut path = vec![atom];
+    while let Some(step) = previous[path.last().unwrap().0] {
+        path.push(step);
+    }
+    path.reverse();
+    path
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+/// Buckets points into cubic cells at least `cell_size` wide, so every point
+/// within that distance of a query lies in the 27 cells around it.
+pub(crate) struct CellGrid {
+    cell_size: f32,
+    cells: HashMap<[i32; 3], Vec<usize>>,
+}
+
+impl CellGrid {
+    // Tiny or zero cutoffs would give absurdly many cells; larger cells only
+    // cost a few extra candidates
+    pub(crate) fn new(cell_size: f32, points: impl IntoIterator<Item = (usize, [f32; 3])>) -> Self {
+        let mut grid = Self { cell_size: cell_size.max(1.0), cells: HashMap::new() };
+        for (index, position) in points {
+            grid.cells.entry(grid.cell_of(position)).or_default().push(index);
+        }
+        grid
+    }
+
+    /// Indices of the points in the cells around `position`; callers still
+    /// check the distance.
+    pub(crate) fn candidates(&self, position: [f32; 3]) -> impl Iterator<Item = usize> + '_ {
+        let [x, y, z] = self.cell_of(position);
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [x + dx, y + dy, z + dz])))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+
+    fn cell_of(&self, position: [f32; 3]) -> [i32; 3] {
+        position.map(|value| (value / self.cell_size).floor() as i32)
+    }
+}
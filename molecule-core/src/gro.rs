@@ -0,0 +1,264 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::invalid_data;
+use crate::parser::infer_element_from_atom_name;
+use crate::structure::{Atom, AtomIndex, Molecule, UnitCell};
+use crate::writer::selected_blocks;
+
+// GROMACS stores lengths in nm, molecule-rs uses Å
+const NM_TO_ANGSTROM: f32 = 10.0;
+
+pub struct GroParser;
+
+impl Default for GroParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GroParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<Molecule> {
+        let reader = BufReader::new(content.as_bytes());
+
+        self.parse_reader(reader)
+    }
+
+    /// Parses one or more concatenated .gro frames. A single frame is loaded
+    /// directly into the molecule; several frames become consecutive models.
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Molecule> {
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        let mut frames = Vec::new();
+        let mut cursor = 0;
+
+        // Titles may be empty, so only blank lines after the last frame are skipped
+        while lines[cursor..].iter().any(|line| !line.trim().is_empty()) {
+            frames.push(self.parse_frame(&lines, &mut cursor)?);
+        }
+
+        let mut molecule = Molecule::new();
+        molecule.metadata.title = lines.first().map(|title| title.trim().to_string()).filter(|title| !title.is_empty());
+        let multi_model = frames.len() > 1;
+        let mut next_id = 1;
+
+        for (index, (atoms, unit_cell)) in frames.into_iter().enumerate() {
+            if multi_model {
+                molecule.start_model(index + 1);
+            }
+            for mut atom in atoms {
+                // .gro atom numbers wrap at 99999, so serials are assigned sequentially
+                atom.id = next_id;
+                next_id += 1;
+                molecule.add_atom(atom);
+            }
+            if multi_model {
+                molecule.end_model();
+            }
+            molecule.unit_cell = unit_cell;
+        }
+
+        // Template bonds for standard residues, distance bonds for the rest
+        molecule.calculate_template_bonds();
+
+        Ok(molecule)
+    }
+
+    fn parse_frame(&self, lines: &[String], cursor: &mut usize) -> io::Result<(Vec<Atom>, Option<UnitCell>)> {
+        // Title line, then the atom count
+        let count_line = lines
+            .get(*cursor + 1)
+            .ok_or_else(|| invalid_data("missing atom count line"))?;
+        let atom_count = count_line
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| invalid_data(&format!("invalid atom count: {:?}", count_line.trim())))?;
+        *cursor += 2;
+
+        if lines.len() < *cursor + atom_count + 1 {
+            return Err(invalid_data("file ends before all atoms and the box line were read"));
+        }
+
+        let mut atoms = Vec::with_capacity(atom_count);
+        for line in &lines[*cursor..*cursor + atom_count] {
+            let atom = self
+                .parse_atom_line(line)
+                .ok_or_else(|| invalid_data(&format!("malformed atom line: {:?}", line)))?;
+            atoms.push(atom);
+        }
+        *cursor += atom_count;
+
+        let unit_cell = self.parse_box_line(&lines[*cursor]);
+        *cursor += 1;
+
+        Ok((atoms, unit_cell))
+    }
+
+    fn parse_atom_line(&self, line: &str) -> Option<Atom> {
+        if line.len() < 44 {
+            return None;
+        }
+
+        // Fixed columns: resnr(5) resname(5) atomname(5) atomnr(5), then coordinates.
+        // The atom number is ignored since serials are reassigned by the caller.
+        // See: https://manual.gromacs.org/current/reference-manual/file-formats.html#gro
+        // Columns are bytes, so a multi-byte character straddling one makes the line malformed
+        let residue_id = line.get(0..5)?.trim().parse::<usize>().ok()?;
+        let residue_name = line.get(5..10)?.trim().to_string();
+        let atom_name = line.get(10..15)?.trim().to_string();
+        let coordinates = line.get(20..)?;
+
+        // Field width follows the precision, which is the distance between decimal points
+        let first_dot = coordinates.find('.')?;
+        let second_dot = coordinates[first_dot + 1..].find('.')? + first_dot + 1;
+        let width = second_dot - first_dot;
+
+        let fields = parse_fixed_fields(coordinates, width)?;
+        let position = fields.map(|v| v * NM_TO_ANGSTROM);
+
+        // Velocities share the field width but carry one more decimal
+        let velocity = coordinates
+            .get(3 * width..)
+            .filter(|rest| !rest.trim().is_empty())
+            .and_then(|rest| parse_fixed_fields(rest, width))
+            .map(|v| v.map(|c| c * NM_TO_ANGSTROM));
+
+        Some(Atom {
+            element: infer_element_from_atom_name(&atom_name),
+            name: atom_name,
+            position,
+            residue_id,
+            residue_name,
+            velocity,
+            ..Atom::default()
+        })
+    }
+
+    fn parse_box_line(&self, line: &str) -> Option<UnitCell> {
+        let values: Vec<f32> = line
+            .split_whitespace()
+            .map(|v| v.parse::<f32>().map(|v| v * NM_TO_ANGSTROM))
+            .collect::<Result<_, _>>()
+            .ok()?;
+
+        match values.len() {
+            3 => Some(UnitCell::orthorhombic(values[0], values[1], values[2])),
+            // v1(x) v2(y) v3(z) v1(y) v1(z) v2(x) v2(z) v3(x) v3(y)
+            9 => Some(UnitCell::new([
+                [values[0], values[3], values[4]],
+                [values[5], values[1], values[6]],
+                [values[7], values[8], values[2]],
+            ])),
+            _ => None,
+        }
+    }
+}
+
+/// Writer for GROMACS .gro files. Each model becomes one frame, velocities are
+/// written when every atom has one, and the unit cell goes on the box line.
+pub struct GroWriter {
+    selection: Option<Vec<AtomIndex>>,
+}
+
+impl Default for GroWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GroWriter {
+    pub fn new() -> Self {
+        Self { selection: None }
+    }
+
+    /// Restricts output to these atoms, e.g. from `Molecule::select`.
+    pub fn with_selection(mut self, atoms: Vec<AtomIndex>) -> Self {
+        self.selection = Some(atoms);
+        self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        let title = molecule.metadata.title.as_deref().unwrap_or("Generated by molecule-rs");
+
+        for (_, atoms) in selected_blocks(molecule, self.selection.as_deref())? {
+            writeln!(writer, "{}", title.lines().next().unwrap_or(""))?;
+            writeln!(writer, "{:>5}", atoms.len())?;
+
+            let with_velocities = !atoms.is_empty() && atoms.iter().all(|atom| atom.velocity.is_some());
+            for (i, atom) in atoms.iter().enumerate() {
+                // Residue and atom numbers wrap at five digits, as in GROMACS
+                let [x, y, z] = atom.position.map(|v| v / NM_TO_ANGSTROM);
+                let mut line = format!(
+                    "{:>5}{:<5}{:>5}{:>5}{:8.3}{:8.3}{:8.3}",
+                    atom.residue_id % 100_000,
+                    truncate(&atom.residue_name, 5),
+                    truncate(&atom.name, 5),
+                    (i + 1) % 100_000,
+                    x,
+                    y,
+                    z
+                );
+                if with_velocities && let Some(velocity) = atom.velocity {
+                    let [vx, vy, vz] = velocity.map(|v| v / NM_TO_ANGSTROM);
+                    line.push_str(&format!("{:8.4}{:8.4}{:8.4}", vx, vy, vz));
+                }
+                writeln!(writer, "{}", line)?;
+            }
+
+            writeln!(writer, "{}", format_box_line(molecule.unit_cell.as_ref()))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_box_line(unit_cell: Option<&UnitCell>) -> String {
+    let Some(cell) = unit_cell else {
+        return format!("{:10.5}{:10.5}{:10.5}", 0.0, 0.0, 0.0);
+    };
+
+    let [a, b, c] = cell.vectors.map(|vector| vector.map(|v| v / NM_TO_ANGSTROM));
+    let mut values = vec![a[0], b[1], c[2]];
+    if !cell.is_orthorhombic() {
+        values.extend([a[1], a[2], b[0], b[2], c[0], c[1]]);
+    }
+    values.iter().map(|value| format!("{:10.5}", value)).collect()
+}
+
+fn truncate(text: &str, width: usize) -> &str {
+    text.char_indices().nth(width).map_or(text, |(end, _)| &text[..end])
+}
+
+fn parse_fixed_fields(text: &str, width: usize) -> Option<[f32; 3]> {
+    let mut values = [0.0; 3];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = text.get(i * width..(i + 1) * width)?.trim().parse::<f32>().ok()?;
+    }
+    Some(values)
+}
@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+use crate::classify::ResidueKind;
+use crate::geometry::{add, cross, distance, dot, normalize, scale, sub};
+use crate::grid::CellGrid;
+use crate::kekulize::default_valence;
+use crate::structure::{Atom, AtomIndex, Bond, BondOrder, Element, Model, Molecule};
+use crate::templates::residue_template;
+
+// Rotamer sampling step for polar hydrogens, in degrees
+const ROTATION_STEP: usize = 10;
+// Ideal donor hydrogen to acceptor distance, and the window around it that scores
+const HBOND_DISTANCE: f32 = 1.9;
+const HBOND_WINDOW: f32 = 0.7;
+// Heavy atoms further than this from a donor cannot affect its score
+const HBOND_REACH: f32 = 4.0;
+
+/// Protonation of a titratable or tautomeric atom.
+#[derive(Debug, Clone, Copy)]
+enum Protonation {
+    // pKa, then hydrogen count and formal charge below and above it
+    Titratable(f32, (usize, i8), (usize, i8)),
+    Fixed(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Geometry {
+    Linear,
+    Trigonal,
+    Tetrahedral,
+}
+
+struct Placement {
+    heavy: AtomIndex,
+    names: Vec<String>,
+    positions: Vec<[f32; 3]>,
+}
+
+impl Molecule {
+    /// Adds missing hydrogens with the protonation state expected at `ph`,
+    /// and returns how many were added.
+    ///
+    /// Standard residues follow pKa values for Asp, Glu, His, Cys, Tyr, Lys,
+    /// Arg, the termini and phosphates, setting formal charges to match; other
+    /// atoms are filled up to their usual valence. Hydrogens are placed in
+    /// ideal geometry, named after their heavy atom in PDB style (HB2, HD21,
+    /// H1' ...), and rotatable OH, SH and NH3+ groups are turned towards
+    /// nearby acceptors. Existing hydrogens are kept. Atoms are re-serialized
+    /// from 1 in each model and indices taken before the call are invalidated.
+    pub fn add_hydrogens(&mut self, ph: f32) -> usize {
+        let neighbors = self.neighbor_lists();
+        let heavy_atoms = CellGrid::new(
+            HBOND_REACH,
+            self.atoms.iter().enumerate().filter(|(_, atom)| atom.element != Element::H).map(|(i, atom)| (i, atom.position)),
+        );
+        let mut placements = Vec::new();
+
+        for heavy in 0..self.atoms.len() {
+            let atom = &self.atoms[heavy];
+            if atom.element == Element::H {
+                continue;
+            }
+            let existing = neighbors[heavy].iter().filter(|&&(other, _)| self.atoms[other].element == Element::H).count();
+            let (target, charge) = self.hydrogen_target(heavy, &neighbors[heavy], ph);
+            if let Some(charge) = charge {
+                self.atoms[heavy].formal_charge = charge;
+            }
+            let count = target.saturating_sub(existing);
+            if count == 0 {
+                continue;
+            }
+
+            let geometry = self.geometry(heavy, &neighbors, count);
+            let bonded: Vec<usize> = neighbors[heavy].iter().map(|&(other, _)| other).collect();
+            let positions = self.place_hydrogens(heavy, &bonded, count, geometry, &neighbors, &heavy_atoms);
+            let names = self.hydrogen_names(heavy, count, &neighbors[heavy]);
+            placements.push(Placement { heavy: AtomIndex(heavy), names, positions });
+        }
+
+        let added = placements.iter().map(|placement| placement.positions.len()).sum();
+        if added > 0 {
+            self.insert_hydrogens(placements);
+        }
+        added
+    }
+
+    fn neighbor_lists(&self) -> Vec<Vec<(usize, BondOrder)>> {
+        let mut neighbors = vec![Vec::new(); self.atoms.len()];
+        for bond in &self.bonds {
+            if bond.atom1.0 < self.atoms.len() && bond.atom2.0 < self.atoms.len() {
+                neighbors[bond.atom1.0].push((bond.atom2.0, bond.order));
+                neighbors[bond.atom2.0].push((bond.atom1.0, bond.order));
+            }
+        }
+        neighbors
+    }
+
+    // Total hydrogens the atom should carry, and its formal charge when the
+    // protonation state decides it
+    fn hydrogen_target(&self, atom: usize, neighbors: &[(usize, BondOrder)], ph: f32) -> (usize, Option<i8>) {
+        let name = self.atoms[atom].name.as_str();
+        let residue = &self.residues[self.atoms[atom].residue.0];
+        let template = residue_template(&residue.name);
+
+        // Bonds leaving the residue (disulfides, metal sites) rule out titration
+        let external = neighbors.iter().any(|&(other, _)| {
+            self.atoms[other].residue != self.atoms[atom].residue && self.atoms[other].element != Element::H
+        });
+        let protonation = template.and_then(|template| {
+            if template.name != "PRO" && template.kind == ResidueKind::Protein && name == "N" && !external {
+                return Some(Protonation::Titratable(8.0, (3, 1), (2, 0)));
+            }
+            if template.name == "PRO" && name == "N" && !external {
+                return Some(Protonation::Titratable(8.0, (2, 1), (1, 0)));
+            }
+            protonation(template.name, name)
+        });
+
+        match protonation {
+            Some(Protonation::Fixed(count)) => (count, None),
+            Some(Protonation::Titratable(pka, acid, base)) if !external => {
+                let (count, charge) = if ph < pka { acid } else { base };
+                (count, Some(charge))
+            }
+            _ => {
+                if template.is_some() && never_protonated(name) {
+                    return (0, None);
+                }
+                let atom = &self.atoms[atom];
+                let Some(valence) = default_valence(atom.element, atom.formal_charge) else {
+                    return (0, None);
+                };
+                // Aromatic bonds count one and a half, rounded down per atom
+                let used: f32 = neighbors
+                    .iter()
+                    .filter(|&&(other, _)| self.atoms[other].element != Element::H)
+                    .map(|&(_, order)| match order {
+                        BondOrder::Single => 1.0,
+                        BondOrder::Aromatic => 1.5,
+                        BondOrder::Double => 2.0,
+                        BondOrder::Triple => 3.0,
+                    })
+                    .sum();
+                ((valence - used.floor() as i32).max(0) as usize, None)
+            }
+        }
+    }
+
+    fn geometry(&self, atom: usize, neighbors: &[Vec<(usize, BondOrder)>], count: usize) -> Geometry {
+        let orders: Vec<BondOrder> = neighbors[atom].iter().map(|&(_, order)| order).collect();
+        if orders.contains(&BondOrder::Triple) || orders.iter().filter(|&&order| order == BondOrder::Double).count() == 2 {
+            return Geometry::Linear;
+        }
+        if orders.iter().any(|&order| matches!(order, BondOrder::Double | BondOrder::Aromatic)) {
+            return Geometry::Trigonal;
+        }
+        // Nitrogen next to a π system (amides, anilines, guanidinium) is planar
+        // unless it is fully protonated
+        let conjugated = neighbors[atom].iter().any(|&(other, _)| {
+            neighbors[other].iter().any(|&(_, order)| matches!(order, BondOrder::Double | BondOrder::Aromatic))
+        });
+        if self.atoms[atom].element == Element::N && conjugated && neighbors[atom].len() + count <= 3 {
+            return Geometry::Trigonal;
+        }
+        Geometry::Tetrahedral
+    }
+
+    fn place_hydrogens(
+        &self,
+        atom: usize,
+        bonded: &[usize],
+        count: usize,
+        geometry: Geometry,
+        neighbors: &[Vec<(usize, BondOrder)>],
+        heavy_atoms: &CellGrid,
+    ) -> Vec<[f32; 3]> {
+        let center = self.atoms[atom].position;
+        let length = match self.atoms[atom].element {
+            Element::C => 1.09,
+            Element::N => 1.01,
+            Element::O => 0.96,
+            Element::S => 1.34,
+            _ => 1.05,
+        };
+        let units: Vec<[f32; 3]> = bonded
+            .iter()
+            .map(|&other| normalize(sub(self.atoms[other].position, center)))
+            .collect();
+
+        let directions: Vec<[f32; 3]> = match (units.len(), geometry) {
+            (0, _) => {
+                // Isolated atoms such as water oxygen: any orientation will do
+                let angle = 104.5f32.to_radians();
+                vec![[1.0, 0.0, 0.0], [angle.cos(), angle.sin(), 0.0]]
+            }
+            (1, _) => {
+                let axis = scale(units[0], -1.0);
+                let reference = neighbors[bonded[0]]
+                    .iter()
+                    .map(|&(other, _)| other)
+                    .find(|&other| other != atom)
+                    .map(|other| sub(self.atoms[other].position, self.atoms[bonded[0]].position));
+                let (v, w) = frame(axis, reference);
+                let (tilt, dihedrals): (f32, Vec<f32>) = match geometry {
+                    Geometry::Linear => (0.0, vec![0.0]),
+                    Geometry::Trigonal => (60.0, vec![180.0, 0.0]),
+                    Geometry::Tetrahedral => (70.5, vec![180.0, 60.0, -60.0]),
+                };
+                let mut best = self.cone(axis, v, w, tilt, &dihedrals, 0.0);
+                if geometry == Geometry::Tetrahedral && is_rotatable(self.atoms[atom].element) {
+                    let nearby = self.nearby_heavy_atoms(atom, neighbors, heavy_atoms);
+                    let mut best_score = f32::MIN;
+                    for step in (0..360).step_by(ROTATION_STEP) {
+                        let candidate = self.cone(axis, v, w, tilt, &dihedrals, step as f32);
+                        let positions: Vec<[f32; 3]> =
+                            candidate.iter().take(count).map(|&d| add(center, scale(d, length))).collect();
+                        let score = self.hydrogen_bond_score(&nearby, &positions);
+                        if score > best_score + 1e-6 {
+                            best_score = score;
+                            best = candidate;
+                        }
+                    }
+                }
+                best
+            }
+            (2, Geometry::Tetrahedral) => {
+                let bisector = normalize(scale(add(units[0], units[1]), -1.0));
+                let normal = normalize(cross(units[0], units[1]));
+                let half = (109.5f32 / 2.0).to_radians();
+                vec![
+                    add(scale(bisector, half.cos()), scale(normal, half.sin())),
+                    add(scale(bisector, half.cos()), scale(normal, -half.sin())),
+                ]
+            }
+            _ => vec![normalize(scale(units.iter().fold([0.0; 3], |sum, &unit| add(sum, unit)), -1.0))],
+        };
+
+        directions.into_iter().take(count).map(|direction| add(center, scale(direction, length))).collect()
+    }
+
+    // Unit vectors at `tilt` degrees from the axis, at the given dihedrals
+    // (plus a common rotation) measured from the reference direction
+    fn cone(&self, axis: [f32; 3], v: [f32; 3], w: [f32; 3], tilt: f32, dihedrals: &[f32], rotation: f32) -> Vec<[f32; 3]> {
+        let tilt = tilt.to_radians();
+        dihedrals
+            .iter()
+            .map(|&dihedral| {
+                let angle = (dihedral + rotation) * PI / 180.0;
+                let radial = add(scale(v, angle.cos()), scale(w, angle.sin()));
+                normalize(add(scale(axis, tilt.cos()), scale(radial, tilt.sin())))
+            })
+            .collect()
+    }
+
+    // Heavy atoms within reach of the donor that are not bonded to it, in file order
+    fn nearby_heavy_atoms(&self, donor: usize, neighbors: &[Vec<(usize, BondOrder)>], heavy_atoms: &CellGrid) -> Vec<usize> {
+        let origin = self.atoms[donor].position;
+        let mut nearby: Vec<usize> = heavy_atoms
+            .candidates(origin)
+            .filter(|&index| {
+                index != donor
+                    && !neighbors[donor].iter().any(|&(other, _)| other == index)
+                    && distance(self.atoms[index].position, origin) <= HBOND_REACH
+            })
+            .collect();
+        nearby.sort_unstable();
+        nearby
+    }
+
+    // Rewards hydrogens near N or O acceptors and penalizes clashes
+    fn hydrogen_bond_score(&self, nearby: &[usize], hydrogens: &[[f32; 3]]) -> f32 {
+        let mut score = 0.0;
+        for atom in nearby.iter().map(|&index| &self.atoms[index]) {
+            for &hydrogen in hydrogens {
+                let d = distance(atom.position, hydrogen);
+                if matches!(atom.element, Element::N | Element::O) {
+                    score += (1.0 - (d - HBOND_DISTANCE).abs() / HBOND_WINDOW).max(0.0);
+                }
+                if d < 1.6 {
+                    score -= 2.0;
+                }
+            }
+        }
+        score
+    }
+
+    fn hydrogen_names(&self, atom: usize, count: usize, neighbors: &[(usize, BondOrder)]) -> Vec<String> {
+        let heavy = &self.atoms[atom];
+        let residue = &self.residues[heavy.residue.0];
+        let mut taken: HashSet<String> = residue.atoms.iter().map(|&other| self.atoms[other.0].name.clone()).collect();
+        let template = residue_template(&residue.name);
+
+        let n_terminus = heavy.name == "N"
+            && template.is_some_and(|template| template.kind == ResidueKind::Protein)
+            && !neighbors.iter().any(|&(other, _)| self.atoms[other].residue != heavy.residue);
+        let names: Vec<String> = if n_terminus && count > 1 {
+            (1..=count).map(|k| format!("H{}", k)).collect()
+        } else {
+            let symbol = heavy.element.symbol().to_ascii_uppercase();
+            let stem = match heavy.name.strip_prefix(symbol.as_str()) {
+                // Nucleic acid hydroxyls keep the oxygen: HO3', HO5'
+                Some(stem) if heavy.element == Element::C || !stem.ends_with('\'') => stem.to_string(),
+                _ => heavy.name.clone(),
+            };
+            let base = format!("H{}", stem);
+            match count {
+                1 => vec![base],
+                2 if stem.ends_with('\'') => vec![base.clone(), format!("{}'", base)],
+                2 if heavy.element == Element::C => vec![format!("{}2", base), format!("{}3", base)],
+                _ => (1..=count).map(|k| format!("{}{}", base, k)).collect(),
+            }
+        };
+
+        let mut next = 1;
+        names
+            .into_iter()
+            .map(|name| {
+                if name.len() <= 4 && taken.insert(name.clone()) {
+                    return name;
+                }
+                while taken.contains(&format!("H{}", next)) {
+                    next += 1;
+                }
+                let name = format!("H{}", next);
+                taken.insert(name.clone());
+                name
+            })
+            .collect()
+    }
+
+    // Rebuilds the molecule with each residue's hydrogens after its last atom
+    fn insert_hydrogens(&mut self, placements: Vec<Placement>) {
+        let mut by_residue: Vec<Vec<Atom>> = vec![Vec::new(); self.residues.len()];
+        let mut parents: Vec<Vec<AtomIndex>> = vec![Vec::new(); self.residues.len()];
+        for placement in placements {
+            let heavy = &self.atoms[placement.heavy.0];
+            for (name, position) in placement.names.into_iter().zip(placement.positions) {
+                by_residue[heavy.residue.0].push(Atom {
+                    id: 0,
+                    name,
+                    element: Element::H,
+                    position,
+                    residue_id: heavy.residue_id,
+                    chain_id: heavy.chain_id,
+                    b_factor: heavy.b_factor,
+                    occupancy: heavy.occupancy,
+                    residue_name: heavy.residue_name.clone(),
+                    alt_loc: heavy.alt_loc,
+                    ins_code: heavy.ins_code,
+                    is_hetatm: heavy.is_hetatm,
+                    ..Atom::default()
+                });
+                parents[heavy.residue.0].push(placement.heavy);
+            }
+        }
+
+        let source = std::mem::take(self);
+        self.unit_cell = source.unit_cell;
+        self.metadata = source.metadata.clone();
+
+        // Models keep their indices, so interleaved models are resumed rather
+        // than duplicated; serials count per model, loose atoms last
+        self.models = source.models.iter().map(|model| Model { id: model.id, atoms: Vec::new() }).collect();
+        let mut serials = vec![0; source.models.len() + 1];
+        let mut mapping = vec![AtomIndex(0); source.atoms.len()];
+        let mut hydrogen_bonds = Vec::new();
+        for (index, atom) in source.atoms.iter().enumerate() {
+            let model = source.chains[source.residues[atom.residue.0].chain.0].model;
+            self.current_model = model;
+            let serial = &mut serials[model.map_or(source.models.len(), |model| model.0)];
+
+            *serial += 1;
+            mapping[index] = self.add_atom(Atom { id: *serial, ..atom.clone() });
+
+            let residue = &source.residues[atom.residue.0];
+            if residue.atoms.last() == Some(&AtomIndex(index)) {
+                for (hydrogen, parent) in std::mem::take(&mut by_residue[atom.residue.0])
+                    .into_iter()
+                    .zip(&parents[atom.residue.0])
+                {
+                    *serial += 1;
+                    let hydrogen = self.add_atom(Atom { id: *serial, ..hydrogen });
+                    hydrogen_bonds.push((*parent, hydrogen));
+                }
+            }
+        }
+        self.current_model = source.current_model;
+
+        for bond in &source.bonds {
+            self.bonds.push(Bond {
+                atom1: mapping[bond.atom1.0],
+                atom2: mapping[bond.atom2.0],
+                ..bond.clone()
+            });
+        }
+        for (parent, hydrogen) in hydrogen_bonds {
+            self.add_bond(mapping[parent.0], hydrogen, BondOrder::Single);
+        }
+    }
+}
+
+fn protonation(template: &str, atom: &str) -> Option<Protonation> {
+    use Protonation::{Fixed, Titratable};
+
+    let protonation = match (template, atom) {
+        ("ASP", "OD2") => Titratable(3.9, (1, 0), (0, -1)),
+        ("GLU", "OE2") => Titratable(4.2, (1, 0), (0, -1)),
+        // Neutral histidine takes the HE2 tautomer
+        ("HIS", "ND1") => Titratable(6.0, (1, 1), (0, 0)),
+        ("HIS", "NE2") => Fixed(1),
+        ("CYS", "SG") => Titratable(8.3, (1, 0), (0, -1)),
+        ("TYR", "OH") => Titratable(10.1, (1, 0), (0, -1)),
+        ("LYS", "NZ") => Titratable(10.5, (3, 1), (2, 0)),
+        ("ARG", "NH2") => Titratable(12.5, (2, 1), (1, 0)),
+        ("TRP", "NE1") => Fixed(1),
+        (_, "OXT") => Titratable(3.1, (1, 0), (0, -1)),
+        (_, "OP2" | "O2P" | "OP3" | "O3P") => Titratable(1.0, (1, 0), (0, -1)),
+        ("DG" | "G", "N1") | ("DT" | "U", "N3") => Fixed(1),
+        ("DA" | "A", "N1" | "N3" | "N7") | ("DG" | "G", "N3" | "N7") | ("DC" | "C", "N3") => Fixed(0),
+        _ => return None,
+    };
+    Some(protonation)
+}
+
+// Backbone atoms that stay bare even when a neighbor is missing
+fn never_protonated(atom: &str) -> bool {
+    matches!(atom, "C" | "O" | "P" | "OP1" | "O1P")
+}
+
+fn is_rotatable(element: Element) -> bool {
+    matches!(element, Element::O | Element::S | Element::N)
+}
+
+// Two unit vectors perpendicular to the axis, the first towards the reference
+fn frame(axis: [f32; 3], reference: Option<[f32; 3]>) -> ([f32; 3], [f32; 3]) {
+    let fallback = if axis[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let mut v = reference.unwrap_or(fallback);
+    v = sub(v, scale(axis, dot(v, axis)));
+    if dot(v, v) < 1e-6 {
+        v = sub(fallback, scale(axis, dot(fallback, axis)));
+    }
+    let v = normalize(v);
+    (v, cross(axis, v))
+}
@@ -0,0 +1,137 @@
+use crate::structure::{BondOrder, Element, Molecule};
+
+// Upper bound on backtracking steps, so pathological inputs fail quickly
+const SEARCH_BUDGET: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Need {
+    None,
+    Required,
+    Optional, // Pyrrole-type N or P that may donate its lone pair instead
+}
+
+/// Usual valence of an element given its formal charge, for the elements that
+/// take part in aromatic systems.
+pub(crate) fn default_valence(element: Element, formal_charge: i8) -> Option<i32> {
+    let charge = formal_charge as i32;
+    match element {
+        Element::C => Some(4 - charge.abs()),
+        Element::N | Element::P => Some(3 + charge),
+        Element::O | Element::S | Element::Se => Some(2 + charge),
+        Element::B => Some(3 - charge),
+        _ => None,
+    }
+}
+
+/// Replaces aromatic bonds with alternating single and double bonds.
+///
+/// Returns the bond orders in `molecule.bonds` order, or `None` when no valid
+/// assignment exists (for example an aromatic ring atom with too many bonds).
+pub(crate) fn kekulize(molecule: &Molecule) -> Option<Vec<BondOrder>> {
+    let mut orders: Vec<BondOrder> = molecule.bonds.iter().map(|bond| bond.order).collect();
+    if !orders.contains(&BondOrder::Aromatic) {
+        return Some(orders);
+    }
+
+    let atom_count = molecule.atoms.len();
+    let mut aromatic: Vec<Vec<(usize, usize)>> = vec![Vec::new(); atom_count];
+    let mut used_valence = vec![0i32; atom_count];
+    let mut degree = vec![0usize; atom_count];
+
+    for (bond_index, bond) in molecule.bonds.iter().enumerate() {
+        let (a, b) = (bond.atom1.0, bond.atom2.0);
+        if a >= atom_count || b >= atom_count {
+            continue;
+        }
+        let valence = match bond.order {
+            BondOrder::Single | BondOrder::Aromatic => 1,
+            BondOrder::Double => 2,
+            BondOrder::Triple => 3,
+        };
+        used_valence[a] += valence;
+        used_valence[b] += valence;
+        degree[a] += 1;
+        degree[b] += 1;
+        if bond.order == BondOrder::Aromatic {
+            aromatic[a].push((bond_index, b));
+            aromatic[b].push((bond_index, a));
+        }
+    }
+
+    let needs: Vec<Need> = molecule
+        .atoms
+        .iter()
+        .enumerate()
+        .map(|(i, atom)| {
+            if aromatic[i].is_empty() {
+                return Need::None;
+            }
+            let free = default_valence(atom.element, atom.formal_charge).map_or(0, |valence| valence - used_valence[i]);
+            if free < 1 {
+                Need::None
+            } else if free == 1 && degree[i] == 2 && matches!(atom.element, Element::N | Element::P) {
+                Need::Optional
+            } else {
+                Need::Required
+            }
+        })
+        .collect();
+
+    let order: Vec<usize> = (0..atom_count).filter(|&i| needs[i] != Need::None).collect();
+    let mut partner: Vec<Option<usize>> = vec![None; atom_count];
+    let mut budget = SEARCH_BUDGET;
+    if !assign(0, &order, &needs, &aromatic, &mut partner, &mut budget) {
+        return None;
+    }
+
+    for order in orders.iter_mut().filter(|order| **order == BondOrder::Aromatic) {
+        *order = BondOrder::Single;
+    }
+    for (atom, neighbors) in aromatic.iter().enumerate() {
+        if let Some(other) = partner[atom]
+            && atom < other
+            && let Some(&(bond_index, _)) = neighbors.iter().find(|&&(_, neighbor)| neighbor == other)
+        {
+            orders[bond_index] = BondOrder::Double;
+        }
+    }
+
+    Some(orders)
+}
+
+// Depth-first search for a matching of aromatic bonds that covers every
+// required atom exactly once
+fn assign(
+    position: usize,
+    order: &[usize],
+    needs: &[Need],
+    aromatic: &[Vec<(usize, usize)>],
+    partner: &mut [Option<usize>],
+    budget: &mut usize,
+) -> bool {
+    let Some(&atom) = order.get(position) else {
+        return true;
+    };
+    if partner[atom].is_some() {
+        return assign(position + 1, order, needs, aromatic, partner, budget);
+    }
+    if *budget == 0 {
+        return false;
+    }
+    *budget -= 1;
+
+    for &(_, neighbor) in &aromatic[atom] {
+        if needs[neighbor] == Need::None || partner[neighbor].is_some() {
+            continue;
+        }
+        partner[atom] = Some(neighbor);
+        partner[neighbor] = Some(atom);
+        if assign(position + 1, order, needs, aromatic, partner, budget) {
+            return true;
+        }
+        partner[atom] = None;
+        partner[neighbor] = None;
+    }
+
+    needs[atom] == Need::Optional && assign(position + 1, order, needs, aromatic, partner, budget)
+}
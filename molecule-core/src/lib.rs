@@ -1,5 +1,58 @@
+mod amber;
+mod cache;
+mod ccd;
+mod cif;
+mod classify;
+mod dcd;
+mod edit;
+mod error;
+mod extract;
+mod geometry;
+mod graph;
+mod grid;
+mod gro;
+mod hydrogens;
+mod kekulize;
+mod mmcif;
 mod parser;
+mod perceive;
+mod pqr;
+mod psf;
+mod sdf;
+mod select;
+mod smarts;
+mod smiles;
 mod structure;
+mod templates;
+mod trajectory;
+mod view;
+mod writer;
+mod xtc;
+mod xyz;
 
+pub use amber::{InpcrdParser, PrmtopParser};
+pub use cache::{content_hash, BinaryParser, BinaryWriter, StructureCache, BINARY_FORMAT_VERSION};
+pub use ccd::{CcdParser, ChemicalComponent, ComponentAtom, ComponentBond, ComponentDictionary, ComponentReport};
+pub use classify::{classify_residue, Entity, ResidueKind};
+pub use dcd::DcdReader;
+pub use extract::ExtractOptions;
+pub use graph::MolecularGraph;
+pub use gro::{GroParser, GroWriter};
+pub use mmcif::MmcifWriter;
 pub use parser::PdbParser;
-pub use structure::{Atom, Bond, BondOrder, Chain, Element, Model, Molecule, Residue};
\ No newline at end of file
+pub use pqr::{PqrParser, PqrWriter};
+pub use psf::PsfParser;
+pub use sdf::SdfWriter;
+pub use select::Selection;
+pub use smarts::SmartsPattern;
+pub use smiles::{SmilesParser, SmilesWriter};
+pub use structure::{
+    Atom, AtomIndex, Bond, BondDirection, BondIndex, BondOrder, Chain, ChainIndex, Chirality, Element, Metadata, Model,
+    ModelIndex, Molecule, Residue, ResidueIndex, UnitCell,
+};
+pub use templates::{residue_template, ResidueTemplate, TemplateBond};
+pub use trajectory::Frame;
+pub use view::{AtomView, ChainView, ModelView, ResidueView};
+pub use writer::PdbWriter;
+pub use xtc::XtcReader;
+pub use xyz::XyzWriter;
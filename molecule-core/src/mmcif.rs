@@ -0,0 +1,470 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::classify::{classify_residue, ResidueKind};
+use crate::structure::{Atom, Element, Molecule};
+use crate::writer::model_blocks;
+
+/// Writer for PDBx/mmCIF files.
+///
+/// Writes `_entity`, `_atom_site` and `_struct_conn` categories, plus cell and
+/// symmetry when the molecule has a unit cell. Unlike PDB there are no column
+/// limits, so any molecule can be written regardless of its size.
+pub struct MmcifWriter;
+
+impl Default for MmcifWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EntityType {
+    Polymer,
+    NonPolymer,
+    Water,
+}
+
+impl EntityType {
+    fn of(atom: &Atom) -> Self {
+        if classify_residue(&atom.residue_name) == ResidueKind::Water {
+            EntityType::Water
+        } else if atom.is_hetatm {
+            EntityType::NonPolymer
+        } else {
+            EntityType::Polymer
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EntityType::Polymer => "polymer",
+            EntityType::NonPolymer => "non-polymer",
+            EntityType::Water => "water",
+        }
+    }
+}
+
+// Identifies one entity instance (label_asym_id): the polymer part of a chain,
+// its waters, or a single ligand residue
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AsymKey {
+    Polymer(char),
+    Water(char),
+    Ligand(char, usize, char),
+}
+
+impl AsymKey {
+    fn of(atom: &Atom) -> Self {
+        match EntityType::of(atom) {
+            EntityType::Polymer => AsymKey::Polymer(atom.chain_id),
+            EntityType::Water => AsymKey::Water(atom.chain_id),
+            EntityType::NonPolymer => AsymKey::Ligand(atom.chain_id, atom.residue_id, atom.ins_code),
+        }
+    }
+}
+
+struct Entity {
+    entity_type: EntityType,
+    description: Option<String>,
+}
+
+struct Layout {
+    entities: Vec<Entity>,
+    asym_ids: HashMap<AsymKey, String>,
+    asym_entities: HashMap<AsymKey, usize>,
+}
+
+impl MmcifWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        let entry_id = molecule
+            .metadata
+            .id_code
+            .clone()
+            .unwrap_or_else(|| "molecule".to_string());
+        let layout = self.layout(molecule);
+
+        writeln!(writer, "data_{}", entry_id.replace(char::is_whitespace, "_"))?;
+        writeln!(writer, "#")?;
+        writeln!(writer, "_entry.id {}", quote(&entry_id))?;
+        writeln!(writer, "#")?;
+        if let Some(title) = &molecule.metadata.title {
+            writeln!(writer, "_struct.entry_id {}", quote(&entry_id))?;
+            writeln!(writer, "_struct.title {}", quote(title))?;
+            writeln!(writer, "#")?;
+        }
+
+        if let Some(cell) = &molecule.unit_cell {
+            let [a, b, c] = cell.lengths();
+            let [alpha, beta, gamma] = cell.angles();
+            writeln!(writer, "_cell.entry_id {}", quote(&entry_id))?;
+            writeln!(writer, "_cell.length_a {:.3}", a)?;
+            writeln!(writer, "_cell.length_b {:.3}", b)?;
+            writeln!(writer, "_cell.length_c {:.3}", c)?;
+            writeln!(writer, "_cell.angle_alpha {:.2}", alpha)?;
+            writeln!(writer, "_cell.angle_beta {:.2}", beta)?;
+            writeln!(writer, "_cell.angle_gamma {:.2}", gamma)?;
+            writeln!(writer, "#")?;
+            writeln!(writer, "_symmetry.entry_id {}", quote(&entry_id))?;
+            writeln!(
+                writer,
+                "_symmetry.space_group_name_H-M {}",
+                quote(molecule.metadata.space_group.as_deref().unwrap_or("P 1"))
+            )?;
+            writeln!(writer, "#")?;
+        }
+
+        self.write_entities(&layout, &mut writer)?;
+        self.write_atom_site(molecule, &layout, &mut writer)?;
+        self.write_struct_conn(molecule, &layout, &mut writer)
+    }
+
+    // Assigns entity ids (identical polymer sequences and ligand types share an
+    // entity) and label_asym_ids in order of first appearance
+    fn layout(&self, molecule: &Molecule) -> Layout {
+        let mut asym_order: Vec<AsymKey> = Vec::new();
+        let mut asym_first_block: HashMap<AsymKey, usize> = HashMap::new();
+        let mut sequences: HashMap<AsymKey, Vec<String>> = HashMap::new();
+
+        for (block_index, (_, atoms)) in model_blocks(molecule).into_iter().enumerate() {
+            let mut previous: Option<&Atom> = None;
+            for atom in atoms.into_iter().map(|index| &molecule[index]) {
+                let key = AsymKey::of(atom);
+                let first_block = *asym_first_block.entry(key.clone()).or_insert_with(|| {
+                    asym_order.push(key.clone());
+                    block_index
+                });
+                if first_block == block_index && previous.is_none_or(|previous| !same_residue(previous, atom)) {
+                    sequences.entry(key).or_default().push(atom.residue_name.clone());
+                }
+                previous = Some(atom);
+            }
+        }
+
+        let mut entities: Vec<Entity> = Vec::new();
+        let mut entity_keys: HashMap<(EntityType, Vec<String>), usize> = HashMap::new();
+        let mut asym_ids = HashMap::new();
+        let mut asym_entities = HashMap::new();
+
+        for (index, key) in asym_order.into_iter().enumerate() {
+            let sequence = sequences.remove(&key).unwrap_or_default();
+            let entity_type = match key {
+                AsymKey::Polymer(_) => EntityType::Polymer,
+                AsymKey::Water(_) => EntityType::Water,
+                AsymKey::Ligand(..) => EntityType::NonPolymer,
+            };
+            // All waters form one entity whatever their count
+            let entity_key = match entity_type {
+                EntityType::Water => (entity_type, Vec::new()),
+                _ => (entity_type, sequence.clone()),
+            };
+            let entity = *entity_keys.entry(entity_key).or_insert_with(|| {
+                entities.push(Entity {
+                    entity_type,
+                    description: match entity_type {
+                        EntityType::Polymer => None,
+                        _ => sequence.first().cloned(),
+                    },
+                });
+                entities.len()
+            });
+
+            asym_ids.insert(key.clone(), asym_id(index));
+            asym_entities.insert(key, entity);
+        }
+
+        Layout {
+            entities,
+            asym_ids,
+            asym_entities,
+        }
+    }
+
+    fn write_entities<W: Write>(&self, layout: &Layout, writer: &mut W) -> io::Result<()> {
+        if layout.entities.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(writer, "loop_")?;
+        writeln!(writer, "_entity.id")?;
+        writeln!(writer, "_entity.type")?;
+        writeln!(writer, "_entity.pdbx_description")?;
+        for (index, entity) in layout.entities.iter().enumerate() {
+            writeln!(
+                writer,
+                "{} {} {}",
+                index + 1,
+                entity.entity_type.name(),
+                entity.description.as_deref().map_or("?".to_string(), quote)
+            )?;
+        }
+        writeln!(writer, "#")
+    }
+
+    fn write_atom_site<W: Write>(&self, molecule: &Molecule, layout: &Layout, writer: &mut W) -> io::Result<()> {
+        if molecule.atoms.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(writer, "loop_")?;
+        for column in [
+            "group_PDB",
+            "id",
+            "type_symbol",
+            "label_atom_id",
+            "label_alt_id",
+            "label_comp_id",
+            "label_asym_id",
+            "label_entity_id",
+            "label_seq_id",
+            "pdbx_PDB_ins_code",
+            "Cartn_x",
+            "Cartn_y",
+            "Cartn_z",
+            "occupancy",
+            "B_iso_or_equiv",
+            "pdbx_formal_charge",
+            "auth_seq_id",
+            "auth_comp_id",
+            "auth_asym_id",
+            "auth_atom_id",
+            "pdbx_PDB_model_num",
+        ] {
+            writeln!(writer, "_atom_site.{}", column)?;
+        }
+
+        for (block_index, (model_id, atoms)) in model_blocks(molecule).into_iter().enumerate() {
+            let model_number = model_id.unwrap_or(block_index + 1);
+            let mut seq_ids: HashMap<AsymKey, usize> = HashMap::new();
+            let mut previous: Option<&Atom> = None;
+
+            for atom in atoms.into_iter().map(|index| &molecule[index]) {
+                let key = AsymKey::of(atom);
+                let new_residue = previous.is_none_or(|previous| !same_residue(previous, atom));
+                let seq_id = seq_ids.entry(key.clone()).or_insert(0);
+                if new_residue {
+                    *seq_id += 1;
+                }
+                previous = Some(atom);
+
+                let asym_id = &layout.asym_ids[&key];
+                writeln!(
+                    writer,
+                    "{} {} {} {} {} {} {} {} {} {} {:.3} {:.3} {:.3} {:.2} {:.2} {} {} {} {} {} {}",
+                    if atom.is_hetatm { "HETATM" } else { "ATOM" },
+                    atom.id,
+                    if atom.element == Element::Unknown { "?".to_string() } else { atom.element.symbol().to_uppercase() },
+                    quote(&atom.name),
+                    optional_char(atom.alt_loc, "."),
+                    quote(&atom.residue_name),
+                    asym_id,
+                    layout.asym_entities[&key],
+                    if matches!(key, AsymKey::Polymer(_)) { seq_id.to_string() } else { ".".to_string() },
+                    optional_char(atom.ins_code, "?"),
+                    atom.position[0],
+                    atom.position[1],
+                    atom.position[2],
+                    atom.occupancy,
+                    atom.b_factor,
+                    atom.formal_charge,
+                    atom.residue_id,
+                    quote(&atom.residue_name),
+                    auth_asym_id(atom, asym_id),
+                    quote(&atom.name),
+                    model_number,
+                )?;
+            }
+        }
+
+        writeln!(writer, "#")
+    }
+
+    // Covalent links between residues other than the regular polymer backbone,
+    // disulfides and metal coordination
+    fn write_struct_conn<W: Write>(&self, molecule: &Molecule, layout: &Layout, writer: &mut W) -> io::Result<()> {
+        // Models repeat the same links, so they are listed once per serial pair
+        let mut seen = HashSet::new();
+        let connections: Vec<(&str, &Atom, &Atom)> = molecule
+            .bonds
+            .iter()
+            .map(|bond| (&molecule[bond.atom1], &molecule[bond.atom2]))
+            .filter(|(a, b)| !same_residue(a, b) && !is_polymer_link(a, b))
+            .filter(|(a, b)| seen.insert((a.id.min(b.id), a.id.max(b.id))))
+            .map(|(a, b)| {
+                let conn_type = if is_metal(a.element) || is_metal(b.element) {
+                    "metalc"
+                } else if a.element == Element::S && b.element == Element::S {
+                    "disulf"
+                } else {
+                    "covale"
+                };
+                (conn_type, a, b)
+            })
+            .collect();
+
+        if connections.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(writer, "loop_")?;
+        for column in [
+            "id",
+            "conn_type_id",
+            "ptnr1_label_asym_id",
+            "ptnr1_label_comp_id",
+            "ptnr1_label_atom_id",
+            "ptnr1_auth_asym_id",
+            "ptnr1_auth_seq_id",
+            "pdbx_ptnr1_PDB_ins_code",
+            "ptnr2_label_asym_id",
+            "ptnr2_label_comp_id",
+            "ptnr2_label_atom_id",
+            "ptnr2_auth_asym_id",
+            "ptnr2_auth_seq_id",
+            "pdbx_ptnr2_PDB_ins_code",
+        ] {
+            writeln!(writer, "_struct_conn.{}", column)?;
+        }
+
+        let mut counters: HashMap<&str, usize> = HashMap::new();
+        for (conn_type, a, b) in connections {
+            let counter = counters.entry(conn_type).or_insert(0);
+            *counter += 1;
+
+            let asym_a = &layout.asym_ids[&AsymKey::of(a)];
+            let asym_b = &layout.asym_ids[&AsymKey::of(b)];
+            writeln!(
+                writer,
+                "{}{} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                conn_type,
+                counter,
+                conn_type,
+                asym_a,
+                quote(&a.residue_name),
+                quote(&a.name),
+                auth_asym_id(a, asym_a),
+                a.residue_id,
+                optional_char(a.ins_code, "?"),
+                asym_b,
+                quote(&b.residue_name),
+                quote(&b.name),
+                auth_asym_id(b, asym_b),
+                b.residue_id,
+                optional_char(b.ins_code, "?"),
+            )?;
+        }
+
+        writeln!(writer, "#")
+    }
+}
+
+fn same_residue(a: &Atom, b: &Atom) -> bool {
+    a.chain_id == b.chain_id
+        && a.residue_id == b.residue_id
+        && a.ins_code == b.ins_code
+        && a.residue_name == b.residue_name
+}
+
+// Peptide C-N and nucleic acid O3'-P bonds are implied by the polymer sequence
+fn is_polymer_link(a: &Atom, b: &Atom) -> bool {
+    if a.is_hetatm || b.is_hetatm || a.chain_id != b.chain_id {
+        return false;
+    }
+    let names = (a.name.as_str(), b.name.as_str());
+    matches!(names, ("C", "N") | ("N", "C") | ("O3'", "P") | ("P", "O3'"))
+}
+
+fn is_metal(element: Element) -> bool {
+    matches!(
+        element,
+        Element::Li
+            | Element::Be
+            | Element::Na
+            | Element::Mg
+            | Element::Al
+            | Element::K
+            | Element::Ca
+            | Element::Mn
+            | Element::Fe
+            | Element::Co
+            | Element::Ni
+            | Element::Cu
+            | Element::Zn
+    )
+}
+
+// A, B, ..., Z, AA, BA, ..., as used by the PDB for label_asym_id
+fn asym_id(index: usize) -> String {
+    let mut id = String::new();
+    let mut value = index;
+    loop {
+        id.push((b'A' + (value % 26) as u8) as char);
+        value /= 26;
+        if value == 0 {
+            break;
+        }
+        value -= 1;
+    }
+    id
+}
+
+fn auth_asym_id(atom: &Atom, label_asym_id: &str) -> String {
+    if atom.chain_id == ' ' {
+        label_asym_id.to_string()
+    } else {
+        quote(&atom.chain_id.to_string())
+    }
+}
+
+// A blank alt_id is inapplicable ("."), a blank insertion code is written as
+// unknown ("?") the way wwPDB files do
+fn optional_char(value: char, blank: &str) -> String {
+    if value == ' ' { blank.to_string() } else { quote(&value.to_string()) }
+}
+
+/// Quotes a CIF value when it is empty, contains whitespace or quotes, or could
+/// be mistaken for a reserved word or a data name.
+pub(crate) fn quote(value: &str) -> String {
+    if value.is_empty() {
+        return "?".to_string();
+    }
+
+    let lowercase = value.to_ascii_lowercase();
+    let needs_quotes = value.contains(char::is_whitespace)
+        || value.contains(['\'', '"'])
+        || value.starts_with(['_', '#', '$', '[', ']', ';'])
+        || value == "."
+        || value == "?"
+        || lowercase.starts_with("data_")
+        || lowercase.starts_with("save_")
+        || ["loop_", "stop_", "global_"].contains(&lowercase.as_str());
+
+    if !needs_quotes {
+        value.to_string()
+    } else if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
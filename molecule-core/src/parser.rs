@@ -1,11 +1,18 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 
-use crate::structure::{Atom, Element, Molecule};
+use crate::structure::{Atom, AtomIndex, BondOrder, Element, Molecule, UnitCell};
 
 pub struct PdbParser;
 
+impl Default for PdbParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PdbParser {
     pub fn new() -> Self {
         Self
@@ -26,11 +33,25 @@ impl PdbParser {
     
     pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Molecule> {
         let mut molecule = Molecule::new();
+        let mut connections = Vec::new();
         
         for line in reader.lines() {
             let line = line?;
             
-            if line.starts_with("MODEL ") {
+            if line.starts_with("HEADER") {
+                self.parse_header_line(&line, &mut molecule);
+            } else if line.starts_with("TITLE ") {
+                let text = line.get(10..).unwrap_or("").trim();
+                let title = molecule.metadata.title.get_or_insert_with(String::new);
+                if !title.is_empty() && !text.is_empty() {
+                    title.push(' ');
+                }
+                title.push_str(text);
+            } else if line.starts_with("CRYST1") {
+                self.parse_cryst1_line(&line, &mut molecule);
+            } else if line.starts_with("CONECT") {
+                connections.extend(self.parse_conect_line(&line));
+            } else if line.starts_with("MODEL ") {
                 if let Some(model_id) = self.parse_model_line(&line) {
                     molecule.start_model(model_id);
                 }
@@ -46,12 +67,82 @@ impl PdbParser {
             // For now we ignore TER, ANISOU and other records
         }
         
-        // Calculate bonds based on distances
-        molecule.calculate_bonds();
+        // Explicit CONECT bonds first. Serials repeat in every model, so the
+        // records apply to each model and to the atoms outside any model; each
+        // bond is usually listed from both ends
+        let mut groups: Vec<Vec<AtomIndex>> = molecule.models.iter().map(|model| model.atoms.clone()).collect();
+        let mut in_model = vec![false; molecule.atoms.len()];
+        for index in groups.iter().flatten() {
+            in_model[index.0] = true;
+        }
+        groups.push((0..molecule.atoms.len()).filter(|&i| !in_model[i]).map(AtomIndex).collect());
+        let mut seen = HashSet::new();
+        for group in groups {
+            let by_serial: HashMap<usize, AtomIndex> = group.iter().map(|&index| (molecule[index].id, index)).collect();
+            for &(serial1, serial2) in &connections {
+                if let (Some(&atom1), Some(&atom2)) = (by_serial.get(&serial1), by_serial.get(&serial2))
+                    && atom1 != atom2
+                    && seen.insert((atom1.min(atom2), atom1.max(atom2)))
+                {
+                    molecule.add_bond(atom1.min(atom2), atom1.max(atom2), BondOrder::Single);
+                }
+            }
+        }
+        
+        // Template bonds for standard residues, distance bonds for the rest
+        molecule.calculate_template_bonds();
         
         Ok(molecule)
     }
     
+    fn parse_header_line(&self, line: &str, molecule: &mut Molecule) {
+        let field = |start: usize, end: usize| {
+            line.get(start..end.min(line.len()))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        
+        molecule.metadata.classification = field(10, 50);
+        molecule.metadata.deposition_date = field(50, 59);
+        molecule.metadata.id_code = field(62, 66);
+    }
+    
+    fn parse_cryst1_line(&self, line: &str, molecule: &mut Molecule) {
+        let value = |start: usize, end: usize| line.get(start..end)?.trim().parse::<f32>().ok();
+        
+        if let (Some(a), Some(b), Some(c), Some(alpha), Some(beta), Some(gamma)) = (
+            value(6, 15),
+            value(15, 24),
+            value(24, 33),
+            value(33, 40),
+            value(40, 47),
+            value(47, 54),
+        ) {
+            molecule.unit_cell = Some(UnitCell::from_parameters(a, b, c, alpha, beta, gamma));
+        }
+        
+        molecule.metadata.space_group = line
+            .get(55..66.min(line.len()))
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(str::to_string);
+    }
+    
+    fn parse_conect_line(&self, line: &str) -> Vec<(usize, usize)> {
+        let serial = |start: usize| line.get(start..start + 5)?.trim().parse::<usize>().ok();
+        
+        let Some(atom_id) = serial(6) else {
+            return Vec::new();
+        };
+        
+        [11, 16, 21, 26]
+            .iter()
+            .filter_map(|&start| serial(start))
+            .map(|bonded_id| (atom_id, bonded_id))
+            .collect()
+    }
+    
     fn parse_model_line(&self, line: &str) -> Option<usize> {
         if line.len() < 14 {
             return None;
@@ -60,7 +151,7 @@ impl PdbParser {
         line[10..14].trim().parse::<usize>().ok()
     }
     
-    fn parse_atom_line(&self, line: &str) -> Option<Atom> {
+    pub(crate) fn parse_atom_line(&self, line: &str) -> Option<Atom> {
         if line.len() < 54 {
             return None;
         }
@@ -78,7 +169,9 @@ impl PdbParser {
             ' '
         };
         
-        let residue_name = line[17..20].trim().to_string();
+        // Column 21 is blank in standard files but holds the fourth character of
+        // longer residue names written by CHARMM and GROMACS tools
+        let residue_name = line[17..21].trim().to_string();
         
         let chain_id = line[21..22].chars().next().unwrap_or('A');
         let residue_id = line[22..26].trim().parse::<usize>().ok()?;
@@ -113,12 +206,15 @@ impl PdbParser {
                 Element::from_symbol(element_str)
             } else {
                 // Fallback to inferring from atom name
-                infer_element_from_atom_name(&atom_name)
+                infer_element(&atom_name, &residue_name)
             }
         } else {
-            infer_element_from_atom_name(&atom_name)
+            infer_element(&atom_name, &residue_name)
         };
         
+        // Formal charge (columns 79-80), written as e.g. "2+" or "1-"
+        let formal_charge = line.get(78..80).map_or(0, parse_formal_charge);
+        
         Some(Atom {
             id: atom_id,
             name: atom_name,
@@ -132,12 +228,43 @@ impl PdbParser {
             alt_loc,
             ins_code,
             is_hetatm: false, // This will be set by the caller
+            formal_charge,
+            ..Atom::default()
         })
     }
 }
 
+fn parse_formal_charge(field: &str) -> i8 {
+    let field = field.trim();
+    let magnitude = field
+        .chars()
+        .find(|c| c.is_ascii_digit())
+        .and_then(|c| c.to_digit(10))
+        .unwrap_or(1) as i8;
+    
+    if field.contains('-') {
+        -magnitude
+    } else if field.contains('+') {
+        magnitude
+    } else {
+        0
+    }
+}
+
+// Monatomic ions are named after their element in a residue of the same
+// name, e.g. ZN in ZN or CL in CL; everything else goes by the atom name
+pub(crate) fn infer_element(atom_name: &str, residue_name: &str) -> Element {
+    if atom_name == residue_name {
+        let element = Element::from_symbol(atom_name);
+        if element != Element::Unknown {
+            return element;
+        }
+    }
+    infer_element_from_atom_name(atom_name)
+}
+
 // Helper function to better infer elements from atom names
-fn infer_element_from_atom_name(atom_name: &str) -> Element {
+pub(crate) fn infer_element_from_atom_name(atom_name: &str) -> Element {
     // First character is usually the element for common elements
     let trimmed_name = atom_name.trim();
     if trimmed_name.is_empty() {
@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use crate::geometry::{distance, dot, sub, torsion};
+use crate::kekulize::kekulize;
+use crate::structure::{Atom, AtomIndex, BondOrder, Element, Molecule};
+use crate::templates::residue_template;
+
+// Rings larger than this are not considered for aromaticity
+const MAX_RING_SIZE: usize = 7;
+// Largest ring torsion, in degrees, for a ring to count as planar
+const PLANAR_TORSION: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hybridization {
+    Sp,
+    Sp2,
+    Sp3,
+}
+
+impl Molecule {
+    /// Assigns double, triple and aromatic bonds to ligands from their 3D
+    /// geometry.
+    ///
+    /// Hybridization comes from bond angles, or from the bond length for
+    /// terminal atoms. Bonds of planar rings are kekulized, and rings whose
+    /// π electron count satisfies Hückel's 4n+2 rule become aromatic; other
+    /// multiple bonds are placed by comparing lengths with reference values.
+    /// Only single bonds between atoms of residues without a built-in
+    /// template are changed, so run this before `apply_components`.
+    pub fn perceive_bond_orders(&mut self) {
+        let atom_count = self.atoms.len();
+        let eligible: Vec<bool> = self
+            .atoms
+            .iter()
+            .map(|atom| residue_template(&self.residues[atom.residue.0].name).is_none())
+            .collect();
+        let candidate: Vec<bool> = self
+            .bonds
+            .iter()
+            .map(|bond| {
+                bond.order == BondOrder::Single
+                    && bond.atom1.0 < atom_count
+                    && bond.atom2.0 < atom_count
+                    && eligible[bond.atom1.0]
+                    && eligible[bond.atom2.0]
+            })
+            .collect();
+
+        let mut neighbors: Vec<Vec<(usize, usize)>> = vec![Vec::new(); atom_count];
+        for (index, bond) in self.bonds.iter().enumerate() {
+            if bond.atom1.0 < atom_count && bond.atom2.0 < atom_count {
+                neighbors[bond.atom1.0].push((index, bond.atom2.0));
+                neighbors[bond.atom2.0].push((index, bond.atom1.0));
+            }
+        }
+
+        let mut needs: Vec<i32> = (0..atom_count)
+            .map(|atom| if eligible[atom] { self.pi_bonds_needed(atom, &neighbors[atom]) } else { 0 })
+            .collect();
+
+        // Terminal atoms first: their bond length is the only evidence
+        let mut terminal: Vec<usize> = (0..self.bonds.len())
+            .filter(|&index| candidate[index])
+            .filter(|&index| {
+                let bond = &self.bonds[index];
+                neighbors[bond.atom1.0].len() == 1 || neighbors[bond.atom2.0].len() == 1
+            })
+            .collect();
+        terminal.sort_by(|&a, &b| self.bond_excess(a).total_cmp(&self.bond_excess(b)));
+        for index in terminal {
+            self.assign_by_length(index, &mut needs);
+        }
+
+        // Ring search and kekulization run on the ligand atoms and their
+        // bonded neighbours only, so rings of residues with templates, whole
+        // or truncated, never take part
+        let (fragment, fragment_atoms, fragment_bonds) = self.ligand_fragment(&eligible);
+
+        // Planar rings are kekulized as a whole, since lengths alternate poorly
+        let rings: Vec<Vec<usize>> = fragment
+            .graph()
+            .rings()
+            .into_iter()
+            .filter(|ring| ring.len() <= MAX_RING_SIZE)
+            .map(|ring| ring.into_iter().map(|atom| fragment_atoms[atom.0]).collect::<Vec<usize>>())
+            .filter(|ring| self.is_planar(ring))
+            .filter(|ring| {
+                // Ring atoms either still need a π bond, already have an
+                // exocyclic one (as in uracil), or can donate a lone pair
+                ring.iter().all(|&atom| {
+                    let exocyclic = neighbors[atom].iter().any(|&(bond, _)| self.bonds[bond].order == BondOrder::Double);
+                    eligible[atom]
+                        && (needs[atom] > 0
+                            || exocyclic
+                            || matches!(self.atoms[atom].element, Element::N | Element::O | Element::S))
+                })
+            })
+            .collect();
+        let ring_bonds: HashSet<usize> = rings
+            .iter()
+            .flat_map(|ring| ring_bond_indices(ring, &neighbors))
+            .filter(|&index| candidate[index] && self.bonds[index].order == BondOrder::Single)
+            .collect();
+        let mut fragment = fragment;
+        for (bond, &index) in fragment.bonds.iter_mut().zip(&fragment_bonds) {
+            bond.order = if ring_bonds.contains(&index) { BondOrder::Aromatic } else { self.bonds[index].order };
+        }
+        if let Some(orders) = kekulize(&fragment) {
+            for (&order, &index) in orders.iter().zip(&fragment_bonds) {
+                if ring_bonds.contains(&index) && order == BondOrder::Double {
+                    self.bonds[index].order = order;
+                    needs[self.bonds[index].atom1.0] -= 1;
+                    needs[self.bonds[index].atom2.0] -= 1;
+                }
+            }
+        }
+
+        let mut remaining: Vec<usize> = (0..self.bonds.len())
+            .filter(|&index| candidate[index] && self.bonds[index].order == BondOrder::Single)
+            .collect();
+        remaining.sort_by(|&a, &b| self.bond_excess(a).total_cmp(&self.bond_excess(b)));
+        for index in remaining {
+            self.assign_by_length(index, &mut needs);
+        }
+
+        for ring in &rings {
+            if self.is_huckel_aromatic(ring, &neighbors) {
+                for index in ring_bond_indices(ring, &neighbors) {
+                    self.bonds[index].order = BondOrder::Aromatic;
+                }
+            }
+        }
+    }
+
+    // Number of π bonds an atom should take part in, net of existing ones
+    fn pi_bonds_needed(&self, atom: usize, neighbors: &[(usize, usize)]) -> i32 {
+        let existing: i32 = neighbors
+            .iter()
+            .map(|&(bond, _)| match self.bonds[bond].order {
+                BondOrder::Single | BondOrder::Aromatic => 0,
+                BondOrder::Double => 1,
+                BondOrder::Triple => 2,
+            })
+            .sum();
+
+        let degree = neighbors.len();
+        let hybridization = match degree {
+            0 => return 0,
+            1 => {
+                let (bond, _) = neighbors[0];
+                match self.closest_order(bond) {
+                    Some(BondOrder::Triple) => Hybridization::Sp,
+                    Some(BondOrder::Double) => Hybridization::Sp2,
+                    _ => Hybridization::Sp3,
+                }
+            }
+            _ => {
+                let angle = self.mean_angle(atom, neighbors);
+                if degree == 2 && angle > 160.0 {
+                    Hybridization::Sp
+                } else if degree <= 3 && angle > 115.0 {
+                    Hybridization::Sp2
+                } else {
+                    Hybridization::Sp3
+                }
+            }
+        };
+
+        let needed = match (self.atoms[atom].element, hybridization) {
+            (_, Hybridization::Sp3) => 0,
+            (Element::C, Hybridization::Sp) => 2,
+            (Element::C, Hybridization::Sp2) => 1,
+            (Element::N, Hybridization::Sp) => 2,
+            // Planar three-coordinate nitrogen (amides, anilines) keeps its lone pair
+            (Element::N, Hybridization::Sp2) => i32::from(degree <= 2),
+            (Element::O | Element::S, Hybridization::Sp2) => i32::from(degree == 1),
+            _ => 0,
+        };
+        (needed - existing).max(0)
+    }
+
+    fn assign_by_length(&mut self, index: usize, needs: &mut [i32]) {
+        let (a, b) = (self.bonds[index].atom1.0, self.bonds[index].atom2.0);
+        let available = needs[a].min(needs[b]);
+        let order = match self.closest_order(index) {
+            Some(BondOrder::Triple) if available >= 2 => BondOrder::Triple,
+            Some(BondOrder::Triple | BondOrder::Double) if available >= 1 => BondOrder::Double,
+            _ => return,
+        };
+        let used = if order == BondOrder::Triple { 2 } else { 1 };
+        needs[a] -= used;
+        needs[b] -= used;
+        self.bonds[index].order = order;
+    }
+
+    // Order whose reference length is nearest to the observed one
+    fn closest_order(&self, index: usize) -> Option<BondOrder> {
+        let bond = &self.bonds[index];
+        let references = reference_lengths(self.atoms[bond.atom1.0].element, self.atoms[bond.atom2.0].element)?;
+        let length = self.length(bond.atom1.0, bond.atom2.0);
+        [BondOrder::Single, BondOrder::Double, BondOrder::Triple]
+            .into_iter()
+            .zip(references)
+            .filter(|(_, reference)| *reference > 0.0)
+            .min_by(|a, b| (a.1 - length).abs().total_cmp(&(b.1 - length).abs()))
+            .map(|(order, _)| order)
+    }
+
+    // How much shorter than a single bond, so the clearest multiple bonds go first
+    fn bond_excess(&self, index: usize) -> f32 {
+        let bond = &self.bonds[index];
+        let single = reference_lengths(self.atoms[bond.atom1.0].element, self.atoms[bond.atom2.0].element)
+            .map_or(0.0, |references| references[0]);
+        self.length(bond.atom1.0, bond.atom2.0) - single
+    }
+
+    fn length(&self, a: usize, b: usize) -> f32 {
+        distance(self.atoms[a].position, self.atoms[b].position)
+    }
+
+    fn mean_angle(&self, atom: usize, neighbors: &[(usize, usize)]) -> f32 {
+        let center = self.atoms[atom].position;
+        let mut total = 0.0;
+        let mut count = 0;
+        for (i, &(_, first)) in neighbors.iter().enumerate() {
+            for &(_, second) in &neighbors[i + 1..] {
+                let u = sub(self.atoms[first].position, center);
+                let v = sub(self.atoms[second].position, center);
+                let cosine = dot(u, v) / (dot(u, u).sqrt() * dot(v, v).sqrt());
+                total += cosine.clamp(-1.0, 1.0).acos().to_degrees();
+                count += 1;
+            }
+        }
+        total / count as f32
+    }
+
+    // The eligible atoms, the atoms bonded to them and every bond touching an
+    // eligible atom, with the atom and bond indices they have in `self`
+    fn ligand_fragment(&self, eligible: &[bool]) -> (Molecule, Vec<usize>, Vec<usize>) {
+        let mut fragment = Molecule::new();
+        let mut atoms = Vec::new();
+        let mut bonds = Vec::new();
+        let mut index_of = vec![None; self.atoms.len()];
+
+        for (index, bond) in self.bonds.iter().enumerate() {
+            let (a, b) = (bond.atom1.0, bond.atom2.0);
+            if a >= self.atoms.len() || b >= self.atoms.len() || !(eligible[a] || eligible[b]) {
+                continue;
+            }
+            let mut local = [0; 2];
+            for (slot, atom) in local.iter_mut().zip([a, b]) {
+                *slot = *index_of[atom].get_or_insert_with(|| {
+                    let source = &self.atoms[atom];
+                    fragment.atoms.push(Atom {
+                        element: source.element,
+                        formal_charge: source.formal_charge,
+                        ..Atom::default()
+                    });
+                    atoms.push(atom);
+                    atoms.len() - 1
+                });
+            }
+            fragment.add_bond(AtomIndex(local[0]), AtomIndex(local[1]), bond.order);
+            bonds.push(index);
+        }
+
+        (fragment, atoms, bonds)
+    }
+
+    fn is_planar(&self, ring: &[usize]) -> bool {
+        let n = ring.len();
+        (0..n).all(|i| {
+            let [a, b, c, d] = [0, 1, 2, 3].map(|offset| self.atoms[ring[(i + offset) % n]].position);
+            torsion(a, b, c, d).abs() <= PLANAR_TORSION
+        })
+    }
+
+    // Hückel count on the kekulized ring: ring or exocyclic C=C bonds give one
+    // electron per atom, exocyclic C=O none, and N, O or S lone pairs two
+    fn is_huckel_aromatic(&self, ring: &[usize], neighbors: &[Vec<(usize, usize)>]) -> bool {
+        let mut electrons = 0;
+        for &atom in ring {
+            let double = neighbors[atom]
+                .iter()
+                .find(|&&(bond, _)| matches!(self.bonds[bond].order, BondOrder::Double | BondOrder::Aromatic));
+            electrons += match double {
+                Some(&(_, other)) if ring.contains(&other) || self.atoms[other].element == Element::C => 1,
+                Some(_) => 0,
+                None if matches!(self.atoms[atom].element, Element::N | Element::O | Element::S) => 2,
+                None => return false,
+            };
+        }
+        electrons % 4 == 2
+    }
+}
+
+fn ring_bond_indices(ring: &[usize], neighbors: &[Vec<(usize, usize)>]) -> Vec<usize> {
+    (0..ring.len())
+        .filter_map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            neighbors[a].iter().find(|&&(_, other)| other == b).map(|&(bond, _)| bond)
+        })
+        .collect()
+}
+
+// Typical single, double and triple bond lengths in Å; 0 where the order does not occur
+fn reference_lengths(a: Element, b: Element) -> Option<[f32; 3]> {
+    use Element::{C, N, O, S};
+
+    match (a, b) {
+        (C, C) => Some([1.54, 1.34, 1.20]),
+        (C, N) | (N, C) => Some([1.47, 1.28, 1.16]),
+        (C, O) | (O, C) => Some([1.43, 1.21, 0.0]),
+        (C, S) | (S, C) => Some([1.82, 1.67, 0.0]),
+        (N, N) => Some([1.45, 1.25, 1.10]),
+        (N, O) | (O, N) => Some([1.40, 1.21, 0.0]),
+        _ => None,
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::parser::{infer_element, PdbParser};
+use crate::structure::{Atom, Element, Molecule};
+use crate::writer::{format_atom_name, model_blocks};
+
+/// Reader for PQR files as produced by PDB2PQR and consumed by APBS.
+///
+/// Records follow the PDB column layout up to the coordinates; the charge and
+/// radius that replace occupancy and B-factor are read as whitespace-delimited
+/// fields, since PQR producers do not agree on their column widths.
+pub struct PqrParser {
+    pdb: PdbParser,
+}
+
+impl Default for PqrParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PqrParser {
+    pub fn new() -> Self {
+        Self { pdb: PdbParser::new() }
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<Molecule> {
+        let reader = BufReader::new(content.as_bytes());
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Molecule> {
+        let mut molecule = Molecule::new();
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.starts_with("MODEL ") {
+                if let Some(model_id) = line.split_whitespace().nth(1).and_then(|id| id.parse().ok()) {
+                    molecule.start_model(model_id);
+                }
+            } else if line.starts_with("ENDMDL") {
+                molecule.end_model();
+            } else if line.starts_with("ATOM") || line.starts_with("HETATM") {
+                let is_hetatm = line.starts_with("HETATM");
+                if let Some(mut atom) = self.parse_atom_line(&line) {
+                    atom.is_hetatm = is_hetatm;
+                    molecule.add_atom(atom);
+                }
+            }
+        }
+
+        // Template bonds for standard residues, distance bonds for the rest
+        molecule.calculate_template_bonds();
+
+        Ok(molecule)
+    }
+
+    fn parse_atom_line(&self, line: &str) -> Option<Atom> {
+        let columns = line.get(..54).and_then(|fixed| self.pdb.parse_atom_line(fixed));
+        let (mut atom, rest) = match columns {
+            Some(atom) => (atom, &line[54..]),
+            None => return parse_free_format_atom_line(line),
+        };
+
+        let mut fields = rest.split_whitespace();
+        atom.charge = fields.next().and_then(|v| v.parse().ok());
+        atom.radius = fields.next().and_then(|v| v.parse().ok());
+
+        // The PDB columns above stop before the element, so read it here
+        atom.element = match line.get(76..78).map(str::trim) {
+            Some(symbol) if !symbol.is_empty() && symbol.chars().all(|c| c.is_ascii_alphabetic()) => Element::from_symbol(symbol),
+            _ => infer_element(&atom.name, &atom.residue_name),
+        };
+
+        Some(atom)
+    }
+}
+
+// Fallback for PQR files without PDB column alignment:
+// record serial name resName [chainID] resSeq x y z charge radius
+fn parse_free_format_atom_line(line: &str) -> Option<Atom> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (chain_id, rest) = match fields.len() {
+        11 => (fields[4].chars().next()?, &fields[5..]),
+        10 => (' ', &fields[4..]),
+        _ => return None,
+    };
+
+    let name = fields[2].to_string();
+    Some(Atom {
+        id: fields[1].parse().ok()?,
+        element: infer_element(&name, fields[3]),
+        name,
+        residue_name: fields[3].to_string(),
+        chain_id,
+        residue_id: rest[0].parse().ok()?,
+        position: [rest[1].parse().ok()?, rest[2].parse().ok()?, rest[3].parse().ok()?],
+        charge: Some(rest[4].parse().ok()?),
+        radius: Some(rest[5].parse().ok()?),
+        ..Atom::default()
+    })
+}
+
+/// Writer for PQR files. Atoms without a charge or radius are written with 0.0.
+pub struct PqrWriter;
+
+impl Default for PqrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PqrWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        for (model_id, atoms) in model_blocks(molecule) {
+            if let Some(model_id) = model_id {
+                writeln!(writer, "MODEL     {:>4}", model_id)?;
+            }
+            let atoms: Vec<&Atom> = atoms.into_iter().map(|index| &molecule[index]).collect();
+            self.write_atoms(&atoms, &mut writer)?;
+            if model_id.is_some() {
+                writeln!(writer, "ENDMDL")?;
+            }
+        }
+
+        writeln!(writer, "END")
+    }
+
+    fn write_atoms<W: Write>(&self, atoms: &[&Atom], writer: &mut W) -> io::Result<()> {
+        for (i, atom) in atoms.iter().enumerate() {
+            writeln!(
+                writer,
+                "{:<6}{:>5} {}{}{:>3} {}{:>4}{}   {:8.3}{:8.3}{:8.3} {:7.4} {:6.4}",
+                if atom.is_hetatm { "HETATM" } else { "ATOM" },
+                atom.id,
+                format_atom_name(&atom.name, atom.element),
+                atom.alt_loc,
+                atom.residue_name,
+                atom.chain_id,
+                atom.residue_id,
+                atom.ins_code,
+                atom.position[0],
+                atom.position[1],
+                atom.position[2],
+                atom.charge.unwrap_or(0.0),
+                atom.radius.unwrap_or(0.0),
+            )?;
+
+            let chain_ends = atoms.get(i + 1).is_none_or(|next| next.chain_id != atom.chain_id);
+            if chain_ends && !atom.is_hetatm {
+                writeln!(writer, "TER")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::invalid_data;
+use crate::structure::{Atom, AtomIndex, BondOrder, Element, Molecule};
+
+/// Reader for CHARMM/NAMD/X-PLOR protein structure files (PSF).
+///
+/// Atoms get their CHARMM type, charge and mass, and bonds come from the
+/// !NBOND section. Coordinates are zero until a frame is applied, for example
+/// from a `DcdReader`.
+pub struct PsfParser;
+
+impl Default for PsfParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PsfParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_string(&self, content: &str) -> io::Result<Molecule> {
+        let reader = BufReader::new(content.as_bytes());
+
+        self.parse_reader(reader)
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Molecule> {
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        if !lines.first().is_some_and(|line| line.starts_with("PSF")) {
+            return Err(invalid_data("not a PSF file: missing PSF header"));
+        }
+
+        let mut molecule = Molecule::new();
+
+        let (atom_count, atom_start) = find_section(&lines, "!NATOM")
+            .ok_or_else(|| invalid_data("missing !NATOM section"))?;
+        let atom_lines = lines
+            .get(atom_start..atom_start + atom_count)
+            .ok_or_else(|| invalid_data("file ends inside the !NATOM section"))?;
+        for line in atom_lines {
+            let atom = parse_atom_line(line)
+                .ok_or_else(|| invalid_data(&format!("malformed atom line: {:?}", line)))?;
+            molecule.add_atom(atom);
+        }
+
+        if let Some((bond_count, bond_start)) = find_section(&lines, "!NBOND") {
+            // Atom index pairs, four pairs per line
+            let indices: Vec<usize> = lines[bond_start..]
+                .iter()
+                .flat_map(|line| line.split_whitespace())
+                .take(2 * bond_count)
+                .map(|index| index.parse().map_err(|_| invalid_data(&format!("invalid bond index {:?}", index))))
+                .collect::<io::Result<_>>()?;
+            if indices.len() < 2 * bond_count {
+                return Err(invalid_data("file ends inside the !NBOND section"));
+            }
+
+            for pair in indices.chunks_exact(2) {
+                if pair.iter().any(|&index| index == 0 || index > atom_count) {
+                    return Err(invalid_data("bond references a missing atom"));
+                }
+                molecule.add_bond(AtomIndex(pair[0] - 1), AtomIndex(pair[1] - 1), BondOrder::Single);
+            }
+        }
+
+        Ok(molecule)
+    }
+}
+
+// Returns the entry count and the index of the first line after a section title
+fn find_section(lines: &[String], title: &str) -> Option<(usize, usize)> {
+    lines.iter().enumerate().find_map(|(index, line)| {
+        if !line.contains(title) {
+            return None;
+        }
+        let count = line.split_whitespace().next()?.parse().ok()?;
+        Some((count, index + 1))
+    })
+}
+
+// ID SEGID RESID RESNAME NAME TYPE CHARGE MASS IMOVE
+fn parse_atom_line(line: &str) -> Option<Atom> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 8 {
+        return None;
+    }
+
+    // Residue numbers may carry an insertion code, e.g. "52A"
+    let resid = fields[2];
+    let digits_end = resid
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+        .map_or(resid.len(), |(i, _)| i);
+    let residue_id = resid[..digits_end].parse::<i64>().ok()?.max(0) as usize;
+    let ins_code = resid[digits_end..].chars().next().unwrap_or(' ');
+
+    let mass = fields[7].parse::<f32>().ok()?;
+
+    Some(Atom {
+        id: fields[0].parse().ok()?,
+        chain_id: chain_from_segment(fields[1]),
+        residue_id,
+        ins_code,
+        residue_name: fields[3].to_string(),
+        name: fields[4].to_string(),
+        atom_type: Some(fields[5].to_string()),
+        charge: Some(fields[6].parse().ok()?),
+        mass: Some(mass),
+        element: Element::from_mass(mass),
+        ..Atom::default()
+    })
+}
+
+// Single-letter segments are used directly; CHARMM-GUI style segments such as
+// "PROA" or "HETB" map to their trailing chain letter.
+fn chain_from_segment(segment: &str) -> char {
+    const PREFIXES: [&str; 6] = ["PRO", "DNA", "RNA", "HET", "GLC", "CAR"];
+
+    let mut chars = segment.chars();
+    match (chars.next(), chars.next()) {
+        (Some(chain), None) => chain,
+        _ => PREFIXES
+            .iter()
+            .find_map(|prefix| segment.strip_prefix(prefix))
+            .filter(|rest| rest.len() == 1)
+            .and_then(|rest| rest.chars().next())
+            .unwrap_or(' '),
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::invalid_input;
+use crate::kekulize::kekulize;
+use crate::structure::{BondOrder, Element, Molecule};
+
+/// Writer for MDL molfiles and SD files (V2000).
+///
+/// Each molecule becomes one record terminated by `$$$$`, so writing several
+/// molecules to the same stream produces a multi-record SD file. Aromatic
+/// bonds are written in Kekulé form, and `metadata.properties` become data
+/// items after the connection table.
+pub struct SdfWriter;
+
+impl Default for SdfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SdfWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes every molecule as a separate record of one SD file.
+    pub fn write_all<'a, I, W>(&self, molecules: I, mut writer: W) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a Molecule>,
+        W: Write,
+    {
+        for molecule in molecules {
+            self.write(molecule, &mut writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        self.write_molfile(molecule, &mut writer)?;
+
+        for (name, value) in &molecule.metadata.properties {
+            writeln!(writer, ">  <{}>", name)?;
+            for line in value.lines() {
+                writeln!(writer, "{}", line)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writeln!(writer, "$$$$")
+    }
+
+    /// Writes the header and connection table only, as a standalone molfile.
+    pub fn write_molfile<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        if molecule.atoms.len() > 999 || molecule.bonds.len() > 999 {
+            return Err(invalid_input(&format!(
+                "{} atoms and {} bonds do not fit a V2000 connection table",
+                molecule.atoms.len(),
+                molecule.bonds.len()
+            )));
+        }
+
+        // Aromatic bonds that cannot be kekulized are kept as MDL aromatic bonds
+        let orders = kekulize(molecule).unwrap_or_else(|| molecule.bonds.iter().map(|bond| bond.order).collect());
+
+        let name = molecule.metadata.title.as_deref().or(molecule.metadata.id_code.as_deref()).unwrap_or("");
+        writeln!(writer, "{}", name.lines().next().unwrap_or(""))?;
+        writeln!(writer, "  molecule          3D")?;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000",
+            molecule.atoms.len(),
+            molecule.bonds.len()
+        )?;
+
+        for atom in &molecule.atoms {
+            let symbol = if atom.element == Element::Unknown { "*" } else { atom.element.symbol() };
+            writeln!(
+                writer,
+                "{:>10.4}{:>10.4}{:>10.4} {:<3} 0{:>3}  0  0  0  0  0  0  0  0  0  0",
+                atom.position[0],
+                atom.position[1],
+                atom.position[2],
+                symbol,
+                charge_code(atom.formal_charge)
+            )?;
+        }
+
+        for (bond, order) in molecule.bonds.iter().zip(&orders) {
+            if bond.atom1.0 >= molecule.atoms.len() || bond.atom2.0 >= molecule.atoms.len() {
+                return Err(invalid_input(&format!(
+                    "bond {}-{} references an atom outside the molecule",
+                    bond.atom1.0, bond.atom2.0
+                )));
+            }
+            let order = match order {
+                BondOrder::Single => 1,
+                BondOrder::Double => 2,
+                BondOrder::Triple => 3,
+                BondOrder::Aromatic => 4,
+            };
+            writeln!(writer, "{:>3}{:>3}{:>3}  0", bond.atom1.0 + 1, bond.atom2.0 + 1, order)?;
+        }
+
+        // M  CHG supersedes the atom block charge field, and allows |charge| > 3
+        let charged: Vec<(usize, i8)> = molecule
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, atom)| atom.formal_charge != 0)
+            .map(|(i, atom)| (i + 1, atom.formal_charge))
+            .collect();
+        for chunk in charged.chunks(8) {
+            let mut line = format!("M  CHG{:>3}", chunk.len());
+            for (index, charge) in chunk {
+                line.push_str(&format!(" {:>3} {:>3}", index, charge));
+            }
+            writeln!(writer, "{}", line)?;
+        }
+
+        writeln!(writer, "M  END")
+    }
+}
+
+fn charge_code(charge: i8) -> u8 {
+    match charge {
+        3 => 1,
+        2 => 2,
+        1 => 3,
+        -1 => 5,
+        -2 => 6,
+        -3 => 7,
+        _ => 0,
+    }
+}
@@ -0,0 +1,378 @@
+use std::io;
+
+use crate::error::invalid_input;
+use crate::grid::CellGrid;
+use crate::structure::{AtomIndex, Element, Molecule};
+
+/// A parsed atom selection expression.
+///
+/// The grammar follows the usual viewer conventions:
+///
+/// ```text
+/// chain A and resi 50-80 and not hydrogen
+/// name CA CB or (resn HOH and b > 30)
+/// within 5 of resn HEM
+/// ```
+///
+/// Keywords are `all`, `none`, `hetatm`, `hydrogen`, `element`, `name`,
+/// `resn`, `resi`, `serial`, `chain`, `b` and `occupancy`, combined with
+/// `and`, `or`, `not` and parentheses. `name` and `resn` accept a trailing `*`
+/// wildcard; `resi` and `serial` accept `start-end` ranges. `within D of S`
+/// includes the atoms of `S` itself, `around D of S` excludes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    All,
+    None,
+    Hetatm,
+    Hydrogen,
+    Element(Vec<String>),
+    Name(Vec<String>),
+    ResName(Vec<String>),
+    ResId(Vec<(usize, usize)>),
+    Serial(Vec<(usize, usize)>),
+    Chain(Vec<char>),
+    Compare(Property, Comparison, f32),
+    Within(f32, Box<Expr>),
+    Around(f32, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Property {
+    BFactor,
+    Occupancy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Molecule {
+    /// Indices of the atoms matching a selection expression, in file order.
+    pub fn select(&self, expression: &str) -> io::Result<Vec<AtomIndex>> {
+        Ok(Selection::parse(expression)?.evaluate(self))
+    }
+}
+
+impl Selection {
+    pub fn parse(expression: &str) -> io::Result<Self> {
+        let tokens = tokenize(expression);
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let expr = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(token) => Err(invalid_input(&format!("unexpected '{}' in selection", token))),
+        }
+    }
+
+    /// Indices of the matching atoms, in file order.
+    pub fn evaluate(&self, molecule: &Molecule) -> Vec<AtomIndex> {
+        evaluate(&self.expr, molecule)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, selected)| *selected)
+            .map(|(index, _)| AtomIndex(index))
+            .collect()
+    }
+}
+
+fn evaluate(expr: &Expr, molecule: &Molecule) -> Vec<bool> {
+    let atoms = &molecule.atoms;
+    match expr {
+        Expr::All => vec![true; atoms.len()],
+        Expr::None => vec![false; atoms.len()],
+        Expr::Hetatm => atoms.iter().map(|atom| atom.is_hetatm).collect(),
+        Expr::Hydrogen => atoms.iter().map(|atom| atom.element == Element::H).collect(),
+        Expr::Element(symbols) => atoms
+            .iter()
+            .map(|atom| symbols.iter().any(|symbol| atom.element.symbol().eq_ignore_ascii_case(symbol)))
+            .collect(),
+        Expr::Name(patterns) => atoms
+            .iter()
+            .map(|atom| patterns.iter().any(|pattern| matches_pattern(pattern, &atom.name)))
+            .collect(),
+        Expr::ResName(patterns) => atoms
+            .iter()
+            .map(|atom| patterns.iter().any(|pattern| matches_pattern(pattern, &atom.residue_name)))
+            .collect(),
+        Expr::ResId(ranges) => atoms.iter().map(|atom| in_ranges(ranges, atom.residue_id)).collect(),
+        Expr::Serial(ranges) => atoms.iter().map(|atom| in_ranges(ranges, atom.id)).collect(),
+        Expr::Chain(ids) => atoms.iter().map(|atom| ids.contains(&atom.chain_id)).collect(),
+        Expr::Compare(property, comparison, value) => atoms
+            .iter()
+            .map(|atom| {
+                let actual = match property {
+                    Property::BFactor => atom.b_factor,
+                    Property::Occupancy => atom.occupancy,
+                };
+                compare(*comparison, actual, *value)
+            })
+            .collect(),
+        Expr::Within(distance, inner) | Expr::Around(distance, inner) => {
+            let inner = evaluate(inner, molecule);
+            let centers = CellGrid::new(
+                *distance,
+                atoms.iter().enumerate().filter(|&(i, _)| inner[i]).map(|(i, atom)| (i, atom.position)),
+            );
+            let cutoff = distance * distance;
+            let exclude_inner = matches!(expr, Expr::Around(..));
+            // Models of an ensemble overlap in space but never neighbor each other
+            let models: Vec<_> = atoms.iter().map(|atom| molecule[molecule[atom.residue].chain].model).collect();
+
+            atoms
+                .iter()
+                .zip(&inner)
+                .enumerate()
+                .map(|(i, (atom, &selected))| {
+                    if selected {
+                        return !exclude_inner;
+                    }
+                    centers.candidates(atom.position).filter(|&center| models[center] == models[i]).any(|center| {
+                        let center = atoms[center].position;
+                        let dx = atom.position[0] - center[0];
+                        let dy = atom.position[1] - center[1];
+                        let dz = atom.position[2] - center[2];
+                        dx * dx + dy * dy + dz * dz <= cutoff
+                    })
+                })
+                .collect()
+        }
+        Expr::Not(inner) => evaluate(inner, molecule).into_iter().map(|selected| !selected).collect(),
+        Expr::And(left, right) => {
+            let right = evaluate(right, molecule);
+            evaluate(left, molecule).into_iter().zip(right).map(|(a, b)| a && b).collect()
+        }
+        Expr::Or(left, right) => {
+            let right = evaluate(right, molecule);
+            evaluate(left, molecule).into_iter().zip(right).map(|(a, b)| a || b).collect()
+        }
+    }
+}
+
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+fn in_ranges(ranges: &[(usize, usize)], value: usize) -> bool {
+    ranges.iter().any(|&(start, end)| (start..=end).contains(&value))
+}
+
+fn compare(comparison: Comparison, actual: f32, value: f32) -> bool {
+    match comparison {
+        Comparison::Less => actual < value,
+        Comparison::LessEqual => actual <= value,
+        Comparison::Greater => actual > value,
+        Comparison::GreaterEqual => actual >= value,
+        Comparison::Equal => actual == value,
+        Comparison::NotEqual => actual != value,
+    }
+}
+
+// Words, parentheses and comparison operators; operators may touch their operands
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if is_operator_char(c) {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|&&c| is_operator_char(c)) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|&&c| !c.is_whitespace() && c != '(' && c != ')' && !is_operator_char(c)) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    tokens
+}
+
+fn is_operator_char(c: char) -> bool {
+    matches!(c, '<' | '>' | '=' | '!')
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|token| token.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> io::Result<()> {
+        match self.next() {
+            Some(token) if token.eq_ignore_ascii_case(keyword) => Ok(()),
+            Some(token) => Err(invalid_input(&format!("expected '{}' but found '{}'", keyword, token))),
+            None => Err(invalid_input(&format!("expected '{}' at end of selection", keyword))),
+        }
+    }
+
+    fn parse_or(&mut self) -> io::Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> io::Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek_keyword("and") {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> io::Result<Expr> {
+        let Some(token) = self.next() else {
+            return Err(invalid_input("selection ended unexpectedly"));
+        };
+
+        match token.to_ascii_lowercase().as_str() {
+            "(" => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(invalid_input("unbalanced parenthesis in selection")),
+                }
+            }
+            "not" => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            "all" => Ok(Expr::All),
+            "none" => Ok(Expr::None),
+            "hetatm" => Ok(Expr::Hetatm),
+            "hydrogen" => Ok(Expr::Hydrogen),
+            "element" => Ok(Expr::Element(self.parse_values(token)?)),
+            "name" => Ok(Expr::Name(self.parse_values(token)?)),
+            "resn" => Ok(Expr::ResName(self.parse_values(token)?)),
+            "resi" => Ok(Expr::ResId(self.parse_ranges(token)?)),
+            "serial" => Ok(Expr::Serial(self.parse_ranges(token)?)),
+            "chain" => {
+                let values = self.parse_values(token)?;
+                let mut ids = Vec::new();
+                for value in values {
+                    let mut chars = value.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(id), None) => ids.push(id),
+                        _ => return Err(invalid_input(&format!("chain identifier '{}' is not one character", value))),
+                    }
+                }
+                Ok(Expr::Chain(ids))
+            }
+            "b" | "bfactor" => self.parse_comparison(Property::BFactor),
+            "occupancy" => self.parse_comparison(Property::Occupancy),
+            "within" | "around" => {
+                let distance = self.parse_number()?;
+                if distance.is_nan() || distance < 0.0 {
+                    return Err(invalid_input(&format!("'{}' needs a distance of at least 0, not {}", token, distance)));
+                }
+                self.expect_keyword("of")?;
+                let inner = Box::new(self.parse_unary()?);
+                if token.eq_ignore_ascii_case("within") {
+                    Ok(Expr::Within(distance, inner))
+                } else {
+                    Ok(Expr::Around(distance, inner))
+                }
+            }
+            _ => Err(invalid_input(&format!("unknown selection keyword '{}'", token))),
+        }
+    }
+
+    // Values run until the next boolean operator or closing parenthesis
+    fn parse_values(&mut self, keyword: &str) -> io::Result<Vec<String>> {
+        let mut values = Vec::new();
+        while let Some(token) = self.peek() {
+            if token == ")" || token == "(" || ["and", "or", "not"].iter().any(|k| token.eq_ignore_ascii_case(k)) {
+                break;
+            }
+            values.push(token.to_string());
+            self.next();
+        }
+
+        if values.is_empty() {
+            return Err(invalid_input(&format!("'{}' needs at least one value", keyword)));
+        }
+        Ok(values)
+    }
+
+    fn parse_ranges(&mut self, keyword: &str) -> io::Result<Vec<(usize, usize)>> {
+        let mut ranges = Vec::new();
+        for value in self.parse_values(keyword)? {
+            let parse = |text: &str| {
+                text.parse::<usize>()
+                    .map_err(|_| invalid_input(&format!("invalid number '{}' for '{}'", text, keyword)))
+            };
+            let range = match value.split_once(['-', ':']) {
+                Some((start, end)) => (parse(start)?, parse(end)?),
+                None => (parse(&value)?, parse(&value)?),
+            };
+            ranges.push(range);
+        }
+        Ok(ranges)
+    }
+
+    fn parse_comparison(&mut self, property: Property) -> io::Result<Expr> {
+        let comparison = match self.next() {
+            Some("<") => Comparison::Less,
+            Some("<=") => Comparison::LessEqual,
+            Some(">") => Comparison::Greater,
+            Some(">=") => Comparison::GreaterEqual,
+            Some("=") | Some("==") => Comparison::Equal,
+            Some("!=") => Comparison::NotEqual,
+            Some(token) => return Err(invalid_input(&format!("expected a comparison but found '{}'", token))),
+            None => return Err(invalid_input("expected a comparison at end of selection")),
+        };
+        Ok(Expr::Compare(property, comparison, self.parse_number()?))
+    }
+
+    fn parse_number(&mut self) -> io::Result<f32> {
+        match self.next() {
+            Some(token) => token
+                .parse()
+                .map_err(|_| invalid_input(&format!("expected a number but found '{}'", token))),
+            None => Err(invalid_input("expected a number at end of selection")),
+        }
+    }
+}
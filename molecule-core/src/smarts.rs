@@ -0,0 +1,776 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use crate::error::invalid_input;
+use crate::graph::MolecularGraph;
+use crate::smiles::{bond_valence, implied_hydrogens, perceived_orders, ring_bonds};
+use crate::structure::{AtomIndex, BondOrder, Element, Molecule};
+
+/// A parsed SMARTS pattern for substructure search.
+///
+/// ```text
+/// C(=O)[O-,OH]            carboxylate or carboxylic acid
+/// c([OH])c[OH]            catechol
+/// [NX3;H2,H1;!$(NC=O)]    primary or secondary amine, not an amide
+/// ```
+///
+/// Atoms take element symbols (uppercase aliphatic, lowercase aromatic),
+/// `*`, `a`, `A`, `#n`, isotopes, charges and the counts `D`, `X`, `H`, `h`,
+/// `v`, `R`, `r` and `x`, combined with `!`, `&`, `,` and `;` and nested as
+/// `$(...)`. Bonds take `-`, `=`, `#`, `:`, `~` and `@` with the same
+/// operators; an unwritten bond is single or aromatic, and `/` and `\` are
+/// single. `@` and `@@` on atoms are accepted but not checked.
+///
+/// Aromaticity and ring counts are perceived the way `SmilesWriter` does,
+/// and structures without any hydrogen atoms get implicit hydrogens from
+/// usual valences, so `H` counts work on heavy-atom PDB models.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartsPattern {
+    atoms: Vec<AtomExpr>,
+    bonds: Vec<(usize, usize, BondExpr)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AtomExpr {
+    Any,
+    Aromatic(bool),
+    // Atomic number, and whether the atom must be aromatic or aliphatic
+    Element(u8, Option<bool>),
+    Isotope(u16),
+    Charge(i8),
+    TotalHydrogens(usize),
+    ImplicitHydrogens(usize),
+    Degree(usize),
+    Connectivity(usize),
+    Valence(usize),
+    // `None` is any ring membership
+    RingCount(Option<usize>),
+    RingSize(Option<usize>),
+    RingConnectivity(Option<usize>),
+    Recursive(Box<SmartsPattern>),
+    Not(Box<AtomExpr>),
+    And(Box<AtomExpr>, Box<AtomExpr>),
+    Or(Box<AtomExpr>, Box<AtomExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BondExpr {
+    Any,
+    // Single or aromatic, for bonds written without a symbol
+    Implicit,
+    Order(BondOrder),
+    Ring,
+    Not(Box<BondExpr>),
+    And(Box<BondExpr>, Box<BondExpr>),
+    Or(Box<BondExpr>, Box<BondExpr>),
+}
+
+impl Molecule {
+    /// All matches of a SMARTS pattern, one atom per pattern atom.
+    pub fn find_smarts(&self, smarts: &str) -> io::Result<Vec<Vec<AtomIndex>>> {
+        Ok(SmartsPattern::parse(smarts)?.matches(self))
+    }
+}
+
+impl SmartsPattern {
+    pub fn parse(smarts: &str) -> io::Result<Self> {
+        let chars: Vec<char> = smarts.trim().chars().collect();
+        if chars.is_empty() {
+            return Err(invalid_input("empty SMARTS pattern"));
+        }
+        let mut parser = Parser { chars: &chars, position: 0 };
+        parser.parse_pattern()
+    }
+
+    /// Number of atoms in the pattern, and so in every match.
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    /// Every mapping of pattern atoms onto molecule atoms, in pattern atom
+    /// order. Symmetric patterns match the same atoms several times; see
+    /// `unique_matches`.
+    pub fn matches(&self, molecule: &Molecule) -> Vec<Vec<AtomIndex>> {
+        let target = Target::new(molecule);
+        let mut matches = Vec::new();
+        self.search(&target, None, &mut matches);
+        matches
+    }
+
+    /// Matches that cover different sets of atoms, keeping the first
+    /// mapping found for each set.
+    pub fn unique_matches(&self, molecule: &Molecule) -> Vec<Vec<AtomIndex>> {
+        let mut seen = HashSet::new();
+        let mut unique = Vec::new();
+        for mapping in self.matches(molecule) {
+            let mut key = mapping.clone();
+            key.sort_unstable();
+            if seen.insert(key) {
+                unique.push(mapping);
+            }
+        }
+        unique
+    }
+
+    fn search(&self, target: &Target, root: Option<usize>, matches: &mut Vec<Vec<AtomIndex>>) {
+        let mut search = Search {
+            pattern: self,
+            target,
+            order: self.search_order(),
+            root,
+            mapping: vec![usize::MAX; self.atoms.len()],
+            used: vec![false; target.molecule.atoms.len()],
+            matches,
+        };
+        search.extend(0);
+    }
+
+    // Pattern atoms in an order where each atom after the first of its
+    // component is bonded to an earlier one
+    fn search_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.atoms.len());
+        let mut seen = vec![false; self.atoms.len()];
+        for start in 0..self.atoms.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let first = order.len();
+            order.push(start);
+            let mut next = first;
+            while next < order.len() {
+                let atom = order[next];
+                next += 1;
+                for (a, b, _) in &self.bonds {
+                    let other = if *a == atom { *b } else if *b == atom { *a } else { continue };
+                    if !seen[other] {
+                        seen[other] = true;
+                        order.push(other);
+                    }
+                }
+            }
+        }
+        order
+    }
+}
+
+// Backtracking state while mapping pattern atoms onto a molecule
+struct Search<'p, 't> {
+    pattern: &'p SmartsPattern,
+    target: &'t Target<'t>,
+    order: Vec<usize>,
+    // Pins the first pattern atom, for recursive SMARTS
+    root: Option<usize>,
+    mapping: Vec<usize>,
+    used: Vec<bool>,
+    matches: &'p mut Vec<Vec<AtomIndex>>,
+}
+
+impl Search<'_, '_> {
+    fn extend(&mut self, depth: usize) {
+        let Some(&query) = self.order.get(depth) else {
+            self.matches.push(self.mapping.iter().map(|&atom| AtomIndex(atom)).collect());
+            return;
+        };
+
+        // Bonds back to atoms already placed; the first one limits the candidates
+        let mapping = &self.mapping;
+        let placed: Vec<(usize, &BondExpr)> = self
+            .pattern
+            .bonds
+            .iter()
+            .filter_map(|(a, b, bond)| match (*a == query, *b == query) {
+                (true, _) if mapping[*b] != usize::MAX => Some((mapping[*b], bond)),
+                (_, true) if mapping[*a] != usize::MAX => Some((mapping[*a], bond)),
+                _ => None,
+            })
+            .collect();
+        let target = self.target;
+        let candidates: Vec<usize> = match (depth, self.root, placed.first()) {
+            (0, Some(root), _) => vec![root],
+            (_, _, Some(&(anchor, _))) => target.graph.neighbors(AtomIndex(anchor)).map(|atom| atom.0).collect(),
+            _ => (0..target.molecule.atoms.len()).collect(),
+        };
+
+        for atom in candidates {
+            if self.used[atom] || !target.atom_matches(&self.pattern.atoms[query], atom) {
+                continue;
+            }
+            let bonded = placed.iter().all(|&(other, bond)| {
+                target
+                    .graph
+                    .bond_between(AtomIndex(atom), AtomIndex(other))
+                    .is_some_and(|index| target.bond_matches(bond, index.0))
+            });
+            if !bonded {
+                continue;
+            }
+
+            self.mapping[query] = atom;
+            self.used[atom] = true;
+            self.extend(depth + 1);
+            self.mapping[query] = usize::MAX;
+            self.used[atom] = false;
+        }
+    }
+}
+
+// Per-atom properties of the searched molecule, computed once per search
+struct Target<'a> {
+    molecule: &'a Molecule,
+    graph: MolecularGraph,
+    orders: Vec<BondOrder>,
+    ring_bond: Vec<bool>,
+    aromatic: Vec<bool>,
+    // Plain hydrogen atoms and implied hydrogens, which SMARTS counts as implicit
+    implicit_hydrogens: Vec<usize>,
+    // Hydrogen atoms with an isotope, a charge or other bonds stay explicit
+    explicit_hydrogens: Vec<usize>,
+    // Connections other than implicit hydrogens
+    degree: Vec<usize>,
+    valence: Vec<usize>,
+    ring_count: Vec<usize>,
+    smallest_ring: Vec<Option<usize>>,
+    ring_connectivity: Vec<usize>,
+}
+
+impl<'a> Target<'a> {
+    fn new(molecule: &'a Molecule) -> Self {
+        let graph = molecule.graph();
+        let rings = graph.rings();
+        let ring_bond_lists = ring_bonds(&graph, &rings);
+        let (kekule, orders) = perceived_orders(molecule, &graph, &rings, &ring_bond_lists);
+        let count = molecule.atoms.len();
+
+        let mut ring_bond = vec![false; molecule.bonds.len()];
+        for &bond in ring_bond_lists.iter().flatten() {
+            ring_bond[bond] = true;
+        }
+        let mut ring_count = vec![0; count];
+        let mut smallest_ring: Vec<Option<usize>> = vec![None; count];
+        for ring in &rings {
+            for atom in ring {
+                ring_count[atom.0] += 1;
+                smallest_ring[atom.0] = Some(smallest_ring[atom.0].map_or(ring.len(), |size| size.min(ring.len())));
+            }
+        }
+
+        let plain_hydrogen = |index: AtomIndex| {
+            let atom = &molecule.atoms[index.0];
+            atom.element == Element::H
+                && atom.isotope.is_none()
+                && atom.formal_charge == 0
+                && graph.degree(index) == 1
+                && graph.neighbors(index).all(|other| molecule.atoms[other.0].element != Element::H)
+        };
+        let has_hydrogens = molecule.atoms.iter().any(|atom| atom.element == Element::H);
+        let implicit_hydrogens: Vec<usize> = (0..count)
+            .map(|atom| {
+                if has_hydrogens {
+                    graph.neighbors(AtomIndex(atom)).filter(|&other| plain_hydrogen(other)).count()
+                } else {
+                    implied_hydrogens(molecule, &graph, &kekule, AtomIndex(atom))
+                }
+            })
+            .collect();
+        let explicit_hydrogens = (0..count)
+            .map(|atom| {
+                let hydrogens = graph.neighbors(AtomIndex(atom)).filter(|other| molecule.atoms[other.0].element == Element::H).count();
+                hydrogens - if has_hydrogens { implicit_hydrogens[atom] } else { 0 }
+            })
+            .collect();
+        let valence = (0..count)
+            .map(|atom| {
+                let bonds: usize = graph.edges(AtomIndex(atom)).iter().map(|&(_, bond)| bond_valence(kekule[bond.0])).sum();
+                bonds + if has_hydrogens { 0 } else { implicit_hydrogens[atom] }
+            })
+            .collect();
+        let degree = (0..count)
+            .map(|atom| {
+                let hydrogens = if has_hydrogens { implicit_hydrogens[atom] } else { 0 };
+                graph.degree(AtomIndex(atom)) - hydrogens
+            })
+            .collect();
+        let aromatic = (0..count)
+            .map(|atom| graph.edges(AtomIndex(atom)).iter().any(|&(_, bond)| orders[bond.0] == BondOrder::Aromatic))
+            .collect();
+        let ring_connectivity = (0..count)
+            .map(|atom| graph.edges(AtomIndex(atom)).iter().filter(|&&(_, bond)| ring_bond[bond.0]).count())
+            .collect();
+
+        Self {
+            molecule,
+            graph,
+            orders,
+            ring_bond,
+            aromatic,
+            implicit_hydrogens,
+            explicit_hydrogens,
+            degree,
+            valence,
+            ring_count,
+            smallest_ring,
+            ring_connectivity,
+        }
+    }
+
+    fn atom_matches(&self, expr: &AtomExpr, atom: usize) -> bool {
+        let properties = &self.molecule.atoms[atom];
+        match expr {
+            AtomExpr::Any => true,
+            AtomExpr::Aromatic(aromatic) => self.aromatic[atom] == *aromatic,
+            AtomExpr::Element(number, aromatic) => {
+                properties.element.atomic_number() == *number && aromatic.is_none_or(|aromatic| self.aromatic[atom] == aromatic)
+            }
+            AtomExpr::Isotope(isotope) => properties.isotope == Some(*isotope),
+            AtomExpr::Charge(charge) => properties.formal_charge == *charge,
+            AtomExpr::TotalHydrogens(count) => self.explicit_hydrogens[atom] + self.implicit_hydrogens[atom] == *count,
+            AtomExpr::ImplicitHydrogens(count) => self.implicit_hydrogens[atom] == *count,
+            AtomExpr::Degree(count) => self.degree[atom] == *count,
+            AtomExpr::Connectivity(count) => self.degree[atom] + self.implicit_hydrogens[atom] == *count,
+            AtomExpr::Valence(count) => self.valence[atom] == *count,
+            AtomExpr::RingCount(None) => self.ring_count[atom] > 0,
+            AtomExpr::RingCount(Some(count)) => self.ring_count[atom] == *count,
+            AtomExpr::RingSize(None) => self.smallest_ring[atom].is_some(),
+            AtomExpr::RingSize(Some(size)) => self.smallest_ring[atom] == Some(*size),
+            AtomExpr::RingConnectivity(None) => self.ring_connectivity[atom] > 0,
+            AtomExpr::RingConnectivity(Some(count)) => self.ring_connectivity[atom] == *count,
+            AtomExpr::Recursive(pattern) => {
+                let mut matches = Vec::new();
+                pattern.search(self, Some(atom), &mut matches);
+                !matches.is_empty()
+            }
+            AtomExpr::Not(inner) => !self.atom_matches(inner, atom),
+            AtomExpr::And(left, right) => self.atom_matches(left, atom) && self.atom_matches(right, atom),
+            AtomExpr::Or(left, right) => self.atom_matches(left, atom) || self.atom_matches(right, atom),
+        }
+    }
+
+    fn bond_matches(&self, expr: &BondExpr, bond: usize) -> bool {
+        match expr {
+            BondExpr::Any => true,
+            BondExpr::Implicit => matches!(self.orders[bond], BondOrder::Single | BondOrder::Aromatic),
+            BondExpr::Order(order) => self.orders[bond] == *order,
+            BondExpr::Ring => self.ring_bond[bond],
+            BondExpr::Not(inner) => !self.bond_matches(inner, bond),
+            BondExpr::And(left, right) => self.bond_matches(left, bond) && self.bond_matches(right, bond),
+            BondExpr::Or(left, right) => self.bond_matches(left, bond) || self.bond_matches(right, bond),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn unexpected(&self) -> io::Error {
+        match self.peek() {
+            Some(c) => invalid_input(&format!("unexpected '{}' at position {} in SMARTS", c, self.position)),
+            None => invalid_input("SMARTS pattern ended unexpectedly"),
+        }
+    }
+
+    fn parse_pattern(&mut self) -> io::Result<SmartsPattern> {
+        let mut pattern = SmartsPattern { atoms: Vec::new(), bonds: Vec::new() };
+        let mut previous: Option<usize> = None;
+        let mut branches: Vec<Option<usize>> = Vec::new();
+        let mut pending: Option<BondExpr> = None;
+        let mut open_rings: HashMap<usize, (usize, Option<BondExpr>)> = HashMap::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => {
+                    if previous.is_none() || pending.is_some() {
+                        return Err(self.unexpected());
+                    }
+                    self.next();
+                    branches.push(previous);
+                }
+                ')' => {
+                    let Some(branch) = branches.pop() else {
+                        return Err(self.unexpected());
+                    };
+                    if pending.is_some() {
+                        return Err(self.unexpected());
+                    }
+                    self.next();
+                    previous = branch;
+                }
+                '.' => {
+                    if previous.is_none() || pending.is_some() {
+                        return Err(self.unexpected());
+                    }
+                    self.next();
+                    previous = None;
+                }
+                '0'..='9' | '%' => {
+                    let Some(atom) = previous else {
+                        return Err(self.unexpected());
+                    };
+                    let number = self.parse_ring_number()?;
+                    let bond = pending.take();
+                    match open_rings.remove(&number) {
+                        Some((other, opening)) => {
+                            if other == atom {
+                                return Err(invalid_input(&format!("ring bond {} closes on its own atom", number)));
+                            }
+                            let bond = bond.or(opening).unwrap_or(BondExpr::Implicit);
+                            pattern.bonds.push((other, atom, bond));
+                        }
+                        None => {
+                            open_rings.insert(number, (atom, bond));
+                        }
+                    }
+                }
+                '-' | '=' | '#' | ':' | '~' | '@' | '/' | '\\' | '!' => {
+                    if previous.is_none() || pending.is_some() {
+                        return Err(self.unexpected());
+                    }
+                    pending = Some(self.parse_bond_expr()?);
+                }
+                _ => {
+                    let atom = if c == '[' { self.parse_bracket()? } else { self.parse_organic()? };
+                    let index = pattern.atoms.len();
+                    pattern.atoms.push(atom);
+                    if let Some(other) = previous {
+                        pattern.bonds.push((other, index, pending.take().unwrap_or(BondExpr::Implicit)));
+                    }
+                    previous = Some(index);
+                }
+            }
+        }
+
+        if pending.is_some() || !branches.is_empty() || self.chars.last() == Some(&'.') {
+            return Err(invalid_input("unfinished bond, branch or '.' at end of SMARTS"));
+        }
+        if let Some(number) = open_rings.keys().min() {
+            return Err(invalid_input(&format!("ring bond {} is never closed", number)));
+        }
+        if pattern.atoms.is_empty() {
+            return Err(invalid_input("SMARTS pattern has no atoms"));
+        }
+        Ok(pattern)
+    }
+
+    fn parse_ring_number(&mut self) -> io::Result<usize> {
+        if self.peek() == Some('%') {
+            self.next();
+            let (Some(tens), Some(ones)) = (self.next().and_then(|c| c.to_digit(10)), self.next().and_then(|c| c.to_digit(10))) else {
+                return Err(invalid_input("'%' must be followed by two digits in SMARTS"));
+            };
+            return Ok((tens * 10 + ones) as usize);
+        }
+        Ok(self.next().and_then(|c| c.to_digit(10)).unwrap_or(0) as usize)
+    }
+
+    // Atoms outside brackets: the organic subset, `*`, `a` and `A`
+    fn parse_organic(&mut self) -> io::Result<AtomExpr> {
+        let c = self.peek().ok_or_else(|| self.unexpected())?;
+        let expr = match c {
+            '*' => AtomExpr::Any,
+            'a' => AtomExpr::Aromatic(true),
+            'A' => AtomExpr::Aromatic(false),
+            'C' if self.peek_at(1) == Some('l') => {
+                self.position += 1;
+                AtomExpr::Element(Element::Cl.atomic_number(), None)
+            }
+            'B' if self.peek_at(1) == Some('r') => {
+                self.position += 1;
+                AtomExpr::Element(Element::Br.atomic_number(), None)
+            }
+            'B' | 'C' | 'N' | 'O' | 'P' | 'S' => AtomExpr::Element(element_number(&c.to_string()), Some(false)),
+            'F' | 'I' => AtomExpr::Element(element_number(&c.to_string()), None),
+            'b' | 'c' | 'n' | 'o' | 'p' | 's' => AtomExpr::Element(element_number(&c.to_ascii_uppercase().to_string()), Some(true)),
+            _ => return Err(self.unexpected()),
+        };
+        self.next();
+        Ok(expr)
+    }
+
+    fn parse_bracket(&mut self) -> io::Result<AtomExpr> {
+        self.next();
+        // A leading `H` is a hydrogen atom only when nothing but a charge follows
+        if self.peek() == Some('H') && matches!(self.peek_at(1), Some(']' | '+' | '-')) {
+            self.next();
+            let mut expr = AtomExpr::Element(1, None);
+            if self.peek() != Some(']') {
+                expr = AtomExpr::And(Box::new(expr), Box::new(self.parse_atom_low()?));
+            }
+            self.expect(']')?;
+            return Ok(expr);
+        }
+        let expr = self.parse_atom_low()?;
+        self.expect(']')?;
+        Ok(expr)
+    }
+
+    fn expect(&mut self, expected: char) -> io::Result<()> {
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    // `;` binds loosest, then `,`, then `&` and plain juxtaposition, then `!`
+    fn parse_atom_low(&mut self) -> io::Result<AtomExpr> {
+        let mut expr = self.parse_atom_or()?;
+        while self.peek() == Some(';') {
+            self.next();
+            expr = AtomExpr::And(Box::new(expr), Box::new(self.parse_atom_or()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_atom_or(&mut self) -> io::Result<AtomExpr> {
+        let mut expr = self.parse_atom_and()?;
+        while self.peek() == Some(',') {
+            self.next();
+            expr = AtomExpr::Or(Box::new(expr), Box::new(self.parse_atom_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_atom_and(&mut self) -> io::Result<AtomExpr> {
+        let mut expr = self.parse_atom_not()?;
+        loop {
+            match self.peek() {
+                Some('&') => {
+                    self.next();
+                }
+                Some(']' | ',' | ';' | ')') | None => break,
+                Some(_) => {}
+            }
+            let Some(right) = self.parse_atom_not_or_chirality()? else {
+                continue;
+            };
+            expr = AtomExpr::And(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_atom_not(&mut self) -> io::Result<AtomExpr> {
+        loop {
+            if let Some(expr) = self.parse_atom_not_or_chirality()? {
+                return Ok(expr);
+            }
+            // Chirality alone in a conjunction leaves nothing to test
+            if matches!(self.peek(), Some(']' | ',' | ';' | ')') | None) {
+                return Ok(AtomExpr::Any);
+            }
+            if self.peek() == Some('&') {
+                self.next();
+            }
+        }
+    }
+
+    // `None` for skipped chirality
+    fn parse_atom_not_or_chirality(&mut self) -> io::Result<Option<AtomExpr>> {
+        match self.peek() {
+            Some('!') => {
+                self.next();
+                Ok(Some(AtomExpr::Not(Box::new(self.parse_atom_not()?))))
+            }
+            Some('@') => {
+                self.next();
+                if self.peek() == Some('@') {
+                    self.next();
+                }
+                if self.peek() == Some('?') {
+                    self.next();
+                }
+                Ok(None)
+            }
+            _ => self.parse_atom_primitive().map(Some),
+        }
+    }
+
+    fn parse_atom_primitive(&mut self) -> io::Result<AtomExpr> {
+        let c = self.peek().ok_or_else(|| self.unexpected())?;
+        if c.is_ascii_digit() {
+            let isotope = self.parse_count().unwrap_or(0);
+            let isotope = u16::try_from(isotope).map(AtomExpr::Isotope).map_err(|_| invalid_input("isotope out of range in SMARTS"))?;
+            // As at the start of a bracket, `[2H]` is deuterium rather than one hydrogen
+            if self.peek() == Some('H') && matches!(self.peek_at(1), Some(']' | '+' | '-')) {
+                self.next();
+                return Ok(AtomExpr::And(Box::new(isotope), Box::new(AtomExpr::Element(1, None))));
+            }
+            return Ok(isotope);
+        }
+
+        // `He` and `Hg` are elements, not a hydrogen count followed by junk
+        let start = self.position;
+        let two_letters = self.peek_at(1).is_some_and(|next| element_number(&format!("{}{}", c, next)) != 0);
+        if c.is_ascii_uppercase() && two_letters {
+            return self.parse_bracket_symbol();
+        }
+        self.next();
+        let expr = match c {
+            '*' => AtomExpr::Any,
+            '#' => {
+                let number = self.parse_count().ok_or_else(|| invalid_input("'#' must be followed by an atomic number in SMARTS"))?;
+                AtomExpr::Element(u8::try_from(number).unwrap_or(0), None)
+            }
+            '+' | '-' => {
+                let sign: i32 = if c == '+' { 1 } else { -1 };
+                let mut magnitude = 1;
+                if let Some(count) = self.parse_count() {
+                    magnitude = count as i32;
+                } else {
+                    while self.peek() == Some(c) {
+                        self.next();
+                        magnitude += 1;
+                    }
+                }
+                AtomExpr::Charge(i8::try_from(sign * magnitude).map_err(|_| invalid_input("charge out of range in SMARTS"))?)
+            }
+            '$' => {
+                if self.next() != Some('(') {
+                    return Err(invalid_input("'$' must be followed by '(' in SMARTS"));
+                }
+                let mut depth = 1;
+                let inner_start = self.position;
+                while depth > 0 {
+                    match self.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some(_) => {}
+                        None => return Err(invalid_input("unclosed '$(' in SMARTS")),
+                    }
+                }
+                let inner: Vec<char> = self.chars[inner_start..self.position - 1].to_vec();
+                let mut parser = Parser { chars: &inner, position: 0 };
+                AtomExpr::Recursive(Box::new(parser.parse_pattern()?))
+            }
+            'D' => AtomExpr::Degree(self.parse_count().unwrap_or(1)),
+            'X' => AtomExpr::Connectivity(self.parse_count().unwrap_or(1)),
+            'H' => AtomExpr::TotalHydrogens(self.parse_count().unwrap_or(1)),
+            'h' => AtomExpr::ImplicitHydrogens(self.parse_count().unwrap_or(1)),
+            'v' => AtomExpr::Valence(self.parse_count().unwrap_or(1)),
+            'R' => AtomExpr::RingCount(self.parse_count()),
+            'r' => AtomExpr::RingSize(self.parse_count()),
+            'x' => AtomExpr::RingConnectivity(self.parse_count()),
+            'a' => AtomExpr::Aromatic(true),
+            'A' => AtomExpr::Aromatic(false),
+            _ => {
+                self.position = start;
+                return self.parse_bracket_symbol();
+            }
+        };
+        Ok(expr)
+    }
+
+    // Element symbols in brackets; two letters win over one where both are elements
+    fn parse_bracket_symbol(&mut self) -> io::Result<AtomExpr> {
+        let c = self.peek().ok_or_else(|| self.unexpected())?;
+        if c.is_ascii_uppercase() {
+            if let Some(second) = self.peek_at(1).filter(char::is_ascii_lowercase) {
+                let symbol: String = [c, second].iter().collect();
+                let number = element_number(&symbol);
+                if number != 0 {
+                    self.position += 2;
+                    return Ok(AtomExpr::Element(number, Some(false)));
+                }
+            }
+            let number = element_number(&c.to_string());
+            if number != 0 {
+                self.next();
+                return Ok(AtomExpr::Element(number, Some(false)));
+            }
+        } else if c == 's' && self.peek_at(1) == Some('e') {
+            self.position += 2;
+            return Ok(AtomExpr::Element(Element::Se.atomic_number(), Some(true)));
+        } else if matches!(c, 'b' | 'c' | 'n' | 'o' | 'p' | 's') {
+            self.next();
+            return Ok(AtomExpr::Element(element_number(&c.to_ascii_uppercase().to_string()), Some(true)));
+        }
+        Err(self.unexpected())
+    }
+
+    fn parse_count(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.next();
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        digits.parse().ok()
+    }
+
+    fn parse_bond_expr(&mut self) -> io::Result<BondExpr> {
+        let mut expr = self.parse_bond_or()?;
+        while self.peek() == Some(';') {
+            self.next();
+            expr = BondExpr::And(Box::new(expr), Box::new(self.parse_bond_or()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_bond_or(&mut self) -> io::Result<BondExpr> {
+        let mut expr = self.parse_bond_and()?;
+        while self.peek() == Some(',') {
+            self.next();
+            expr = BondExpr::Or(Box::new(expr), Box::new(self.parse_bond_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_bond_and(&mut self) -> io::Result<BondExpr> {
+        let mut expr = self.parse_bond_not()?;
+        loop {
+            match self.peek() {
+                Some('&') => {
+                    self.next();
+                }
+                Some('-' | '=' | '#' | ':' | '~' | '@' | '/' | '\\' | '!') => {}
+                _ => break,
+            }
+            expr = BondExpr::And(Box::new(expr), Box::new(self.parse_bond_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_bond_not(&mut self) -> io::Result<BondExpr> {
+        let expr = match self.peek() {
+            Some('!') => {
+                self.next();
+                return Ok(BondExpr::Not(Box::new(self.parse_bond_not()?)));
+            }
+            Some('-' | '/' | '\\') => BondExpr::Order(BondOrder::Single),
+            Some('=') => BondExpr::Order(BondOrder::Double),
+            Some('#') => BondExpr::Order(BondOrder::Triple),
+            Some(':') => BondExpr::Order(BondOrder::Aromatic),
+            Some('~') => BondExpr::Any,
+            Some('@') => BondExpr::Ring,
+            _ => return Err(self.unexpected()),
+        };
+        self.next();
+        Ok(expr)
+    }
+}
+
+// Atomic number for an exact-case symbol, or 0 when it is not an element
+fn element_number(symbol: &str) -> u8 {
+    let element = Element::from_symbol(symbol);
+    if element != Element::Unknown && element.symbol() == symbol { element.atomic_number() } else { 0 }
+}
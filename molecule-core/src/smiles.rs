@@ -0,0 +1,1452 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{invalid_data, invalid_input};
+use crate::extract::ExtractOptions;
+use crate::geometry::{cross, dot, normalize, scale, sub};
+use crate::graph::MolecularGraph;
+use crate::kekulize::{default_valence, kekulize};
+use crate::structure::{Atom, AtomIndex, BondDirection, BondOrder, Chirality, Element, Molecule};
+
+const RESIDUE_NAME: &str = "LIG";
+
+// Atoms that may be written without brackets
+const ORGANIC_SYMBOLS: [&str; 17] = ["Cl", "Br", "B", "C", "N", "O", "P", "S", "F", "I", "b", "c", "n", "o", "p", "s", "*"];
+
+// Symbols allowed inside brackets, two-letter ones first so `Cl` is not read as `C`
+const BRACKET_SYMBOLS: [&str; 37] = [
+    "He", "Li", "Be", "Ne", "Na", "Mg", "Al", "Si", "Cl", "Ar", "Ca", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Se", "Br",
+    "se", "H", "B", "C", "N", "O", "F", "P", "S", "K", "I", "b", "c", "n", "o", "p", "s", "*",
+];
+
+/// Parser for SMILES strings, building a single-residue ligand.
+///
+/// Implicit hydrogens become explicit `H` atoms, added after all heavy atoms
+/// so heavy atoms keep their SMILES order. Aromatic atoms and bonds use
+/// `BondOrder::Aromatic`; `@`/`@@` and `/`/`\` are kept as `Atom::chirality`
+/// and `Bond::direction`. Positions are left at the origin.
+pub struct SmilesParser;
+
+impl Default for SmilesParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A bond waiting for its ring closure digit to appear again
+struct OpenRing {
+    atom: usize,
+    symbol: Option<char>,
+    slot: usize,
+}
+
+struct State {
+    molecule: Molecule,
+    aromatic: Vec<bool>,
+    // Hydrogen count for bracket atoms; organic subset atoms get implicit ones
+    hydrogens: Vec<Option<u8>>,
+    // Neighbors in the order SMILES lists them, for chirality. `None` is the
+    // bracket hydrogen, `usize::MAX` a ring bond not yet closed.
+    written_order: Vec<Vec<Option<usize>>>,
+    preceded: Vec<bool>,
+    // Bonds between aromatic atoms written without a symbol
+    implicit_aromatic: Vec<usize>,
+}
+
+impl SmilesParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses the SMILES on the first non-empty line of the file.
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Molecule> {
+        let file = File::open(path)?;
+        self.parse_reader(BufReader::new(file))
+    }
+
+    /// Parses one SMILES string. Anything after the first whitespace is
+    /// taken as the molecule's name and stored as the title.
+    pub fn parse_string(&self, content: &str) -> io::Result<Molecule> {
+        let content = content.trim();
+        let (smiles, name) = match content.split_once(char::is_whitespace) {
+            Some((smiles, name)) => (smiles, Some(name.trim())),
+            None => (content, None),
+        };
+        if smiles.is_empty() {
+            return Err(invalid_data("empty SMILES"));
+        }
+
+        let mut molecule = self.parse_smiles(smiles)?;
+        molecule.metadata.title = name.filter(|name| !name.is_empty()).map(str::to_string);
+        Ok(molecule)
+    }
+
+    pub fn parse_reader<R: Read>(&self, reader: R) -> io::Result<Molecule> {
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                return self.parse_string(&line);
+            }
+        }
+        Err(invalid_data("no SMILES found"))
+    }
+
+    fn parse_smiles(&self, smiles: &str) -> io::Result<Molecule> {
+        let chars: Vec<char> = smiles.chars().collect();
+        let mut state = State {
+            molecule: Molecule::new(),
+            aromatic: Vec::new(),
+            hydrogens: Vec::new(),
+            written_order: Vec::new(),
+            preceded: Vec::new(),
+            implicit_aromatic: Vec::new(),
+        };
+        let mut rings: HashMap<usize, OpenRing> = HashMap::new();
+        let mut branches: Vec<Option<usize>> = Vec::new();
+        let mut previous: Option<usize> = None;
+        let mut bond: Option<char> = None;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '-' | '=' | '#' | '$' | ':' | '/' | '\\' => {
+                    if bond.is_some() || previous.is_none() {
+                        return Err(unexpected(c, i));
+                    }
+                    bond = Some(c);
+                    i += 1;
+                }
+                '.' => {
+                    if bond.is_some() || previous.is_none() {
+                        return Err(unexpected(c, i));
+                    }
+                    previous = None;
+                    i += 1;
+                }
+                '(' => {
+                    if previous.is_none() || bond.is_some() {
+                        return Err(unexpected(c, i));
+                    }
+                    branches.push(previous);
+                    i += 1;
+                }
+                ')' => {
+                    let Some(start) = branches.pop() else {
+                        return Err(unexpected(c, i));
+                    };
+                    if bond.is_some() || chars[i - 1] == '(' {
+                        return Err(unexpected(c, i));
+                    }
+                    previous = start;
+                    i += 1;
+                }
+                '0'..='9' | '%' => {
+                    let Some(atom) = previous else {
+                        return Err(unexpected(c, i));
+                    };
+                    let (number, length) = ring_number(&chars[i..]).ok_or_else(|| unexpected(c, i))?;
+                    i += length;
+                    let symbol = bond.take();
+                    match rings.remove(&number) {
+                        Some(open) => state.close_ring(open, atom, symbol, i)?,
+                        None => {
+                            state.written_order[atom].push(Some(usize::MAX));
+                            let slot = state.written_order[atom].len() - 1;
+                            rings.insert(number, OpenRing { atom, symbol, slot });
+                        }
+                    }
+                }
+                _ => {
+                    let (atom, length) = parse_atom(&chars[i..]).ok_or_else(|| unexpected(c, i))?;
+                    let index = state.add_atom(atom);
+                    if let Some(previous) = previous {
+                        state.connect(previous, index, bond.take(), i)?;
+                    }
+                    previous = Some(index);
+                    i += length;
+                }
+            }
+        }
+
+        if bond.is_some() {
+            return Err(invalid_data("SMILES ends with a bond"));
+        }
+        if !branches.is_empty() {
+            return Err(invalid_data("unclosed branch in SMILES"));
+        }
+        if let Some(number) = rings.keys().min() {
+            return Err(invalid_data(&format!("ring bond {} is never closed", number)));
+        }
+
+        state.finish()
+    }
+}
+
+impl State {
+    fn add_atom(&mut self, parsed: ParsedAtom) -> usize {
+        let index = self.molecule.atoms.len();
+        let count = self.molecule.atoms.iter().filter(|atom| atom.element == parsed.element).count();
+        self.molecule.add_atom(Atom {
+            id: index + 1,
+            name: atom_name(parsed.element, count + 1),
+            element: parsed.element,
+            residue_id: 1,
+            chain_id: 'A',
+            residue_name: RESIDUE_NAME.to_string(),
+            is_hetatm: true,
+            formal_charge: parsed.charge,
+            isotope: parsed.isotope,
+            chirality: parsed.chirality,
+            ..Atom::default()
+        });
+        self.aromatic.push(parsed.aromatic);
+        self.hydrogens.push(parsed.hydrogens);
+        self.written_order.push(if parsed.hydrogens.is_some_and(|count| count > 0) { vec![None] } else { Vec::new() });
+        self.preceded.push(false);
+        index
+    }
+
+    fn connect(&mut self, from: usize, to: usize, symbol: Option<char>, position: usize) -> io::Result<()> {
+        // The preceding atom comes first, before a bracket hydrogen
+        self.written_order[to].insert(0, Some(from));
+        self.preceded[to] = true;
+        self.written_order[from].push(Some(to));
+        self.bond(from, to, symbol, position)
+    }
+
+    fn close_ring(&mut self, open: OpenRing, atom: usize, symbol: Option<char>, position: usize) -> io::Result<()> {
+        if open.atom == atom {
+            return Err(invalid_data(&format!("ring bond closes on its own atom at position {}", position)));
+        }
+        let exists = self.molecule.bonds.iter().any(|bond| {
+            (bond.atom1.0, bond.atom2.0) == (open.atom, atom) || (bond.atom1.0, bond.atom2.0) == (atom, open.atom)
+        });
+        if exists {
+            return Err(invalid_data(&format!("duplicate bond at position {}", position)));
+        }
+
+        // A direction written at the closing digit reads from the closing atom
+        let symbol = match (open.symbol, symbol) {
+            (Some(first), Some(second)) if first != second && !is_direction(first) && !is_direction(second) => {
+                return Err(invalid_data(&format!("ring bond orders disagree at position {}", position)));
+            }
+            (Some(first), _) => Some(first),
+            (None, Some('/')) => Some('\\'),
+            (None, Some('\\')) => Some('/'),
+            (None, second) => second,
+        };
+        self.written_order[open.atom][open.slot] = Some(atom);
+        self.written_order[atom].push(Some(open.atom));
+        self.bond(open.atom, atom, symbol, position)
+    }
+
+    fn bond(&mut self, atom1: usize, atom2: usize, symbol: Option<char>, position: usize) -> io::Result<()> {
+        let order = match symbol {
+            None if self.aromatic[atom1] && self.aromatic[atom2] => {
+                self.implicit_aromatic.push(self.molecule.bonds.len());
+                BondOrder::Aromatic
+            }
+            None | Some('-' | '/' | '\\') => BondOrder::Single,
+            Some('=') => BondOrder::Double,
+            Some('#') => BondOrder::Triple,
+            Some(':') => BondOrder::Aromatic,
+            Some(_) => return Err(invalid_data(&format!("quadruple bonds are not supported (position {})", position))),
+        };
+        self.molecule.add_bond(AtomIndex(atom1), AtomIndex(atom2), order);
+        if let Some(bond) = self.molecule.bonds.last_mut() {
+            bond.direction = match symbol {
+                Some('/') => BondDirection::Up,
+                Some('\\') => BondDirection::Down,
+                _ => BondDirection::None,
+            };
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Molecule> {
+        // Aromatic atoms joined outside a ring, as in biphenyl written
+        // c1ccccc1c1ccccc1, are joined by a single bond
+        let graph = self.molecule.graph();
+        let in_ring: Vec<bool> = {
+            let mut in_ring = vec![false; self.molecule.bonds.len()];
+            for ring in graph.rings() {
+                for (i, &atom) in ring.iter().enumerate() {
+                    if let Some(bond) = graph.bond_between(atom, ring[(i + 1) % ring.len()]) {
+                        in_ring[bond.0] = true;
+                    }
+                }
+            }
+            in_ring
+        };
+        for &bond in &self.implicit_aromatic {
+            if !in_ring[bond] {
+                self.molecule.bonds[bond].order = BondOrder::Single;
+            }
+        }
+
+        let heavy_count = self.molecule.atoms.len();
+        for atom in 0..heavy_count {
+            let count = match self.hydrogens[atom] {
+                Some(count) => count as usize,
+                None => self.implicit_hydrogens(atom),
+            };
+            for k in 0..count {
+                let hydrogen = self.add_atom(ParsedAtom { element: Element::H, ..ParsedAtom::default() });
+                self.molecule.add_bond(AtomIndex(atom), AtomIndex(hydrogen), BondOrder::Single);
+                // The bracket hydrogen takes the place SMILES gave it
+                if k == 0
+                    && let Some(slot) = self.written_order[atom].iter_mut().find(|slot| slot.is_none())
+                {
+                    *slot = Some(hydrogen);
+                }
+            }
+        }
+
+        // Chirality was read against the written neighbor order; store it
+        // against bond order instead
+        let graph = self.molecule.graph();
+        for atom in 0..heavy_count {
+            if self.molecule.atoms[atom].chirality == Chirality::None {
+                continue;
+            }
+            let mut written: Vec<usize> = self.written_order[atom].iter().flatten().copied().collect();
+            let mut stored: Vec<usize> = graph.neighbors(AtomIndex(atom)).map(|other| other.0).collect();
+            // A lone pair sits where a bracket hydrogen would in SMILES, and
+            // last in bond order
+            if written.len() == 3 {
+                written.insert(usize::from(self.preceded[atom]), usize::MAX);
+                stored.push(usize::MAX);
+            }
+            if written.len() != 4 || stored.len() != 4 {
+                self.molecule.atoms[atom].chirality = Chirality::None;
+            } else if !same_parity(&written, &stored) {
+                self.molecule.atoms[atom].chirality = self.molecule.atoms[atom].chirality.inverted();
+            }
+        }
+
+        Ok(self.molecule)
+    }
+
+    fn implicit_hydrogens(&self, atom: usize) -> usize {
+        let used = self
+            .molecule
+            .bonds
+            .iter()
+            .filter(|bond| bond.atom1.0 == atom || bond.atom2.0 == atom)
+            .map(|bond| bond_valence(bond.order))
+            .sum();
+        implicit_hydrogens(self.molecule.atoms[atom].element, used, self.aromatic[atom])
+    }
+}
+
+/// Writer for canonical SMILES, e.g. to de-duplicate ligands.
+///
+/// Atoms are ranked by iteratively refined graph invariants, so a structure
+/// gives the same string whatever its atom order or source. Kekulé and
+/// aromatic input are brought to one aromatic form, hydrogens are written
+/// implicitly, and structures without any hydrogens get them from usual
+/// valences. Tetrahedral centers and double bonds keep their stereo from
+/// `Atom::chirality` and `Bond::direction`, or else from 3D coordinates.
+/// Opposite ring centers whose ring neighbors are alike, as in cis and trans
+/// 1,4-disubstituted cyclohexanes, are written with their relative
+/// configuration, the first of the pair always as `@`. Other centers told
+/// apart only by stereo elsewhere in the molecule are written without stereo.
+pub struct SmilesWriter {
+    selection: Option<Vec<AtomIndex>>,
+    stereo: bool,
+}
+
+impl Default for SmilesWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SmilesWriter {
+    pub fn new() -> Self {
+        Self { selection: None, stereo: true }
+    }
+
+    /// Restricts output to these atoms, for example one ligand fragment.
+    pub fn with_selection(mut self, atoms: Vec<AtomIndex>) -> Self {
+        self.selection = Some(atoms);
+        self
+    }
+
+    /// Whether to write `@`, `@@`, `/` and `\`. On by default.
+    pub fn stereo(mut self, stereo: bool) -> Self {
+        self.stereo = stereo;
+        self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes one `.smi` line: the SMILES, then the title if there is one.
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        let smiles = self.canonical(molecule)?;
+        match molecule.metadata.title.as_deref().and_then(|title| title.lines().next()) {
+            Some(title) if !title.trim().is_empty() => writeln!(writer, "{} {}", smiles, title.trim()),
+            _ => writeln!(writer, "{}", smiles),
+        }
+    }
+
+    /// The canonical SMILES alone.
+    pub fn canonical(&self, molecule: &Molecule) -> io::Result<String> {
+        let fragment;
+        let molecule = match &self.selection {
+            Some(atoms) => {
+                fragment = molecule.extract(atoms, ExtractOptions::new())?;
+                &fragment
+            }
+            None => molecule,
+        };
+        if molecule.atoms.is_empty() {
+            return Err(invalid_input("no atoms to write as SMILES"));
+        }
+
+        let mut graph = SmilesGraph::new(molecule);
+        graph.rank();
+        graph.write(self.stereo)
+    }
+}
+
+// Neighbor of a written atom in the order SMILES lists it, for chirality
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Atom(usize),
+    // A hydrogen or lone pair with no atom of its own
+    Implied,
+}
+
+// Where each written atom ended up in the depth-first walk
+#[derive(Default)]
+struct Walk {
+    visit: Vec<usize>,
+    parent: Vec<Option<(usize, usize)>>,
+    children: Vec<Vec<(usize, usize)>>,
+    closings: Vec<Vec<(usize, usize)>>,
+    openings: Vec<Vec<(usize, usize)>>,
+    roots: Vec<usize>,
+}
+
+struct SmilesGraph<'a> {
+    molecule: &'a Molecule,
+    graph: MolecularGraph,
+    // Written atoms as indices into `molecule.atoms`; other hydrogens are implicit
+    atoms: Vec<usize>,
+    local: Vec<Option<usize>>,
+    // Normalized order of every bond in `molecule.bonds`
+    orders: Vec<BondOrder>,
+    aromatic: Vec<bool>,
+    in_ring: Vec<bool>,
+    // Rings of written atoms, in order around each ring
+    rings: Vec<Vec<usize>>,
+    small_ring_bonds: Vec<bool>,
+    hydrogens: Vec<usize>,
+    // Hydrogen atoms folded into each written atom
+    hydrogen_atoms: Vec<Vec<usize>>,
+    // Written neighbors and the bonds to them, by rank once ranked
+    neighbors: Vec<Vec<(usize, usize)>>,
+    // Symmetry classes before ties are broken, and the final ranks
+    classes: Vec<usize>,
+    ranks: Vec<usize>,
+}
+
+impl<'a> SmilesGraph<'a> {
+    fn new(molecule: &'a Molecule) -> Self {
+        let graph = molecule.graph();
+        let rings = graph.rings();
+        let ring_bonds = ring_bonds(&graph, &rings);
+        let (kekule, orders) = perceived_orders(molecule, &graph, &rings, &ring_bonds);
+
+        let mut small_ring_bonds = vec![false; molecule.bonds.len()];
+        let mut atom_in_ring = vec![false; molecule.atoms.len()];
+        for (ring, bonds) in rings.iter().zip(&ring_bonds) {
+            for atom in ring {
+                atom_in_ring[atom.0] = true;
+            }
+            if ring.len() < 8 {
+                for &bond in bonds {
+                    small_ring_bonds[bond] = true;
+                }
+            }
+        }
+
+        // Plain hydrogens on one heavy atom are folded into it
+        let folded = |index: usize| {
+            let atom = &molecule.atoms[index];
+            atom.element == Element::H
+                && atom.isotope.is_none()
+                && atom.formal_charge == 0
+                && graph.degree(AtomIndex(index)) == 1
+                && graph.neighbors(AtomIndex(index)).all(|other| molecule.atoms[other.0].element != Element::H)
+        };
+        let atoms: Vec<usize> = (0..molecule.atoms.len()).filter(|&index| !folded(index)).collect();
+        let mut local = vec![None; molecule.atoms.len()];
+        for (i, &atom) in atoms.iter().enumerate() {
+            local[atom] = Some(i);
+        }
+
+        let has_hydrogens = molecule.atoms.iter().any(|atom| atom.element == Element::H);
+        let mut hydrogen_atoms = vec![Vec::new(); atoms.len()];
+        let mut neighbors = vec![Vec::new(); atoms.len()];
+        for (i, &atom) in atoms.iter().enumerate() {
+            for &(other, bond) in graph.edges(AtomIndex(atom)) {
+                match local[other.0] {
+                    Some(j) => neighbors[i].push((j, bond.0)),
+                    None => hydrogen_atoms[i].push(other.0),
+                }
+            }
+        }
+        let hydrogens = atoms
+            .iter()
+            .enumerate()
+            .map(|(i, &atom)| {
+                if has_hydrogens {
+                    hydrogen_atoms[i].len()
+                } else {
+                    implied_hydrogens(molecule, &graph, &kekule, AtomIndex(atom))
+                }
+            })
+            .collect();
+
+        let aromatic = atoms
+            .iter()
+            .map(|&atom| graph.edges(AtomIndex(atom)).iter().any(|&(_, bond)| orders[bond.0] == BondOrder::Aromatic))
+            .collect();
+        let in_ring = atoms.iter().map(|&atom| atom_in_ring[atom]).collect();
+        let rings = rings
+            .iter()
+            .filter_map(|ring| ring.iter().map(|atom| local[atom.0]).collect::<Option<Vec<usize>>>())
+            .collect();
+
+        Self {
+            molecule,
+            graph,
+            atoms,
+            local,
+            orders,
+            aromatic,
+            in_ring,
+            rings,
+            small_ring_bonds,
+            hydrogens,
+            hydrogen_atoms,
+            neighbors,
+            classes: Vec::new(),
+            ranks: Vec::new(),
+        }
+    }
+
+    // Refines invariants into symmetry classes, then breaks ties one atom at
+    // a time until every atom has its own rank
+    fn rank(&mut self) {
+        let invariants: Vec<_> = (0..self.atoms.len())
+            .map(|i| {
+                let atom = &self.molecule.atoms[self.atoms[i]];
+                (
+                    atom.element.atomic_number(),
+                    atom.isotope.unwrap_or(0),
+                    atom.formal_charge,
+                    self.neighbors[i].len(),
+                    self.hydrogens[i],
+                    self.aromatic[i],
+                    self.in_ring[i],
+                )
+            })
+            .collect();
+        let mut ranks = dense_ranks(&invariants);
+        self.refine(&mut ranks);
+        self.classes = ranks.clone();
+
+        loop {
+            let mut counts = vec![0; ranks.len()];
+            for &rank in &ranks {
+                counts[rank] += 1;
+            }
+            let Some(tied) = (0..counts.len()).find(|&rank| counts[rank] > 1) else {
+                break;
+            };
+            let chosen = ranks.iter().position(|&rank| rank == tied).unwrap_or(0);
+            for (i, rank) in ranks.iter_mut().enumerate() {
+                *rank = 2 * *rank + usize::from(i != chosen);
+            }
+            ranks = dense_ranks(&ranks);
+            self.refine(&mut ranks);
+        }
+
+        for neighbors in &mut self.neighbors {
+            neighbors.sort_by_key(|&(other, _)| ranks[other]);
+        }
+        self.ranks = ranks;
+    }
+
+    fn refine(&self, ranks: &mut Vec<usize>) {
+        let mut classes = count_classes(ranks);
+        loop {
+            let keys: Vec<(usize, Vec<(usize, usize)>)> = (0..ranks.len())
+                .map(|i| {
+                    let mut around: Vec<(usize, usize)> = self.neighbors[i]
+                        .iter()
+                        .map(|&(other, bond)| (ranks[other], order_code(self.orders[bond])))
+                        .collect();
+                    around.sort_unstable();
+                    (ranks[i], around)
+                })
+                .collect();
+            let refined = dense_ranks(&keys);
+            let refined_classes = count_classes(&refined);
+            *ranks = refined;
+            if refined_classes == classes {
+                break;
+            }
+            classes = refined_classes;
+        }
+    }
+
+    fn write(&self, stereo: bool) -> io::Result<String> {
+        let walk = self.walk();
+        let order: Vec<Vec<Slot>> = (0..self.atoms.len()).map(|i| self.written_order(i, &walk)).collect();
+
+        let mut chirality: Vec<Chirality> = (0..self.atoms.len())
+            .map(|i| if stereo { self.chirality(i, &order[i], walk.parent[i].is_some()) } else { Chirality::None })
+            .collect();
+        if stereo {
+            for (i, j) in self.ring_pairs() {
+                let (first, second) = if walk.visit[i] < walk.visit[j] { (i, j) } else { (j, i) };
+                let configurations = [first, second].map(|k| self.configuration(k, &order[k], walk.parent[k].is_some()));
+                // Only the relation between the two is stereo, so flipping both is the same molecule
+                if let [first_configuration @ (Chirality::Clockwise | Chirality::CounterClockwise), second_configuration] =
+                    configurations
+                    && second_configuration != Chirality::None
+                {
+                    chirality[first] = Chirality::CounterClockwise;
+                    chirality[second] = if first_configuration == Chirality::Clockwise {
+                        second_configuration.inverted()
+                    } else {
+                        second_configuration
+                    };
+                }
+            }
+        }
+        let directions = if stereo { self.directions(&walk, &order) } else { vec![None; self.molecule.bonds.len()] };
+
+        let mut fragments: Vec<String> = walk
+            .roots
+            .iter()
+            .map(|&root| self.write_fragment(root, &walk, &chirality, &directions))
+            .collect::<io::Result<_>>()?;
+        fragments.sort();
+        Ok(fragments.join("."))
+    }
+
+    fn walk(&self) -> Walk {
+        let count = self.atoms.len();
+        let mut walk = Walk {
+            visit: vec![usize::MAX; count],
+            parent: vec![None; count],
+            children: vec![Vec::new(); count],
+            closings: vec![Vec::new(); count],
+            openings: vec![Vec::new(); count],
+            roots: Vec::new(),
+        };
+        let mut seen_bonds = vec![false; self.molecule.bonds.len()];
+        let mut visited = 0;
+
+        let mut by_rank: Vec<usize> = (0..count).collect();
+        by_rank.sort_by_key(|&i| self.ranks[i]);
+        for root in by_rank {
+            if walk.visit[root] != usize::MAX {
+                continue;
+            }
+            walk.roots.push(root);
+            walk.visit[root] = visited;
+            visited += 1;
+
+            let mut stack = vec![(root, 0)];
+            while let Some(&(atom, next)) = stack.last() {
+                let Some(&(other, bond)) = self.neighbors[atom].get(next) else {
+                    stack.pop();
+                    continue;
+                };
+                stack.last_mut().unwrap().1 += 1;
+                if seen_bonds[bond] {
+                    continue;
+                }
+                seen_bonds[bond] = true;
+                if walk.visit[other] == usize::MAX {
+                    walk.visit[other] = visited;
+                    visited += 1;
+                    walk.parent[other] = Some((atom, bond));
+                    walk.children[atom].push((other, bond));
+                    stack.push((other, 0));
+                } else {
+                    // Back to an ancestor: the ring opens there and closes here
+                    walk.openings[other].push((atom, bond));
+                    walk.closings[atom].push((other, bond));
+                }
+            }
+        }
+
+        walk
+    }
+
+    // Neighbors in the order the SMILES will list them: the preceding atom, a
+    // bracket hydrogen, ring closures as written, then branches
+    fn written_order(&self, i: usize, walk: &Walk) -> Vec<Slot> {
+        let atom = |(other, _): (usize, usize)| Slot::Atom(self.atoms[other]);
+        let mut order: Vec<Slot> = walk.parent[i].into_iter().map(atom).collect();
+        if self.hydrogens[i] > 0 {
+            order.push(self.hydrogen_atoms[i].first().map_or(Slot::Implied, |&hydrogen| Slot::Atom(hydrogen)));
+        }
+        order.extend(walk.closings[i].iter().copied().map(atom));
+        order.extend(walk.openings[i].iter().copied().map(atom));
+        order.extend(walk.children[i].iter().copied().map(atom));
+        order
+    }
+
+    fn chirality(&self, i: usize, order: &[Slot], has_parent: bool) -> Chirality {
+        let distinct = {
+            let mut classes: Vec<usize> = self.neighbors[i].iter().map(|&(other, _)| self.classes[other]).collect();
+            classes.sort_unstable();
+            classes.dedup();
+            classes.len() == self.neighbors[i].len()
+        };
+        if !distinct {
+            return Chirality::None;
+        }
+        self.configuration(i, order, has_parent)
+    }
+
+    // Chirality around `i` for the given neighbor order, whether or not its
+    // neighbors are all different
+    fn configuration(&self, i: usize, order: &[Slot], has_parent: bool) -> Chirality {
+        let index = self.atoms[i];
+        let atom = &self.molecule.atoms[index];
+        let bonds: Vec<BondOrder> = self.neighbors[i].iter().map(|&(_, bond)| self.orders[bond]).collect();
+        let tetrahedral = self.hydrogens[i] <= 1
+            && self.neighbors[i].len() + self.hydrogens[i] == 4
+            && bonds.iter().all(|&order| order == BondOrder::Single);
+        // Sulfoxides, sulfonium ions and phosphines keep a lone pair in place of the fourth group
+        let pyramidal = matches!(atom.element, Element::S | Element::Se | Element::P)
+            && self.hydrogens[i] == 0
+            && bonds.len() == 3
+            && bonds.iter().filter(|&&order| order == BondOrder::Single).count() >= 2
+            && !bonds.iter().any(|&order| matches!(order, BondOrder::Triple | BondOrder::Aromatic));
+        if !(tetrahedral || pyramidal) {
+            return Chirality::None;
+        }
+
+        // The lone pair sits where a hydrogen would, right after the preceding atom
+        let mut order = order.to_vec();
+        if pyramidal {
+            order.insert(usize::from(has_parent), Slot::Implied);
+        }
+
+        // Chirality read from SMILES is relative to bond order, lone pair last
+        if atom.chirality != Chirality::None {
+            let mut stored: Vec<Slot> =
+                self.graph.neighbors(AtomIndex(index)).map(|other| Slot::Atom(other.0)).collect();
+            if stored.len() == 3 {
+                stored.push(Slot::Implied);
+            }
+            if let Some(even) = permutation_parity(&order, &stored) {
+                return if even { atom.chirality } else { atom.chirality.inverted() };
+            }
+        }
+
+        self.chirality_from_coordinates(index, &order)
+    }
+
+    // Atoms opposite each other in an even ring, each with its two ring
+    // neighbors alike and its other neighbors different, as C1 and C4 of a
+    // 1,4-disubstituted cyclohexane
+    fn ring_pairs(&self) -> Vec<(usize, usize)> {
+        let mut paired = vec![false; self.atoms.len()];
+        let mut pairs = Vec::new();
+        for ring in self.rings.iter().filter(|ring| ring.len() % 2 == 0) {
+            let half = ring.len() / 2;
+            for position in 0..half {
+                let (i, j) = (ring[position], ring[position + half]);
+                if !paired[i] && !paired[j] && self.ring_center(ring, position) && self.ring_center(ring, position + half) {
+                    paired[i] = true;
+                    paired[j] = true;
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn ring_center(&self, ring: &[usize], position: usize) -> bool {
+        let i = ring[position];
+        let previous = ring[(position + ring.len() - 1) % ring.len()];
+        let next = ring[(position + 1) % ring.len()];
+        if self.classes[previous] != self.classes[next] {
+            return false;
+        }
+        let mut others: Vec<usize> = self.neighbors[i]
+            .iter()
+            .map(|&(other, _)| other)
+            .filter(|&other| other != previous && other != next)
+            .map(|other| self.classes[other])
+            .collect();
+        let count = others.len();
+        others.sort_unstable();
+        others.dedup();
+        others.len() == count && !others.contains(&self.classes[previous])
+    }
+
+    fn chirality_from_coordinates(&self, center: usize, order: &[Slot]) -> Chirality {
+        let origin = self.molecule.atoms[center].position;
+        let known: Vec<[f32; 3]> = order
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Atom(atom) => Some(sub(self.molecule.atoms[*atom].position, origin)),
+                Slot::Implied => None,
+            })
+            .collect();
+        // A missing hydrogen or lone pair points away from the other three
+        let implied = known.iter().fold([0.0; 3], |sum, &vector| sub(sum, normalize(vector)));
+        let mut known = known.into_iter();
+        let vectors: Vec<[f32; 3]> = order
+            .iter()
+            .map(|slot| match slot {
+                Slot::Atom(_) => known.next().unwrap_or([0.0; 3]),
+                Slot::Implied => implied,
+            })
+            .collect();
+        if vectors.len() != 4 {
+            return Chirality::None;
+        }
+
+        let [first, second, third] = [1, 2, 3].map(|k| sub(vectors[k], vectors[0]));
+        let volume = dot(first, cross(second, third));
+        if volume.abs() < 1e-3 {
+            Chirality::None
+        } else if volume < 0.0 {
+            Chirality::CounterClockwise
+        } else {
+            Chirality::Clockwise
+        }
+    }
+
+    // `/` or `\` per bond as it reads left to right in the output
+    fn directions(&self, walk: &Walk, order: &[Vec<Slot>]) -> Vec<Option<BondDirection>> {
+        let mut directions: Vec<Option<BondDirection>> = vec![None; self.molecule.bonds.len()];
+        let earlier = |bond: usize| {
+            let (atom1, atom2) = (self.molecule.bonds[bond].atom1.0, self.molecule.bonds[bond].atom2.0);
+            match (self.local[atom1], self.local[atom2]) {
+                (Some(a), Some(b)) if walk.visit[b] < walk.visit[a] => atom2,
+                _ => atom1,
+            }
+        };
+        // Direction of a bond read from `atom` outwards
+        let from = |atom: usize, bond: usize, direction: BondDirection| {
+            if earlier(bond) == atom { direction } else { direction.reversed() }
+        };
+
+        let mut double_bonds: Vec<(usize, usize, usize)> = Vec::new();
+        for (i, neighbors) in self.neighbors.iter().enumerate() {
+            for &(j, bond) in neighbors {
+                if walk.visit[i] < walk.visit[j] && self.orders[bond] == BondOrder::Double && !self.small_ring_bonds[bond] {
+                    double_bonds.push((i, j, bond));
+                }
+            }
+        }
+        double_bonds.sort_by_key(|&(i, _, _)| walk.visit[i]);
+
+        for (a, b, double) in double_bonds {
+            let (Some((ref_a, bond_a)), Some((ref_b, bond_b))) =
+                (self.reference(a, double, &order[a]), self.reference(b, double, &order[b]))
+            else {
+                continue;
+            };
+            let Some(cis) = self.stored_cis(a, b, double, ref_a, ref_b).or_else(|| self.cis_from_coordinates(a, b, ref_a, ref_b))
+            else {
+                continue;
+            };
+            let (atom_a, atom_b) = (self.atoms[a], self.atoms[b]);
+
+            match (directions[bond_a], directions[bond_b]) {
+                (Some(_), Some(_)) => {}
+                (Some(existing), None) => {
+                    let outward = from(atom_a, bond_a, existing);
+                    let wanted = if cis { outward } else { outward.reversed() };
+                    directions[bond_b] = Some(from(atom_b, bond_b, wanted));
+                }
+                (None, Some(existing)) => {
+                    let outward = from(atom_b, bond_b, existing);
+                    let wanted = if cis { outward } else { outward.reversed() };
+                    directions[bond_a] = Some(from(atom_a, bond_a, wanted));
+                }
+                (None, None) => {
+                    directions[bond_a] = Some(BondDirection::Up);
+                    let outward = from(atom_a, bond_a, BondDirection::Up);
+                    let wanted = if cis { outward } else { outward.reversed() };
+                    directions[bond_b] = Some(from(atom_b, bond_b, wanted));
+                }
+            }
+        }
+
+        directions
+    }
+
+    // The first written single-bonded neighbor of a double bond end, when the
+    // end can carry cis/trans stereo at all
+    fn reference(&self, i: usize, double: usize, order: &[Slot]) -> Option<(usize, usize)> {
+        let others: Vec<(usize, usize)> = self.neighbors[i].iter().copied().filter(|&(_, bond)| bond != double).collect();
+        let connections = others.len() + self.hydrogens[i];
+        if others.is_empty() || connections > 2 || self.hydrogens[i] > 1 {
+            return None;
+        }
+        if others.len() == 2 && self.classes[others[0].0] == self.classes[others[1].0] {
+            return None;
+        }
+        order.iter().find_map(|slot| {
+            let Slot::Atom(atom) = *slot else {
+                return None;
+            };
+            let j = self.local[atom]?;
+            others
+                .iter()
+                .find(|&&(other, bond)| other == j && self.orders[bond] == BondOrder::Single)
+                .map(|&(_, bond)| (atom, bond))
+        })
+    }
+
+    // Cis/trans of the references from `/` and `\` read from SMILES
+    fn stored_cis(&self, a: usize, b: usize, double: usize, ref_a: usize, ref_b: usize) -> Option<bool> {
+        let directed = |i: usize| {
+            let atom = self.atoms[i];
+            self.molecule.bonds.iter().enumerate().find_map(|(index, bond)| {
+                if index == double || bond.direction == BondDirection::None {
+                    return None;
+                }
+                if bond.atom1.0 == atom {
+                    Some((bond.atom2.0, bond.direction))
+                } else if bond.atom2.0 == atom {
+                    Some((bond.atom1.0, bond.direction.reversed()))
+                } else {
+                    None
+                }
+            })
+        };
+        let ((neighbor_a, direction_a), (neighbor_b, direction_b)) = (directed(a)?, directed(b)?);
+        let cis = (direction_a == direction_b) ^ (neighbor_a != ref_a) ^ (neighbor_b != ref_b);
+        Some(cis)
+    }
+
+    fn cis_from_coordinates(&self, a: usize, b: usize, ref_a: usize, ref_b: usize) -> Option<bool> {
+        let position = |atom: usize| self.molecule.atoms[atom].position;
+        let (pa, pb) = (position(self.atoms[a]), position(self.atoms[b]));
+        let axis = normalize(sub(pb, pa));
+        let perpendicular = |vector: [f32; 3]| sub(vector, scale(axis, dot(vector, axis)));
+        let (side_a, side_b) = (perpendicular(sub(position(ref_a), pa)), perpendicular(sub(position(ref_b), pb)));
+        if dot(sub(pb, pa), sub(pb, pa)) < 1e-6 || dot(side_a, side_a) < 1e-6 || dot(side_b, side_b) < 1e-6 {
+            return None;
+        }
+        Some(dot(side_a, side_b) > 0.0)
+    }
+
+    fn write_fragment(
+        &self,
+        root: usize,
+        walk: &Walk,
+        chirality: &[Chirality],
+        directions: &[Option<BondDirection>],
+    ) -> io::Result<String> {
+        enum Task {
+            Atom(usize),
+            Text(&'static str),
+        }
+
+        let mut smiles = String::new();
+        let mut free_digits: Vec<usize> = (1..100).rev().collect();
+        let mut digits: HashMap<usize, usize> = HashMap::new();
+        let mut tasks = vec![Task::Atom(root)];
+
+        while let Some(task) = tasks.pop() {
+            let i = match task {
+                Task::Text(text) => {
+                    smiles.push_str(text);
+                    continue;
+                }
+                Task::Atom(i) => i,
+            };
+            if let Some((parent, bond)) = walk.parent[i] {
+                smiles.push_str(&self.bond_symbol(parent, i, bond, directions));
+            }
+            smiles.push_str(&self.atom_token(i, chirality[i]));
+
+            for &(_, bond) in &walk.closings[i] {
+                let digit = digits.remove(&bond).unwrap_or(0);
+                push_ring_digit(&mut smiles, digit);
+                free_digits.push(digit);
+                free_digits.sort_unstable_by(|a, b| b.cmp(a));
+            }
+            for &(other, bond) in &walk.openings[i] {
+                // SMILES has no ring bond numbers past 99
+                let Some(digit) = free_digits.pop() else {
+                    return Err(invalid_input("more than 99 ring bonds open at once"));
+                };
+                digits.insert(bond, digit);
+                smiles.push_str(&self.bond_symbol(i, other, bond, directions));
+                push_ring_digit(&mut smiles, digit);
+            }
+
+            if let Some((&(last, _), branches)) = walk.children[i].split_last() {
+                tasks.push(Task::Atom(last));
+                for &(child, _) in branches.iter().rev() {
+                    tasks.push(Task::Text(")"));
+                    tasks.push(Task::Atom(child));
+                    tasks.push(Task::Text("("));
+                }
+            }
+        }
+
+        Ok(smiles)
+    }
+
+    fn bond_symbol(&self, i: usize, j: usize, bond: usize, directions: &[Option<BondDirection>]) -> String {
+        let both_aromatic = self.aromatic[i] && self.aromatic[j];
+        let symbol = match self.orders[bond] {
+            BondOrder::Double => "=",
+            BondOrder::Triple => "#",
+            BondOrder::Aromatic if both_aromatic => "",
+            BondOrder::Aromatic => ":",
+            BondOrder::Single => match directions[bond] {
+                Some(BondDirection::Up) => "/",
+                Some(BondDirection::Down) => "\\",
+                _ if both_aromatic => "-",
+                _ => "",
+            },
+        };
+        symbol.to_string()
+    }
+
+    fn atom_token(&self, i: usize, chirality: Chirality) -> String {
+        let atom = &self.molecule.atoms[self.atoms[i]];
+        let aromatic = self.aromatic[i]
+            && matches!(atom.element, Element::B | Element::C | Element::N | Element::O | Element::P | Element::S | Element::Se);
+        let symbol = match atom.element {
+            Element::Unknown => "*".to_string(),
+            element if aromatic => element.symbol().to_ascii_lowercase(),
+            element => element.symbol().to_string(),
+        };
+
+        let used = self.neighbors[i].iter().map(|&(_, bond)| bond_valence(self.orders[bond])).sum();
+        let organic = ORGANIC_SYMBOLS.contains(&symbol.as_str())
+            && atom.formal_charge == 0
+            && atom.isotope.is_none()
+            && chirality == Chirality::None
+            && self.hydrogens[i] == implicit_hydrogens(atom.element, used, aromatic);
+        if organic {
+            return symbol;
+        }
+
+        let mut token = String::from("[");
+        if let Some(isotope) = atom.isotope {
+            token.push_str(&isotope.to_string());
+        }
+        token.push_str(&symbol);
+        token.push_str(match chirality {
+            Chirality::None => "",
+            Chirality::CounterClockwise => "@",
+            Chirality::Clockwise => "@@",
+        });
+        match self.hydrogens[i] {
+            0 => {}
+            1 => token.push('H'),
+            count => token.push_str(&format!("H{}", count)),
+        }
+        match atom.formal_charge {
+            0 => {}
+            1 => token.push('+'),
+            -1 => token.push('-'),
+            charge => token.push_str(&format!("{:+}", charge)),
+        }
+        token.push(']');
+        token
+    }
+}
+
+// Bonds around each ring, in `Molecule::bonds` indices
+pub(crate) fn ring_bonds(graph: &MolecularGraph, rings: &[Vec<AtomIndex>]) -> Vec<Vec<usize>> {
+    rings
+        .iter()
+        .map(|ring| {
+            (0..ring.len())
+                .filter_map(|i| graph.bond_between(ring[i], ring[(i + 1) % ring.len()]))
+                .map(|bond| bond.0)
+                .collect()
+        })
+        .collect()
+}
+
+// Kekulé orders, or the stored ones when no Kekulé form fits, and the same
+// orders with aromatic rings normalized
+pub(crate) fn perceived_orders(
+    molecule: &Molecule,
+    graph: &MolecularGraph,
+    rings: &[Vec<AtomIndex>],
+    ring_bonds: &[Vec<usize>],
+) -> (Vec<BondOrder>, Vec<BondOrder>) {
+    let kekule = kekulize(molecule).unwrap_or_else(|| molecule.bonds.iter().map(|bond| bond.order).collect());
+    let orders = aromatic_orders(molecule, graph, rings, ring_bonds, &kekule);
+    (kekule, orders)
+}
+
+// Hydrogens an atom carries at its usual valence, for structures that come
+// without any hydrogen atoms
+pub(crate) fn implied_hydrogens(molecule: &Molecule, graph: &MolecularGraph, kekule: &[BondOrder], atom: AtomIndex) -> usize {
+    let used: usize = graph.edges(atom).iter().map(|&(_, bond)| bond_valence(kekule[bond.0])).sum();
+    let atom = &molecule.atoms[atom.0];
+    let valence = match atom.element {
+        Element::F | Element::Cl | Element::Br | Element::I if atom.formal_charge == 0 => Some(1),
+        element => default_valence(element, atom.formal_charge),
+    };
+    valence.map_or(0, |valence| (valence - used as i32).max(0) as usize)
+}
+
+// Kekulé orders with Hückel rings of five to seven atoms made aromatic.
+// Rings fused to aromatic ones count the shared double bonds, so naphthalene
+// is aromatic whichever Kekulé form it comes in.
+fn aromatic_orders(
+    molecule: &Molecule,
+    graph: &MolecularGraph,
+    rings: &[Vec<AtomIndex>],
+    ring_bonds: &[Vec<usize>],
+    kekule: &[BondOrder],
+) -> Vec<BondOrder> {
+    let mut aromatic = vec![false; kekule.len()];
+    loop {
+        let mut changed = false;
+        for (ring, bonds) in rings.iter().zip(ring_bonds) {
+            if !(5..=7).contains(&ring.len())
+                || bonds.iter().all(|&bond| aromatic[bond])
+                || bonds.iter().any(|&bond| kekule[bond] == BondOrder::Aromatic)
+            {
+                continue;
+            }
+            let electrons: Option<usize> = ring
+                .iter()
+                .map(|&atom| pi_electrons(molecule, graph, atom, bonds, kekule, &aromatic))
+                .sum();
+            if electrons.is_some_and(|electrons| electrons % 4 == 2) {
+                for &bond in bonds {
+                    aromatic[bond] = true;
+                }
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    kekule
+        .iter()
+        .zip(&aromatic)
+        .map(|(&order, &aromatic)| if aromatic { BondOrder::Aromatic } else { order })
+        .collect()
+}
+
+fn pi_electrons(
+    molecule: &Molecule,
+    graph: &MolecularGraph,
+    atom: AtomIndex,
+    ring_bonds: &[usize],
+    kekule: &[BondOrder],
+    aromatic: &[bool],
+) -> Option<usize> {
+    let mut exocyclic = false;
+    for &(_, bond) in graph.edges(atom) {
+        match kekule[bond.0] {
+            BondOrder::Double if ring_bonds.contains(&bond.0) || aromatic[bond.0] => return Some(1),
+            BondOrder::Double => exocyclic = true,
+            BondOrder::Triple => return None,
+            _ => {}
+        }
+    }
+    let atom = &molecule.atoms[atom.0];
+    // Carbonyl-type ring carbons, as in pyridones, give no electrons
+    if exocyclic {
+        return (atom.element == Element::C).then_some(0);
+    }
+    match (atom.element, atom.formal_charge) {
+        (Element::N | Element::P | Element::O | Element::S | Element::Se, 0) | (Element::C, -1) => Some(2),
+        (Element::C, 1) | (Element::B, 0) => Some(0),
+        _ => None,
+    }
+}
+
+// Whether `first` is an even permutation of `second`, or `None` when they
+// hold different neighbors
+fn permutation_parity(first: &[Slot], second: &[Slot]) -> Option<bool> {
+    if first.len() != second.len() {
+        return None;
+    }
+    let mut permutation = Vec::with_capacity(first.len());
+    for slot in first {
+        let position = second.iter().enumerate().position(|(k, other)| other == slot && !permutation.contains(&k))?;
+        permutation.push(position);
+    }
+    let mut swaps = 0;
+    for i in 0..permutation.len() {
+        while permutation[i] != i {
+            let target = permutation[i];
+            permutation.swap(i, target);
+            swaps += 1;
+        }
+    }
+    Some(swaps % 2 == 0)
+}
+
+// Ring bond digit as written, with `%` from ten on
+fn push_ring_digit(smiles: &mut String, digit: usize) {
+    if digit < 10 {
+        smiles.push_str(&digit.to_string());
+    } else {
+        smiles.push_str(&format!("%{}", digit));
+    }
+}
+
+// Ranks from 0 up, equal keys sharing a rank
+fn dense_ranks<T: Ord>(keys: &[T]) -> Vec<usize> {
+    let mut sorted: Vec<&T> = keys.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    keys.iter().map(|key| sorted.binary_search(&key).unwrap_or(0)).collect()
+}
+
+fn count_classes(ranks: &[usize]) -> usize {
+    let mut classes = ranks.to_vec();
+    classes.sort_unstable();
+    classes.dedup();
+    classes.len()
+}
+
+fn order_code(order: BondOrder) -> usize {
+    match order {
+        BondOrder::Single => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+        BondOrder::Aromatic => 4,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ParsedAtom {
+    element: Element,
+    aromatic: bool,
+    isotope: Option<u16>,
+    chirality: Chirality,
+    hydrogens: Option<u8>,
+    charge: i8,
+}
+
+impl Default for ParsedAtom {
+    fn default() -> Self {
+        Self {
+            element: Element::Unknown,
+            aromatic: false,
+            isotope: None,
+            chirality: Chirality::None,
+            hydrogens: Some(0),
+            charge: 0,
+        }
+    }
+}
+
+// An organic subset atom such as `Cl` or `c`, or a bracket atom
+fn parse_atom(chars: &[char]) -> Option<(ParsedAtom, usize)> {
+    if chars[0] == '[' {
+        let end = chars.iter().position(|&c| c == ']')?;
+        return Some((parse_bracket(&chars[1..end])?, end + 1));
+    }
+
+    let start: String = chars.iter().take(2).collect();
+    let symbol = ORGANIC_SYMBOLS.iter().find(|symbol| start.starts_with(*symbol))?;
+    let atom = ParsedAtom {
+        element: element(symbol)?,
+        aromatic: is_aromatic(symbol),
+        // Wildcards get no implicit hydrogens
+        hydrogens: (*symbol == "*").then_some(0),
+        ..ParsedAtom::default()
+    };
+    Some((atom, symbol.len()))
+}
+
+// The inside of `[...]`: isotope, symbol, chirality, hydrogens, charge, class
+fn parse_bracket(chars: &[char]) -> Option<ParsedAtom> {
+    let mut i = 0;
+    let mut atom = ParsedAtom::default();
+
+    let digits = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        atom.isotope = Some(chars[..digits].iter().collect::<String>().parse().ok()?);
+        i = digits;
+    }
+
+    let rest: String = chars[i..].iter().collect();
+    let symbol = BRACKET_SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol))?;
+    i += symbol.len();
+    atom.element = element(symbol)?;
+    atom.aromatic = is_aromatic(symbol);
+
+    if chars.get(i) == Some(&'@') {
+        i += 1;
+        atom.chirality = if chars.get(i) == Some(&'@') {
+            i += 1;
+            Chirality::Clockwise
+        } else if chars[i..].starts_with(&['T', 'H']) {
+            // @TH1 and @TH2 are the long forms of @ and @@
+            let class = *chars.get(i + 2)?;
+            i += 3;
+            match class {
+                '1' => Chirality::CounterClockwise,
+                '2' => Chirality::Clockwise,
+                _ => return None,
+            }
+        } else if ["AL", "SP", "TB", "OH"].iter().any(|class| {
+            chars[i..].starts_with(&class.chars().collect::<Vec<_>>()) && chars.get(i + 2).is_some_and(char::is_ascii_digit)
+        }) {
+            // Allene, square planar, trigonal bipyramidal and octahedral classes
+            return None;
+        } else {
+            Chirality::CounterClockwise
+        };
+    }
+
+    atom.hydrogens = Some(0);
+    if chars.get(i) == Some(&'H') {
+        i += 1;
+        let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        atom.hydrogens = Some(if digits == 0 { 1 } else { chars[i..i + digits].iter().collect::<String>().parse().ok()? });
+        i += digits;
+    }
+
+    if let Some(&sign) = chars.get(i).filter(|&&c| c == '+' || c == '-') {
+        let repeated = chars[i..].iter().take_while(|&&c| c == sign).count();
+        i += repeated;
+        let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let magnitude: i8 = if digits > 0 {
+            if repeated > 1 {
+                return None;
+            }
+            chars[i..i + digits].iter().collect::<String>().parse().ok()?
+        } else {
+            repeated as i8
+        };
+        i += digits;
+        atom.charge = if sign == '+' { magnitude } else { -magnitude };
+    }
+
+    // Atom classes (`:n`) carry no chemistry and are dropped
+    if chars.get(i) == Some(&':') {
+        let digits = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        i += 1 + digits;
+    }
+
+    (i == chars.len()).then_some(atom)
+}
+
+// Hydrogens implied on an unbracketed atom: the lowest normal valence that
+// fits its bonds, less one for aromatic atoms to leave room for the π system
+fn implicit_hydrogens(element: Element, used: usize, aromatic: bool) -> usize {
+    let valences: &[usize] = match element {
+        Element::B => &[3],
+        Element::C => &[4],
+        Element::N => &[3, 5],
+        Element::O => &[2],
+        Element::P => &[3, 5],
+        Element::S => &[2, 4, 6],
+        Element::F | Element::Cl | Element::Br | Element::I => &[1],
+        _ => return 0,
+    };
+    let Some(&valence) = valences.iter().find(|&&valence| valence >= used) else {
+        return 0;
+    };
+    valence.saturating_sub(used + usize::from(aromatic))
+}
+
+// Aromatic bonds count one, as SMILES valence rules do
+pub(crate) fn bond_valence(order: BondOrder) -> usize {
+    match order {
+        BondOrder::Single | BondOrder::Aromatic => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+    }
+}
+
+// A ring bond number: one digit, or `%` and two digits
+fn ring_number(chars: &[char]) -> Option<(usize, usize)> {
+    if chars[0] == '%' {
+        let tens = chars.get(1)?.to_digit(10)?;
+        let ones = chars.get(2)?.to_digit(10)?;
+        return Some(((tens * 10 + ones) as usize, 3));
+    }
+    Some((chars[0].to_digit(10)? as usize, 1))
+}
+
+// Whether one list is an even permutation of the other
+fn same_parity(first: &[usize], second: &[usize]) -> bool {
+    let mut permutation: Vec<usize> = first.iter().map(|atom| second.iter().position(|other| other == atom).unwrap_or(0)).collect();
+    let mut swaps = 0;
+    for i in 0..permutation.len() {
+        while permutation[i] != i {
+            let target = permutation[i];
+            permutation.swap(i, target);
+            swaps += 1;
+        }
+    }
+    swaps % 2 == 0
+}
+
+fn atom_name(element: Element, number: usize) -> String {
+    let symbol = if element == Element::Unknown { "X" } else { element.symbol() };
+    format!("{}{}", symbol.to_ascii_uppercase(), number)
+}
+
+// Element of an organic subset or bracket symbol; `*` is any atom
+fn element(symbol: &str) -> Option<Element> {
+    if symbol == "*" {
+        return Some(Element::Unknown);
+    }
+    let mut chars = symbol.chars();
+    let capitalized = chars.next()?.to_ascii_uppercase().to_string() + chars.as_str();
+    let element = Element::from_symbol(&capitalized);
+    (element != Element::Unknown).then_some(element)
+}
+
+fn is_aromatic(symbol: &str) -> bool {
+    symbol.starts_with(|c: char| c.is_ascii_lowercase())
+}
+
+fn is_direction(symbol: char) -> bool {
+    symbol == '/' || symbol == '\\'
+}
+
+fn unexpected(c: char, position: usize) -> io::Error {
+    invalid_data(&format!("unexpected '{}' at position {} in SMILES", c, position))
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::{Index, IndexMut};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Element {
@@ -6,11 +7,21 @@ pub enum Element {
     Li, Be, B, C, N, O, F, Ne,
     Na, Mg, Al, Si, P, S, Cl, Ar,
     K, Ca, 
-    Fe, Cu, Zn,
+    Mn, Fe, Co, Ni, Cu, Zn, Se, Br,
+    I,
     // Add more elements as needed
     Unknown,
 }
 
+const ALL_ELEMENTS: [Element; 29] = [
+    Element::H, Element::He,
+    Element::Li, Element::Be, Element::B, Element::C, Element::N, Element::O, Element::F, Element::Ne,
+    Element::Na, Element::Mg, Element::Al, Element::Si, Element::P, Element::S, Element::Cl, Element::Ar,
+    Element::K, Element::Ca,
+    Element::Mn, Element::Fe, Element::Co, Element::Ni, Element::Cu, Element::Zn, Element::Se, Element::Br,
+    Element::I,
+];
+
 impl Element {
     pub fn from_symbol(symbol: &str) -> Self {
         match symbol.trim() {
@@ -34,15 +45,181 @@ impl Element {
             "AR" | "Ar" => Element::Ar,
             "K" => Element::K,
             "CA" | "Ca" => Element::Ca,
+            "MN" | "Mn" => Element::Mn,
             "FE" | "Fe" => Element::Fe,
+            "CO" | "Co" => Element::Co,
+            "NI" | "Ni" => Element::Ni,
             "CU" | "Cu" => Element::Cu,
             "ZN" | "Zn" => Element::Zn,
+            "SE" | "Se" => Element::Se,
+            "BR" | "Br" => Element::Br,
+            "I" => Element::I,
             _ => Element::Unknown,
         }
     }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Element::H => "H",
+            Element::He => "He",
+            Element::Li => "Li",
+            Element::Be => "Be",
+            Element::B => "B",
+            Element::C => "C",
+            Element::N => "N",
+            Element::O => "O",
+            Element::F => "F",
+            Element::Ne => "Ne",
+            Element::Na => "Na",
+            Element::Mg => "Mg",
+            Element::Al => "Al",
+            Element::Si => "Si",
+            Element::P => "P",
+            Element::S => "S",
+            Element::Cl => "Cl",
+            Element::Ar => "Ar",
+            Element::K => "K",
+            Element::Ca => "Ca",
+            Element::Mn => "Mn",
+            Element::Fe => "Fe",
+            Element::Co => "Co",
+            Element::Ni => "Ni",
+            Element::Cu => "Cu",
+            Element::Zn => "Zn",
+            Element::Se => "Se",
+            Element::Br => "Br",
+            Element::I => "I",
+            Element::Unknown => "X",
+        }
+    }
+
+    pub fn atomic_number(&self) -> u8 {
+        match self {
+            Element::H => 1,
+            Element::He => 2,
+            Element::Li => 3,
+            Element::Be => 4,
+            Element::B => 5,
+            Element::C => 6,
+            Element::N => 7,
+            Element::O => 8,
+            Element::F => 9,
+            Element::Ne => 10,
+            Element::Na => 11,
+            Element::Mg => 12,
+            Element::Al => 13,
+            Element::Si => 14,
+            Element::P => 15,
+            Element::S => 16,
+            Element::Cl => 17,
+            Element::Ar => 18,
+            Element::K => 19,
+            Element::Ca => 20,
+            Element::Mn => 25,
+            Element::Fe => 26,
+            Element::Co => 27,
+            Element::Ni => 28,
+            Element::Cu => 29,
+            Element::Zn => 30,
+            Element::Se => 34,
+            Element::Br => 35,
+            Element::I => 53,
+            Element::Unknown => 0,
+        }
+    }
+
+    pub fn from_atomic_number(number: u8) -> Self {
+        ALL_ELEMENTS
+            .iter()
+            .copied()
+            .find(|element| element.atomic_number() == number)
+            .unwrap_or(Element::Unknown)
+    }
+
+    /// Standard atomic weight in g/mol (0.0 for `Unknown`).
+    pub fn mass(&self) -> f32 {
+        match self {
+            Element::H => 1.008,
+            Element::He => 4.003,
+            Element::Li => 6.94,
+            Element::Be => 9.012,
+            Element::B => 10.81,
+            Element::C => 12.011,
+            Element::N => 14.007,
+            Element::O => 15.999,
+            Element::F => 18.998,
+            Element::Ne => 20.180,
+            Element::Na => 22.990,
+            Element::Mg => 24.305,
+            Element::Al => 26.982,
+            Element::Si => 28.085,
+            Element::P => 30.974,
+            Element::S => 32.06,
+            Element::Cl => 35.45,
+            Element::Ar => 39.948,
+            Element::K => 39.098,
+            Element::Ca => 40.078,
+            Element::Mn => 54.938,
+            Element::Fe => 55.845,
+            Element::Co => 58.933,
+            Element::Ni => 58.693,
+            Element::Cu => 63.546,
+            Element::Zn => 65.38,
+            Element::Se => 78.971,
+            Element::Br => 79.904,
+            Element::I => 126.904,
+            Element::Unknown => 0.0,
+        }
+    }
+
+    /// Guesses the element from an atomic mass, for topologies that only
+    /// store masses. Masses below 4 are taken as (possibly repartitioned) hydrogen.
+    pub fn from_mass(mass: f32) -> Self {
+        if mass > 0.0 && mass < 4.0 {
+            return Element::H;
+        }
+
+        ALL_ELEMENTS
+            .iter()
+            .copied()
+            .filter(|element| (element.mass() - mass).abs() < 0.5)
+            .min_by(|a, b| (a.mass() - mass).abs().total_cmp(&(b.mass() - mass).abs()))
+            .unwrap_or(Element::Unknown)
+    }
+}
+
+macro_rules! typed_index {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+        pub struct $name(pub usize);
+    };
 }
 
+typed_index!(
+    /// Position of an atom in `Molecule::atoms`, independent of its serial number.
+    AtomIndex
+);
+typed_index!(
+    /// Position of a bond in `Molecule::bonds`.
+    BondIndex
+);
+typed_index!(
+    /// Position of a residue in `Molecule::residues`.
+    ResidueIndex
+);
+typed_index!(
+    /// Position of a chain in `Molecule::chains`.
+    ChainIndex
+);
+typed_index!(
+    /// Position of a model in `Molecule::models`.
+    ModelIndex
+);
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Atom {
     pub id: usize,
     pub name: String,
@@ -56,16 +233,60 @@ pub struct Atom {
     pub alt_loc: char,           // Alternate location indicator
     pub ins_code: char,          // Insertion code
     pub is_hetatm: bool,         // Whether this atom is from a HETATM record
+    pub velocity: Option<[f32; 3]>, // Velocity in Å/ps, when the source format provides one
+    pub charge: Option<f32>,     // Partial charge in e, e.g. from PQR
+    pub radius: Option<f32>,     // Atomic radius in Å, e.g. from PQR
+    pub atom_type: Option<String>, // Force-field atom type
+    pub mass: Option<f32>,       // Force-field mass in g/mol
+    pub lj_sigma: Option<f32>,   // Lennard-Jones sigma in Å
+    pub lj_epsilon: Option<f32>, // Lennard-Jones well depth in kcal/mol
+    pub formal_charge: i8,
+    pub isotope: Option<u16>,    // Mass number, when given explicitly as in SMILES [13C]
+    pub chirality: Chirality,
+    pub residue: ResidueIndex,   // Owning residue, set by Molecule::add_atom
+}
+
+impl Default for Atom {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            element: Element::Unknown,
+            position: [0.0; 3],
+            residue_id: 0,
+            chain_id: ' ',
+            b_factor: 0.0,
+            occupancy: 1.0,
+            residue_name: String::new(),
+            alt_loc: ' ',
+            ins_code: ' ',
+            is_hetatm: false,
+            velocity: None,
+            charge: None,
+            radius: None,
+            atom_type: None,
+            mass: None,
+            lj_sigma: None,
+            lj_epsilon: None,
+            formal_charge: 0,
+            isotope: None,
+            chirality: Chirality::None,
+            residue: ResidueIndex(0),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bond {
-    pub atom1_id: usize,
-    pub atom2_id: usize,
+    pub atom1: AtomIndex,
+    pub atom2: AtomIndex,
     pub order: BondOrder,
+    pub direction: BondDirection,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BondOrder {
     Single,
     Double,
@@ -73,34 +294,163 @@ pub enum BondOrder {
     Aromatic,
 }
 
+/// Tetrahedral chirality in SMILES terms: looking from the first neighbor,
+/// the other three run counterclockwise (`@`) or clockwise (`@@`). Neighbors
+/// are taken in the order their bonds appear in `Molecule::bonds`; a lone
+/// pair, as on a sulfoxide sulfur, counts as the last neighbor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Chirality {
+    #[default]
+    None,
+    CounterClockwise,
+    Clockwise,
+}
+
+impl Chirality {
+    pub fn inverted(self) -> Self {
+        match self {
+            Chirality::None => Chirality::None,
+            Chirality::CounterClockwise => Chirality::Clockwise,
+            Chirality::Clockwise => Chirality::CounterClockwise,
+        }
+    }
+}
+
+/// SMILES `/` (`Up`) or `\` (`Down`) on a single bond next to a double bond,
+/// read from `atom1` to `atom2`. Together these fix cis/trans geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BondDirection {
+    #[default]
+    None,
+    Up,
+    Down,
+}
+
+impl BondDirection {
+    pub fn reversed(self) -> Self {
+        match self {
+            BondDirection::None => BondDirection::None,
+            BondDirection::Up => BondDirection::Down,
+            BondDirection::Down => BondDirection::Up,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Residue {
-    pub id: usize,
+    pub id: usize,             // Residue sequence number from the file
     pub name: String,
-    pub atoms: Vec<usize>,
+    pub atoms: Vec<AtomIndex>,
     pub ins_code: char,        // Insertion code
+    pub chain: ChainIndex,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chain {
     pub id: char,
-    pub residues: Vec<usize>,
+    pub residues: Vec<ResidueIndex>,
+    pub model: Option<ModelIndex>,  // None for atoms outside any MODEL record
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Model {
-    pub id: usize,
-    pub atoms: Vec<usize>,
+    pub id: usize,             // Serial from the MODEL record
+    pub atoms: Vec<AtomIndex>,
+}
+
+/// Periodic simulation box, stored as three box vectors in Å.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnitCell {
+    pub vectors: [[f32; 3]; 3],
+}
+
+impl UnitCell {
+    pub fn new(vectors: [[f32; 3]; 3]) -> Self {
+        Self { vectors }
+    }
+
+    pub fn orthorhombic(a: f32, b: f32, c: f32) -> Self {
+        Self {
+            vectors: [[a, 0.0, 0.0], [0.0, b, 0.0], [0.0, 0.0, c]],
+        }
+    }
+
+    /// Builds the box from edge lengths (Å) and angles (degrees), with the first
+    /// vector along x and the second in the xy plane.
+    pub fn from_parameters(a: f32, b: f32, c: f32, alpha: f32, beta: f32, gamma: f32) -> Self {
+        let (alpha, beta, gamma) = (alpha.to_radians(), beta.to_radians(), gamma.to_radians());
+        let bx = b * gamma.cos();
+        let by = b * gamma.sin();
+        let cx = c * beta.cos();
+        let cy = c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let cz = (c * c - cx * cx - cy * cy).max(0.0).sqrt();
+
+        Self {
+            vectors: [[a, 0.0, 0.0], [bx, by, 0.0], [cx, cy, cz]],
+        }
+    }
+
+    pub fn lengths(&self) -> [f32; 3] {
+        self.vectors.map(|v| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt())
+    }
+
+    /// Angles alpha (b,c), beta (a,c) and gamma (a,b) in degrees.
+    pub fn angles(&self) -> [f32; 3] {
+        let [a, b, c] = self.vectors;
+        let angle = |u: [f32; 3], v: [f32; 3]| {
+            let dot = u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+            let norm = (u[0] * u[0] + u[1] * u[1] + u[2] * u[2]).sqrt()
+                * (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+            if norm == 0.0 {
+                90.0
+            } else {
+                (dot / norm).clamp(-1.0, 1.0).acos().to_degrees()
+            }
+        };
+        [angle(b, c), angle(a, c), angle(a, b)]
+    }
+
+    pub fn is_orthorhombic(&self) -> bool {
+        let [a, b, c] = self.vectors;
+        a[1] == 0.0 && a[2] == 0.0 && b[0] == 0.0 && b[2] == 0.0 && c[0] == 0.0 && c[1] == 0.0
+    }
+}
+
+/// Header information carried by the source file.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    pub id_code: Option<String>,
+    pub classification: Option<String>,
+    pub deposition_date: Option<String>,
+    pub title: Option<String>,
+    pub space_group: Option<String>,
+    pub properties: Vec<(String, String)>,  // Named data items, e.g. SDF data tags
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Molecule {
     pub atoms: Vec<Atom>,
     pub bonds: Vec<Bond>,
-    pub residues: HashMap<(usize, char), Residue>,  
-    pub chains: HashMap<char, Chain>,
+    pub residues: Vec<Residue>,
+    pub chains: Vec<Chain>,
     pub models: Vec<Model>,
-    pub current_model: Option<usize>,
+    pub current_model: Option<ModelIndex>,
+    pub unit_cell: Option<UnitCell>,
+    pub metadata: Metadata,
+}
+
+impl Default for Molecule {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Molecule {
@@ -108,86 +458,119 @@ impl Molecule {
         Self {
             atoms: Vec::new(),
             bonds: Vec::new(),
-            residues: HashMap::new(),
-            chains: HashMap::new(),
+            residues: Vec::new(),
+            chains: Vec::new(),
             models: Vec::new(),
             current_model: None,
+            unit_cell: None,
+            metadata: Metadata::default(),
         }
     }
     
-    pub fn add_atom(&mut self, atom: Atom) -> usize {
-        let atom_id = atom.id;
-        let residue_id = atom.residue_id;
-        let residue_name = atom.residue_name.clone();
-        let ins_code = atom.ins_code;
-        
-        self.atoms.push(atom);
-        
-        let residue_key = (residue_id, ins_code);
-        if !self.residues.contains_key(&residue_key) {
-            self.residues.insert(residue_key, Residue {
-                id: residue_id,
-                name: residue_name,
+    /// Appends an atom, placing it in the residue, chain and model it belongs
+    /// to. Consecutive atoms with the same chain, residue number, insertion
+    /// code and residue name share a residue.
+    pub fn add_atom(&mut self, mut atom: Atom) -> AtomIndex {
+        let index = AtomIndex(self.atoms.len());
+
+        let same_residue = self.residues.last().is_some_and(|residue| {
+            let chain = &self.chains[residue.chain.0];
+            chain.id == atom.chain_id
+                && chain.model == self.current_model
+                && residue.id == atom.residue_id
+                && residue.ins_code == atom.ins_code
+                && residue.name == atom.residue_name
+        });
+        if !same_residue {
+            let chain = self.chain_for(atom.chain_id);
+            self.chains[chain.0].residues.push(ResidueIndex(self.residues.len()));
+            self.residues.push(Residue {
+                id: atom.residue_id,
+                name: atom.residue_name.clone(),
                 atoms: Vec::new(),
-                ins_code,
+                ins_code: atom.ins_code,
+                chain,
             });
         }
-        
-        if let Some(residue) = self.residues.get_mut(&residue_key) {
-            residue.atoms.push(atom_id);
+
+        let residue = ResidueIndex(self.residues.len() - 1);
+        self.residues[residue.0].atoms.push(index);
+        atom.residue = residue;
+        self.atoms.push(atom);
+
+        if let Some(model) = self.current_model {
+            self.models[model.0].atoms.push(index);
         }
-        
-        let chain_id = self.atoms.last().unwrap().chain_id;
-        if !self.chains.contains_key(&chain_id) {
-            self.chains.insert(chain_id, Chain {
+
+        index
+    }
+
+    // Chains are shared by all residues with the same ID in the current model,
+    // so ligands listed after other chains still join their own chain
+    fn chain_for(&mut self, chain_id: char) -> ChainIndex {
+        let existing = self
+            .chains
+            .iter()
+            .rposition(|chain| chain.id == chain_id && chain.model == self.current_model);
+
+        ChainIndex(existing.unwrap_or_else(|| {
+            self.chains.push(Chain {
                 id: chain_id,
                 residues: Vec::new(),
+                model: self.current_model,
             });
-        }
-        
-        if let Some(chain) = self.chains.get_mut(&chain_id) {
-            if !chain.residues.contains(&residue_id) {
-                chain.residues.push(residue_id);
-            }
-        }
-        
-        // Add to current model if one exists
-        if let Some(model_id) = self.current_model {
-            if let Some(model) = self.models.get_mut(model_id - 1) {  // Models are 1-indexed
-                model.atoms.push(atom_id);
-            }
-        }
-        
-        atom_id
+            self.chains.len() - 1
+        }))
     }
-    
-    pub fn add_bond(&mut self, atom1_id: usize, atom2_id: usize, order: BondOrder) {
+
+    pub fn add_bond(&mut self, atom1: AtomIndex, atom2: AtomIndex, order: BondOrder) {
         self.bonds.push(Bond {
-            atom1_id,
-            atom2_id,
+            atom1,
+            atom2,
             order,
+            direction: BondDirection::None,
         });
     }
-    
+
     pub fn start_model(&mut self, model_id: usize) {
         self.models.push(Model {
             id: model_id,
             atoms: Vec::new(),
         });
-        self.current_model = Some(model_id);
+        self.current_model = Some(ModelIndex(self.models.len() - 1));
     }
-    
+
     pub fn end_model(&mut self) {
         self.current_model = None;
     }
-    
+
+    /// Finds the first atom with the given serial number.
+    pub fn find_atom(&self, serial: usize) -> Option<AtomIndex> {
+        self.atoms.iter().position(|atom| atom.id == serial).map(AtomIndex)
+    }
+
+    /// Adds single bonds between atoms closer than 2 Å, skipping pairs that are
+    /// already bonded (e.g. from CONECT records).
     pub fn calculate_bonds(&mut self) {
+        self.calculate_bonds_between(&[]);
+    }
+
+    /// Distance bonding that skips pairs where both atoms are flagged, e.g.
+    /// because residue templates already bonded them.
+    pub(crate) fn calculate_bonds_between(&mut self, covered: &[bool]) {
+        let is_covered = |index: usize| covered.get(index).copied().unwrap_or(false);
         let mut bonds_to_add = Vec::new();
+        let existing: HashSet<(AtomIndex, AtomIndex)> = self.bonds.iter()
+            .map(|bond| (bond.atom1.min(bond.atom2), bond.atom1.max(bond.atom2)))
+            .collect();
         
         for i in 0..self.atoms.len() {
             let atom1 = &self.atoms[i];
             
             for j in (i+1)..self.atoms.len() {
+                if is_covered(i) && is_covered(j) {
+                    continue;
+                }
                 let atom2 = &self.atoms[j];
                 
                 let dx = atom1.position[0] - atom2.position[0];
@@ -196,14 +579,55 @@ impl Molecule {
                 let distance_squared = dx*dx + dy*dy + dz*dz;
                 
                 // Rough bond distance threshold (could be improved with element-specific logic)
-                if distance_squared < 4.0 {
-                    bonds_to_add.push((atom1.id, atom2.id));
+                let key = (AtomIndex(i), AtomIndex(j));
+                if distance_squared < 4.0 && !existing.contains(&key) {
+                    bonds_to_add.push(key);
                 }
             }
         }
         
-        for (atom1_id, atom2_id) in bonds_to_add {
-            self.add_bond(atom1_id, atom2_id, BondOrder::Single);
+        for (atom1, atom2) in bonds_to_add {
+            self.add_bond(atom1, atom2, BondOrder::Single);
+        }
+    }
+}
+
+macro_rules! index_molecule {
+    ($index:ident, $output:ident, $field:ident) => {
+        impl Index<$index> for Molecule {
+            type Output = $output;
+
+            fn index(&self, index: $index) -> &$output {
+                &self.$field[index.0]
+            }
+        }
+
+        impl IndexMut<$index> for Molecule {
+            fn index_mut(&mut self, index: $index) -> &mut $output {
+                &mut self.$field[index.0]
+            }
         }
+    };
+}
+
+index_molecule!(AtomIndex, Atom, atoms);
+index_molecule!(BondIndex, Bond, bonds);
+index_molecule!(ResidueIndex, Residue, residues);
+index_molecule!(ChainIndex, Chain, chains);
+index_molecule!(ModelIndex, Model, models);
+
+// Elements are stored by symbol, which stays stable if variants are added
+#[cfg(feature = "serde")]
+impl serde::Serialize for Element {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.symbol())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Element {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let symbol = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(Element::from_symbol(&symbol))
+    }
+}
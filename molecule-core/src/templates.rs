@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::classify::ResidueKind;
+use crate::structure::BondOrder::{Aromatic as A, Double as D, Single as S};
+use crate::geometry;
+use crate::grid::CellGrid;
+use crate::structure::{AtomIndex, BondOrder, Element, ModelIndex, Molecule, ResidueIndex};
+
+/// A bond between two named atoms of a residue template.
+pub type TemplateBond = (&'static str, &'static str, BondOrder);
+
+/// Intra-residue bonds of a standard polymer residue, keyed by atom name.
+///
+/// Templates cover heavy atoms only; hydrogens are named too inconsistently
+/// across force fields and are bonded to their nearest heavy atom instead.
+/// Aromatic rings use `BondOrder::Aromatic`.
+#[derive(Debug, Clone, Copy)]
+pub struct ResidueTemplate {
+    pub name: &'static str,
+    pub kind: ResidueKind,
+    backbone: &'static [TemplateBond],
+    side_chain: &'static [TemplateBond],
+}
+
+impl ResidueTemplate {
+    pub fn bonds(&self) -> impl Iterator<Item = &'static TemplateBond> {
+        self.backbone.iter().chain(self.side_chain)
+    }
+}
+
+// Longest bond allowed for inter-residue links and hydrogens, in Å
+const LINK_CUTOFF: f32 = 2.0;
+const DISULFIDE_CUTOFF: f32 = 2.3;
+const HYDROGEN_CUTOFF: f32 = 1.4;
+
+const PEPTIDE: &[TemplateBond] = &[("N", "CA", S), ("CA", "C", S), ("C", "O", D), ("C", "OXT", S)];
+
+const ALA: &[TemplateBond] = &[("CA", "CB", S)];
+const ARG: &[TemplateBond] = &[
+    ("CA", "CB", S), ("CB", "CG", S), ("CG", "CD", S), ("CD", "NE", S), ("NE", "CZ", S), ("CZ", "NH1", S),
+    ("CZ", "NH2", D),
+];
+const ASN: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "OD1", D), ("CG", "ND2", S)];
+const ASP: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "OD1", D), ("CG", "OD2", S)];
+const CYS: &[TemplateBond] = &[("CA", "CB", S), ("CB", "SG", S)];
+const GLN: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "CD", S), ("CD", "OE1", D), ("CD", "NE2", S)];
+const GLU: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "CD", S), ("CD", "OE1", D), ("CD", "OE2", S)];
+const GLY: &[TemplateBond] = &[];
+const HIS: &[TemplateBond] = &[
+    ("CA", "CB", S), ("CB", "CG", S), ("CG", "ND1", A), ("ND1", "CE1", A), ("CE1", "NE2", A), ("NE2", "CD2", A),
+    ("CD2", "CG", A),
+];
+// CHARMM names the delta carbon CD
+const ILE: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG1", S), ("CG1", "CD1", S), ("CG1", "CD", S), ("CB", "CG2", S)];
+const LEU: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "CD1", S), ("CG", "CD2", S)];
+const LYS: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "CD", S), ("CD", "CE", S), ("CE", "NZ", S)];
+const MET: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "SD", S), ("SD", "CE", S)];
+const MSE: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "SE", S), ("SE", "CE", S)];
+const PHE: &[TemplateBond] = &[
+    ("CA", "CB", S), ("CB", "CG", S), ("CG", "CD1", A), ("CD1", "CE1", A), ("CE1", "CZ", A), ("CZ", "CE2", A),
+    ("CE2", "CD2", A), ("CD2", "CG", A),
+];
+const PRO: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG", S), ("CG", "CD", S), ("CD", "N", S)];
+const SER: &[TemplateBond] = &[("CA", "CB", S), ("CB", "OG", S)];
+const THR: &[TemplateBond] = &[("CA", "CB", S), ("CB", "OG1", S), ("CB", "CG2", S)];
+const TRP: &[TemplateBond] = &[
+    ("CA", "CB", S), ("CB", "CG", S), ("CG", "CD1", A), ("CD1", "NE1", A), ("NE1", "CE2", A), ("CE2", "CD2", A),
+    ("CD2", "CG", A), ("CE2", "CZ2", A), ("CZ2", "CH2", A), ("CH2", "CZ3", A), ("CZ3", "CE3", A), ("CE3", "CD2", A),
+];
+const TYR: &[TemplateBond] = &[
+    ("CA", "CB", S), ("CB", "CG", S), ("CG", "CD1", A), ("CD1", "CE1", A), ("CE1", "CZ", A), ("CZ", "CE2", A),
+    ("CE2", "CD2", A), ("CD2", "CG", A), ("CZ", "OH", S),
+];
+const VAL: &[TemplateBond] = &[("CA", "CB", S), ("CB", "CG1", S), ("CB", "CG2", S)];
+
+// Older files name the phosphate oxygens O1P/O2P/O3P
+const DEOXYRIBOSE: &[TemplateBond] = &[
+    ("OP3", "P", S), ("P", "OP1", D), ("P", "OP2", S), ("O3P", "P", S), ("P", "O1P", D), ("P", "O2P", S),
+    ("P", "O5'", S), ("O5'", "C5'", S), ("C5'", "C4'", S), ("C4'", "O4'", S), ("C4'", "C3'", S), ("C3'", "O3'", S),
+    ("C3'", "C2'", S), ("C2'", "C1'", S), ("C1'", "O4'", S),
+];
+const RIBOSE: &[TemplateBond] = &[
+    ("OP3", "P", S), ("P", "OP1", D), ("P", "OP2", S), ("O3P", "P", S), ("P", "O1P", D), ("P", "O2P", S),
+    ("P", "O5'", S), ("O5'", "C5'", S), ("C5'", "C4'", S), ("C4'", "O4'", S), ("C4'", "C3'", S), ("C3'", "O3'", S),
+    ("C3'", "C2'", S), ("C2'", "C1'", S), ("C1'", "O4'", S), ("C2'", "O2'", S),
+];
+
+const ADENINE: &[TemplateBond] = &[
+    ("C1'", "N9", S), ("N9", "C8", A), ("C8", "N7", A), ("N7", "C5", A), ("C5", "C6", A), ("C6", "N6", S),
+    ("C6", "N1", A), ("N1", "C2", A), ("C2", "N3", A), ("N3", "C4", A), ("C4", "C5", A), ("N9", "C4", A),
+];
+const GUANINE: &[TemplateBond] = &[
+    ("C1'", "N9", S), ("N9", "C8", A), ("C8", "N7", A), ("N7", "C5", A), ("C5", "C6", A), ("C6", "O6", D),
+    ("C6", "N1", A), ("N1", "C2", A), ("C2", "N2", S), ("C2", "N3", A), ("N3", "C4", A), ("C4", "C5", A),
+    ("N9", "C4", A),
+];
+const CYTOSINE: &[TemplateBond] = &[
+    ("C1'", "N1", S), ("N1", "C2", A), ("C2", "O2", D), ("C2", "N3", A), ("N3", "C4", A), ("C4", "N4", S),
+    ("C4", "C5", A), ("C5", "C6", A), ("C6", "N1", A),
+];
+const URACIL: &[TemplateBond] = &[
+    ("C1'", "N1", S), ("N1", "C2", A), ("C2", "O2", D), ("C2", "N3", A), ("N3", "C4", A), ("C4", "O4", D),
+    ("C4", "C5", A), ("C5", "C6", A), ("C6", "N1", A),
+];
+const THYMINE: &[TemplateBond] = &[
+    ("C1'", "N1", S), ("N1", "C2", A), ("C2", "O2", D), ("C2", "N3", A), ("N3", "C4", A), ("C4", "O4", D),
+    ("C4", "C5", A), ("C5", "C6", A), ("C6", "N1", A), ("C5", "C7", S), ("C5", "C5M", S),
+];
+
+/// The template for a residue name, including protonation variants
+/// (HID/HIE/HIP, CYX, ASH, ...) and Amber terminal nucleotide names.
+pub fn residue_template(name: &str) -> Option<ResidueTemplate> {
+    use ResidueKind::{Dna, Protein, Rna};
+
+    let (name, kind, backbone, side_chain) = match name.trim() {
+        "ALA" => ("ALA", Protein, PEPTIDE, ALA),
+        "ARG" => ("ARG", Protein, PEPTIDE, ARG),
+        "ASN" => ("ASN", Protein, PEPTIDE, ASN),
+        "ASP" | "ASH" => ("ASP", Protein, PEPTIDE, ASP),
+        "CYS" | "CYX" | "CYM" => ("CYS", Protein, PEPTIDE, CYS),
+        "GLN" => ("GLN", Protein, PEPTIDE, GLN),
+        "GLU" | "GLH" => ("GLU", Protein, PEPTIDE, GLU),
+        "GLY" => ("GLY", Protein, PEPTIDE, GLY),
+        "HIS" | "HID" | "HIE" | "HIP" | "HSD" | "HSE" | "HSP" => ("HIS", Protein, PEPTIDE, HIS),
+        "ILE" => ("ILE", Protein, PEPTIDE, ILE),
+        "LEU" => ("LEU", Protein, PEPTIDE, LEU),
+        "LYS" | "LYN" => ("LYS", Protein, PEPTIDE, LYS),
+        "MET" => ("MET", Protein, PEPTIDE, MET),
+        "MSE" => ("MSE", Protein, PEPTIDE, MSE),
+        "PHE" => ("PHE", Protein, PEPTIDE, PHE),
+        "PRO" => ("PRO", Protein, PEPTIDE, PRO),
+        "SER" => ("SER", Protein, PEPTIDE, SER),
+        "THR" => ("THR", Protein, PEPTIDE, THR),
+        "TRP" => ("TRP", Protein, PEPTIDE, TRP),
+        "TYR" => ("TYR", Protein, PEPTIDE, TYR),
+        "VAL" => ("VAL", Protein, PEPTIDE, VAL),
+        "DA" | "DA5" | "DA3" => ("DA", Dna, DEOXYRIBOSE, ADENINE),
+        "DC" | "DC5" | "DC3" => ("DC", Dna, DEOXYRIBOSE, CYTOSINE),
+        "DG" | "DG5" | "DG3" => ("DG", Dna, DEOXYRIBOSE, GUANINE),
+        "DT" | "DT5" | "DT3" => ("DT", Dna, DEOXYRIBOSE, THYMINE),
+        "A" | "RA" | "A5" | "A3" => ("A", Rna, RIBOSE, ADENINE),
+        "C" | "RC" | "C5" | "C3" => ("C", Rna, RIBOSE, CYTOSINE),
+        "G" | "RG" | "G5" | "G3" => ("G", Rna, RIBOSE, GUANINE),
+        "U" | "RU" | "U5" | "U3" => ("U", Rna, RIBOSE, URACIL),
+        _ => return None,
+    };
+
+    Some(ResidueTemplate { name, kind, backbone, side_chain })
+}
+
+impl Molecule {
+    /// Bonds standard polymer residues from their templates, links
+    /// consecutive residues with peptide (C-N) or phosphodiester (O3'-P)
+    /// bonds, bridges cysteine SG pairs into disulfides, and falls back to
+    /// `calculate_bonds` distance bonding for everything else. Existing bonds
+    /// are kept.
+    pub fn calculate_template_bonds(&mut self) {
+        let mut existing: HashSet<(AtomIndex, AtomIndex)> =
+            self.bonds.iter().map(|bond| (bond.atom1.min(bond.atom2), bond.atom1.max(bond.atom2))).collect();
+        let mut covered = vec![false; self.atoms.len()];
+        let mut new_bonds = Vec::new();
+
+        for residue in 0..self.residues.len() {
+            let Some(template) = residue_template(&self.residues[residue].name) else {
+                continue;
+            };
+            let names = self.atom_names(ResidueIndex(residue));
+            for &(name1, name2, order) in template.bonds() {
+                for (atom1, atom2) in self.alt_loc_pairs(&names, name1, name2) {
+                    covered[atom1.0] = true;
+                    covered[atom2.0] = true;
+                    new_bonds.push((atom1, atom2, order));
+                }
+            }
+
+            // Hydrogens go to the closest heavy atom of the same residue
+            for &hydrogen in &self.residues[residue].atoms {
+                if self[hydrogen].element != Element::H {
+                    continue;
+                }
+                let nearest = self.residues[residue]
+                    .atoms
+                    .iter()
+                    .filter(|&&heavy| self[heavy].element != Element::H && alt_locs_compatible(self, hydrogen, heavy))
+                    .map(|&heavy| (heavy, distance(self, hydrogen, heavy)))
+                    .filter(|&(_, distance)| distance <= HYDROGEN_CUTOFF)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((heavy, _)) = nearest {
+                    covered[hydrogen.0] = true;
+                    new_bonds.push((heavy, hydrogen, BondOrder::Single));
+                }
+            }
+        }
+
+        for chain in 0..self.chains.len() {
+            for pair in self.chains[chain].residues.windows(2) {
+                let (Some(first), Some(second)) =
+                    (residue_template(&self[pair[0]].name), residue_template(&self[pair[1]].name))
+                else {
+                    continue;
+                };
+                let (name1, name2) = match (first.kind, second.kind) {
+                    (ResidueKind::Protein, ResidueKind::Protein) => ("C", "N"),
+                    (ResidueKind::Dna | ResidueKind::Rna, ResidueKind::Dna | ResidueKind::Rna) => ("O3'", "P"),
+                    _ => continue,
+                };
+                for &atom1 in self.atom_names(pair[0]).get(name1).into_iter().flatten() {
+                    for &atom2 in self.atom_names(pair[1]).get(name2).into_iter().flatten() {
+                        if alt_locs_compatible(self, atom1, atom2) && distance(self, atom1, atom2) <= LINK_CUTOFF {
+                            new_bonds.push((atom1, atom2, BondOrder::Single));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Disulfides may cross chains, but never models
+        let sulfurs: Vec<AtomIndex> = (0..self.atoms.len())
+            .map(AtomIndex)
+            .filter(|&atom| {
+                self[atom].name == "SG"
+                    && residue_template(&self[self[atom].residue].name).is_some_and(|template| template.name == "CYS")
+            })
+            .collect();
+        let grid = CellGrid::new(DISULFIDE_CUTOFF, sulfurs.iter().enumerate().map(|(i, &atom)| (i, self[atom].position)));
+        for (i, &atom1) in sulfurs.iter().enumerate() {
+            for j in grid.candidates(self[atom1].position).filter(|&j| j > i) {
+                let atom2 = sulfurs[j];
+                if self.model_of(atom1) == self.model_of(atom2)
+                    && alt_locs_compatible(self, atom1, atom2)
+                    && distance(self, atom1, atom2) <= DISULFIDE_CUTOFF
+                {
+                    new_bonds.push((atom1, atom2, BondOrder::Single));
+                }
+            }
+        }
+
+        for (atom1, atom2, order) in new_bonds {
+            if existing.insert((atom1.min(atom2), atom1.max(atom2))) {
+                self.add_bond(atom1, atom2, order);
+            }
+        }
+
+        self.calculate_bonds_between(&covered);
+    }
+
+    fn model_of(&self, atom: AtomIndex) -> Option<ModelIndex> {
+        self[self[self[atom].residue].chain].model
+    }
+
+    fn atom_names(&self, residue: ResidueIndex) -> HashMap<&str, Vec<AtomIndex>> {
+        let mut names: HashMap<&str, Vec<AtomIndex>> = HashMap::new();
+        for &atom in &self[residue].atoms {
+            names.entry(self[atom].name.as_str()).or_default().push(atom);
+        }
+        names
+    }
+
+    // Every combination of the two names' alternate locations that can coexist
+    fn alt_loc_pairs(&self, names: &HashMap<&str, Vec<AtomIndex>>, name1: &str, name2: &str) -> Vec<(AtomIndex, AtomIndex)> {
+        let (Some(atoms1), Some(atoms2)) = (names.get(name1), names.get(name2)) else {
+            return Vec::new();
+        };
+        atoms1
+            .iter()
+            .flat_map(|&atom1| atoms2.iter().map(move |&atom2| (atom1, atom2)))
+            .filter(|&(atom1, atom2)| alt_locs_compatible(self, atom1, atom2))
+            .collect()
+    }
+}
+
+fn alt_locs_compatible(molecule: &Molecule, atom1: AtomIndex, atom2: AtomIndex) -> bool {
+    let (alt1, alt2) = (molecule[atom1].alt_loc, molecule[atom2].alt_loc);
+    alt1 == ' ' || alt2 == ' ' || alt1 == alt2
+}
+
+fn distance(molecule: &Molecule, atom1: AtomIndex, atom2: AtomIndex) -> f32 {
+    geometry::distance(molecule[atom1].position, molecule[atom2].position)
+}
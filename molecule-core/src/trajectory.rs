@@ -0,0 +1,44 @@
+use std::io;
+
+use crate::error::invalid_input;
+use crate::structure::{Molecule, UnitCell};
+
+/// A single trajectory snapshot. Frames hold only coordinates, velocities and
+/// the box, and are applied onto a `Molecule` topology parsed from a separate file.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub index: usize,
+    pub step: Option<i64>,
+    pub time: Option<f32>,          // Simulation time in ps
+    pub positions: Vec<[f32; 3]>,   // Coordinates in Å, in topology atom order
+    pub velocities: Option<Vec<[f32; 3]>>, // Velocities in Å/ps, when stored
+    pub unit_cell: Option<UnitCell>,
+}
+
+impl Frame {
+    /// Overwrites the coordinates (and box and velocities, when present) of
+    /// `molecule` with this frame. Fails if the atom counts do not match.
+    pub fn apply_to(&self, molecule: &mut Molecule) -> io::Result<()> {
+        if self.positions.len() != molecule.atoms.len() {
+            return Err(invalid_input(&format!(
+                "frame has {} atoms but the topology has {}",
+                self.positions.len(),
+                molecule.atoms.len()
+            )));
+        }
+
+        for (atom, position) in molecule.atoms.iter_mut().zip(&self.positions) {
+            atom.position = *position;
+        }
+        if let Some(velocities) = &self.velocities {
+            for (atom, velocity) in molecule.atoms.iter_mut().zip(velocities) {
+                atom.velocity = Some(*velocity);
+            }
+        }
+        if self.unit_cell.is_some() {
+            molecule.unit_cell = self.unit_cell;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::ops::Deref;
+
+use crate::structure::{Atom, AtomIndex, Chain, ChainIndex, ModelIndex, Molecule, Residue, ResidueIndex};
+
+/// A model of a `Molecule`, or the atoms outside any MODEL record when
+/// `index()` is `None`. Files without MODEL records have one such model.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelView<'a> {
+    molecule: &'a Molecule,
+    index: Option<ModelIndex>,
+}
+
+/// A chain together with the molecule it belongs to. Dereferences to `Chain`.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainView<'a> {
+    molecule: &'a Molecule,
+    index: ChainIndex,
+}
+
+/// A residue together with the molecule it belongs to. Dereferences to `Residue`.
+#[derive(Debug, Clone, Copy)]
+pub struct ResidueView<'a> {
+    molecule: &'a Molecule,
+    index: ResidueIndex,
+}
+
+/// An atom together with the molecule it belongs to. Dereferences to `Atom`.
+#[derive(Debug, Clone, Copy)]
+pub struct AtomView<'a> {
+    molecule: &'a Molecule,
+    index: AtomIndex,
+}
+
+impl Molecule {
+    /// Models in file order. Atoms outside any MODEL record form a model
+    /// without an index, placed where its first atom appears; it is the only
+    /// one for single-model files.
+    pub fn models(&self) -> impl Iterator<Item = ModelView<'_>> {
+        let implicit = (self.chains.iter().any(|chain| chain.model.is_none()) || self.models.is_empty())
+            .then_some(ModelView { molecule: self, index: None });
+        let first_loose = self.atoms.iter().position(|atom| self[self[atom.residue].chain].model.is_none());
+        let split = match first_loose {
+            Some(first) => self.models.iter().position(|model| model.atoms.first().is_some_and(|atom| atom.0 > first)),
+            None => None,
+        }
+        .unwrap_or(self.models.len());
+
+        let indexed = |index: usize| ModelView { molecule: self, index: Some(ModelIndex(index)) };
+        (0..split).map(indexed).chain(implicit).chain((split..self.models.len()).map(indexed))
+    }
+
+    /// All chains of all models, in file order.
+    pub fn chains(&self) -> impl Iterator<Item = ChainView<'_>> {
+        (0..self.chains.len()).map(|index| self.chain(ChainIndex(index)))
+    }
+
+    /// All residues of all models, in file order.
+    pub fn residues(&self) -> impl Iterator<Item = ResidueView<'_>> {
+        (0..self.residues.len()).map(|index| self.residue(ResidueIndex(index)))
+    }
+
+    pub fn model(&self, index: ModelIndex) -> ModelView<'_> {
+        ModelView { molecule: self, index: Some(index) }
+    }
+
+    pub fn chain(&self, index: ChainIndex) -> ChainView<'_> {
+        ChainView { molecule: self, index }
+    }
+
+    pub fn residue(&self, index: ResidueIndex) -> ResidueView<'_> {
+        ResidueView { molecule: self, index }
+    }
+
+    pub fn atom(&self, index: AtomIndex) -> AtomView<'_> {
+        AtomView { molecule: self, index }
+    }
+}
+
+impl<'a> ModelView<'a> {
+    pub fn index(self) -> Option<ModelIndex> {
+        self.index
+    }
+
+    /// Serial number from the MODEL record.
+    pub fn id(self) -> Option<usize> {
+        self.index.map(|index| self.molecule[index].id)
+    }
+
+    pub fn chains(self) -> impl Iterator<Item = ChainView<'a>> {
+        let molecule = self.molecule;
+        molecule
+            .chains
+            .iter()
+            .enumerate()
+            .filter(move |(_, chain)| chain.model == self.index)
+            .map(move |(index, _)| molecule.chain(ChainIndex(index)))
+    }
+
+    /// Atoms in file order, which differs from walking `chains()` when a
+    /// chain's ligands are listed after other chains.
+    pub fn atoms(self) -> impl Iterator<Item = AtomView<'a>> {
+        let molecule = self.molecule;
+        let listed = self.index.map(|index| molecule[index].atoms.iter().copied());
+        let loose = self.index.is_none().then(|| {
+            (0..molecule.atoms.len())
+                .map(AtomIndex)
+                .filter(move |&atom| molecule[molecule[molecule[atom].residue].chain].model.is_none())
+        });
+        listed.into_iter().flatten().chain(loose.into_iter().flatten()).map(move |index| molecule.atom(index))
+    }
+}
+
+impl<'a> ChainView<'a> {
+    /// The underlying `Chain`, borrowed for as long as the molecule.
+    pub fn get(self) -> &'a Chain {
+        &self.molecule[self.index]
+    }
+
+    pub fn index(self) -> ChainIndex {
+        self.index
+    }
+
+    pub fn residues(self) -> impl Iterator<Item = ResidueView<'a>> {
+        let molecule = self.molecule;
+        molecule[self.index].residues.iter().map(move |&index| molecule.residue(index))
+    }
+
+    pub fn atoms(self) -> impl Iterator<Item = AtomView<'a>> {
+        self.residues().flat_map(ResidueView::atoms)
+    }
+
+    pub fn model(self) -> ModelView<'a> {
+        ModelView { molecule: self.molecule, index: self.molecule[self.index].model }
+    }
+}
+
+impl<'a> ResidueView<'a> {
+    /// The underlying `Residue`, borrowed for as long as the molecule.
+    pub fn get(self) -> &'a Residue {
+        &self.molecule[self.index]
+    }
+
+    pub fn index(self) -> ResidueIndex {
+        self.index
+    }
+
+    pub fn atoms(self) -> impl Iterator<Item = AtomView<'a>> {
+        let molecule = self.molecule;
+        molecule[self.index].atoms.iter().map(move |&index| molecule.atom(index))
+    }
+
+    pub fn chain(self) -> ChainView<'a> {
+        self.molecule.chain(self.molecule[self.index].chain)
+    }
+
+    pub fn model(self) -> ModelView<'a> {
+        self.chain().model()
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// The underlying `Atom`, borrowed for as long as the molecule.
+    pub fn get(self) -> &'a Atom {
+        &self.molecule[self.index]
+    }
+
+    pub fn index(self) -> AtomIndex {
+        self.index
+    }
+
+    pub fn residue(self) -> ResidueView<'a> {
+        self.molecule.residue(self.molecule[self.index].residue)
+    }
+
+    pub fn chain(self) -> ChainView<'a> {
+        self.residue().chain()
+    }
+
+    pub fn model(self) -> ModelView<'a> {
+        self.residue().model()
+    }
+}
+
+impl Deref for ChainView<'_> {
+    type Target = Chain;
+
+    fn deref(&self) -> &Chain {
+        self.get()
+    }
+}
+
+impl Deref for ResidueView<'_> {
+    type Target = Residue;
+
+    fn deref(&self) -> &Residue {
+        self.get()
+    }
+}
+
+impl Deref for AtomView<'_> {
+    type Target = Atom;
+
+    fn deref(&self) -> &Atom {
+        self.get()
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::invalid_input;
+use crate::structure::{Atom, AtomIndex, Element, Molecule};
+
+/// Writer for PDB files following the wwPDB v3.3 column layout.
+///
+/// Output includes HEADER/TITLE/CRYST1 from the molecule metadata, MODEL
+/// blocks, a TER record after the polymer atoms of each chain and CONECT
+/// records for every bond involving a HETATM. Atom serials are kept when
+/// they leave room for the TER records; otherwise atoms are renumbered so
+/// that serials run on through the TER records, as in files from the PDB.
+pub struct PdbWriter;
+
+impl Default for PdbWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdbWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        if let Some(atom) = molecule.atoms.iter().find(|atom| atom.residue_id > 9_999) {
+            return Err(invalid_input(&format!(
+                "residue {} does not fit PDB columns; write mmCIF instead",
+                atom.residue_id
+            )));
+        }
+
+        let blocks = model_blocks(molecule);
+        let serials = kept_serials(molecule, &blocks).unwrap_or_else(|| renumbered_serials(molecule, &blocks));
+        // The last record of each block may be a TER taking the next serial
+        let last = blocks
+            .iter()
+            .filter_map(|(_, atoms)| {
+                let ter = usize::from(ends_polymer_chain(molecule, atoms, atoms.len().checked_sub(1)?));
+                Some(serials[atoms.last()?.0] + ter)
+            })
+            .max();
+        if let Some(last) = last.filter(|&last| last > 99_999) {
+            return Err(invalid_input(&format!(
+                "{} records do not fit PDB serial columns; write mmCIF instead",
+                last
+            )));
+        }
+
+        self.write_header(molecule, &mut writer)?;
+
+        for (model_id, atoms) in &blocks {
+            if let Some(model_id) = model_id {
+                writeln!(writer, "MODEL     {:>4}", model_id)?;
+            }
+            self.write_atoms(molecule, atoms, &serials, &mut writer)?;
+            if model_id.is_some() {
+                writeln!(writer, "ENDMDL")?;
+            }
+        }
+
+        self.write_conect(molecule, &serials, &mut writer)?;
+        writeln!(writer, "END")
+    }
+
+    fn write_header<W: Write>(&self, molecule: &Molecule, writer: &mut W) -> io::Result<()> {
+        let metadata = &molecule.metadata;
+
+        if metadata.classification.is_some() || metadata.deposition_date.is_some() || metadata.id_code.is_some() {
+            let line = format!(
+                "HEADER    {:<40}{:<9}   {:<4}",
+                metadata.classification.as_deref().unwrap_or(""),
+                metadata.deposition_date.as_deref().unwrap_or(""),
+                metadata.id_code.as_deref().unwrap_or(""),
+            );
+            writeln!(writer, "{}", line.trim_end())?;
+        }
+
+        if let Some(title) = &metadata.title {
+            for (i, chunk) in wrap_words(title, 69).iter().enumerate() {
+                if i == 0 {
+                    writeln!(writer, "TITLE     {}", chunk)?;
+                } else {
+                    writeln!(writer, "TITLE   {:>2} {}", i + 1, chunk)?;
+                }
+            }
+        }
+
+        if let Some(cell) = &molecule.unit_cell {
+            let [a, b, c] = cell.lengths();
+            let [alpha, beta, gamma] = cell.angles();
+            writeln!(
+                writer,
+                "CRYST1{:>9.3}{:>9.3}{:>9.3}{:>7.2}{:>7.2}{:>7.2} {:<11}{:>4}",
+                a,
+                b,
+                c,
+                alpha,
+                beta,
+                gamma,
+                metadata.space_group.as_deref().unwrap_or("P 1"),
+                1
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_atoms<W: Write>(
+        &self,
+        molecule: &Molecule,
+        atoms: &[AtomIndex],
+        serials: &[usize],
+        writer: &mut W,
+    ) -> io::Result<()> {
+        for (i, &index) in atoms.iter().enumerate() {
+            let atom = &molecule[index];
+            writeln!(writer, "{}", format_atom_record(atom, serials[index.0]))?;
+
+            if ends_polymer_chain(molecule, atoms, i) {
+                writeln!(
+                    writer,
+                    "TER   {:>5}      {}{}{:>4}{}",
+                    serials[index.0] + 1,
+                    format_residue_name(&atom.residue_name),
+                    atom.chain_id,
+                    atom.residue_id,
+                    atom.ins_code
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_conect<W: Write>(&self, molecule: &Molecule, serials: &[usize], writer: &mut W) -> io::Result<()> {
+        // Keyed by serial, so bonds repeated in every model are written once
+        let mut partners: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for bond in &molecule.bonds {
+            if molecule[bond.atom1].is_hetatm || molecule[bond.atom2].is_hetatm {
+                let (serial1, serial2) = (serials[bond.atom1.0], serials[bond.atom2.0]);
+                partners.entry(serial1).or_default().insert(serial2);
+                partners.entry(serial2).or_default().insert(serial1);
+            }
+        }
+
+        for (atom_id, bonded) in partners {
+            let bonded: Vec<usize> = bonded.into_iter().collect();
+            for chunk in bonded.chunks(4) {
+                let mut line = format!("CONECT{:>5}", atom_id);
+                for bonded_id in chunk {
+                    line.push_str(&format!("{:>5}", bonded_id));
+                }
+                writeln!(writer, "{}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The atoms' own serials, if they increase through each model and leave a
+// free serial for every TER record. Loose atoms count as one more model, and
+// their serials must differ from all model serials so CONECT stays unambiguous.
+fn kept_serials(molecule: &Molecule, blocks: &[(Option<usize>, Vec<AtomIndex>)]) -> Option<Vec<usize>> {
+    let mut serials = vec![0; molecule.atoms.len()];
+    let mut model_serials = HashSet::new();
+    let mut loose_serials = Vec::new();
+    let mut loose_last = 0;
+    for (model_id, atoms) in blocks {
+        let mut last = if model_id.is_some() { 0 } else { loose_last };
+        for (i, &index) in atoms.iter().enumerate() {
+            let serial = molecule[index].id;
+            if serial <= last || serial > 99_999 {
+                return None;
+            }
+            serials[index.0] = serial;
+            last = serial;
+            if ends_polymer_chain(molecule, atoms, i) {
+                last += 1;
+            }
+            if model_id.is_some() {
+                model_serials.extend(serial..=last);
+            } else {
+                loose_serials.extend(serial..=last);
+            }
+        }
+        if model_id.is_none() {
+            loose_last = last;
+        }
+    }
+
+    let distinct = loose_serials.iter().all(|serial| !model_serials.contains(serial));
+    (distinct && loose_last <= 99_999).then_some(serials)
+}
+
+// Fresh serials running on through the TER records. Models restart at 1
+// unless loose atoms sit beside them; then one count runs through all blocks.
+fn renumbered_serials(molecule: &Molecule, blocks: &[(Option<usize>, Vec<AtomIndex>)]) -> Vec<usize> {
+    let mixed = blocks.iter().any(|(model_id, _)| model_id.is_some())
+        && blocks.iter().any(|(model_id, _)| model_id.is_none());
+    let mut serials = vec![0; molecule.atoms.len()];
+    let mut serial = 0;
+    for (_, atoms) in blocks {
+        if !mixed {
+            serial = 0;
+        }
+        for (i, &index) in atoms.iter().enumerate() {
+            serial += 1;
+            serials[index.0] = serial;
+            if ends_polymer_chain(molecule, atoms, i) {
+                serial += 1;
+            }
+        }
+    }
+    serials
+}
+
+// TER closes the polymer part of a chain, before any HETATMs
+fn ends_polymer_chain(molecule: &Molecule, atoms: &[AtomIndex], i: usize) -> bool {
+    let atom = &molecule[atoms[i]];
+    let next = atoms.get(i + 1).map(|&index| &molecule[index]);
+    !atom.is_hetatm && next.is_none_or(|next| next.chain_id != atom.chain_id || next.is_hetatm)
+}
+
+fn format_atom_record(atom: &Atom, serial: usize) -> String {
+    let charge = match atom.formal_charge {
+        0 => "  ".to_string(),
+        charge if charge > 0 => format!("{}+", charge),
+        charge => format!("{}-", -charge),
+    };
+
+    format!(
+        "{:<6}{:>5} {}{}{}{}{:>4}{}   {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}{}",
+        if atom.is_hetatm { "HETATM" } else { "ATOM" },
+        serial,
+        format_atom_name(&atom.name, atom.element),
+        atom.alt_loc,
+        format_residue_name(&atom.residue_name),
+        atom.chain_id,
+        atom.residue_id,
+        atom.ins_code,
+        atom.position[0],
+        atom.position[1],
+        atom.position[2],
+        atom.occupancy,
+        atom.b_factor,
+        if atom.element == Element::Unknown { String::new() } else { atom.element.symbol().to_uppercase() },
+        charge
+    )
+}
+
+// Residue names occupy columns 18-20; four-letter names spill into the
+// otherwise blank column 21.
+fn format_residue_name(name: &str) -> String {
+    if name.len() > 3 {
+        format!("{:<4}", name)
+    } else {
+        format!("{:>3} ", name)
+    }
+}
+
+// Names shorter than four characters start in column 14 unless the element
+// symbol has two letters or the name starts with a digit, as in the PDB format.
+pub(crate) fn format_atom_name(name: &str, element: Element) -> String {
+    let starts_with_digit = name.chars().next().is_some_and(|c| c.is_ascii_digit());
+    if name.len() >= 4 || element.symbol().len() == 2 || starts_with_digit {
+        format!("{:<4}", name)
+    } else {
+        format!(" {:<3}", name)
+    }
+}
+
+/// Splits the atoms into per-model blocks in file order. A model's block
+/// is written where its first atom appears and holds all of `model.atoms`,
+/// even when other atoms sit between them; runs of atoms outside of any MODEL
+/// become blocks without a model number.
+pub(crate) fn model_blocks(molecule: &Molecule) -> Vec<(Option<usize>, Vec<AtomIndex>)> {
+    let mut model_of = vec![None; molecule.atoms.len()];
+    for (model_index, model) in molecule.models.iter().enumerate() {
+        for &index in &model.atoms {
+            model_of[index.0] = Some(model_index);
+        }
+    }
+
+    let mut blocks: Vec<(Option<usize>, Vec<AtomIndex>)> = Vec::new();
+    let mut written = vec![false; molecule.models.len()];
+    let mut loose_block = false;
+    for (index, model_index) in model_of.into_iter().enumerate() {
+        match model_index {
+            Some(model_index) if !written[model_index] => {
+                written[model_index] = true;
+                let model = &molecule.models[model_index];
+                blocks.push((Some(model.id), model.atoms.clone()));
+                loose_block = false;
+            }
+            Some(_) => {}
+            None if loose_block => blocks.last_mut().unwrap().1.push(AtomIndex(index)),
+            None => {
+                blocks.push((None, vec![AtomIndex(index)]));
+                loose_block = true;
+            }
+        }
+    }
+    blocks
+}
+
+/// Like `model_blocks`, but keeps only the atoms in `selection`, when one is
+/// given.
+pub(crate) fn selected_blocks<'a>(
+    molecule: &'a Molecule,
+    selection: Option<&[AtomIndex]>,
+) -> io::Result<Vec<(Option<usize>, Vec<&'a Atom>)>> {
+    let mut selected = vec![selection.is_none(); molecule.atoms.len()];
+    for &index in selection.unwrap_or_default() {
+        *selected.get_mut(index.0).ok_or_else(|| {
+            invalid_input(&format!("selected atom index {} is out of range", index.0))
+        })? = true;
+    }
+
+    Ok(model_blocks(molecule)
+        .into_iter()
+        .map(|(model_id, atoms)| {
+            let kept = atoms.into_iter().filter(|index| selected[index.0]).map(|index| &molecule[index]).collect();
+            (model_id, kept)
+        })
+        .collect())
+}
+
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
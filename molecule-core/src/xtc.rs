@@ -0,0 +1,389 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::error::{invalid_data, invalid_input};
+use crate::structure::UnitCell;
+use crate::trajectory::Frame;
+
+const XTC_MAGIC: i32 = 1995;
+// GROMACS 2023 and later write this magic for frames whose compressed size
+// needs a 64-bit byte count.
+const XTC_MAGIC_LARGE: i32 = 2023;
+
+const NM_TO_ANGSTROM: f32 = 10.0;
+
+// Frame header: magic, natoms, step, time, 3x3 box, natoms again
+const HEADER_SIZE: u64 = 4 * (4 + 9 + 1);
+
+// Systems this small are stored as plain floats instead of being compressed
+const MAX_UNCOMPRESSED_ATOMS: usize = 9;
+
+const FIRST_INDEX: usize = 9;
+const MAGIC_INTS: [i32; 73] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64, 80, 101, 128, 161, 203, 256,
+    322, 406, 512, 645, 812, 1024, 1290, 1625, 2048, 2580, 3250, 4096, 5060, 6501, 8192, 10321,
+    13003, 16384, 20642, 26007, 32768, 41285, 52015, 65536, 82570, 104031, 131072, 165140, 208063,
+    262144, 330280, 416127, 524287, 660561, 832255, 1048576, 1321122, 1664510, 2097152, 2642245,
+    3329021, 4194304, 5284491, 6658042, 8388607, 10568983, 13316085, 16777216,
+];
+
+/// Reader for GROMACS XTC trajectories.
+///
+/// Frame offsets are indexed when the reader is created by hopping over the
+/// compressed payloads, so frames can then be read in any order.
+pub struct XtcReader<R> {
+    reader: R,
+    n_atoms: usize,
+    offsets: Vec<u64>,
+    file_size: u64,
+}
+
+impl XtcReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> XtcReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        let mut offsets = Vec::new();
+        let mut n_atoms = None;
+        let mut offset = 0;
+
+        while offset < file_size {
+            reader.seek(SeekFrom::Start(offset))?;
+            let header = read_header(&mut reader)?;
+            if *n_atoms.get_or_insert(header.n_atoms) != header.n_atoms {
+                return Err(invalid_data("atom count changes between frames"));
+            }
+
+            // Corrupt byte counts must not index frames beyond the data
+            let end = payload_size(&mut reader, &header)?
+                .checked_add(HEADER_SIZE)
+                .and_then(|size| offset.checked_add(size))
+                .filter(|&end| end <= file_size)
+                .ok_or_else(|| invalid_data("XTC frame runs past the end of the file"))?;
+            offsets.push(offset);
+            offset = end;
+        }
+
+        Ok(Self {
+            reader,
+            n_atoms: n_atoms.unwrap_or(0),
+            offsets,
+            file_size,
+        })
+    }
+
+    pub fn n_atoms(&self) -> usize {
+        self.n_atoms
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Byte offset of every frame in the file, in frame order.
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// Reads and decompresses the frame at `index` (0-based).
+    pub fn read_frame(&mut self, index: usize) -> io::Result<Frame> {
+        let offset = *self.offsets.get(index).ok_or_else(|| {
+            invalid_input(&format!("frame {} out of range ({} frames)", index, self.offsets.len()))
+        })?;
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let header = read_header(&mut self.reader)?;
+        let coordinates = if header.n_atoms <= MAX_UNCOMPRESSED_ATOMS {
+            (0..header.n_atoms * 3)
+                .map(|_| read_f32(&mut self.reader))
+                .collect::<io::Result<Vec<_>>>()?
+        } else {
+            decompress_coordinates(&mut self.reader, &header, self.file_size - offset - HEADER_SIZE)?
+        };
+
+        let positions = coordinates
+            .chunks(3)
+            .map(|c| [c[0] * NM_TO_ANGSTROM, c[1] * NM_TO_ANGSTROM, c[2] * NM_TO_ANGSTROM])
+            .collect();
+        let vectors = header.box_vectors.map(|v| v.map(|c| c * NM_TO_ANGSTROM));
+        let has_box = vectors.iter().flatten().any(|&c| c != 0.0);
+
+        Ok(Frame {
+            index,
+            step: Some(header.step as i64),
+            time: Some(header.time),
+            positions,
+            velocities: None,
+            unit_cell: has_box.then(|| UnitCell::new(vectors)),
+        })
+    }
+
+    /// Iterates over all frames in file order.
+    pub fn frames(&mut self) -> impl Iterator<Item = io::Result<Frame>> + '_ {
+        (0..self.offsets.len()).map(move |index| self.read_frame(index))
+    }
+}
+
+struct FrameHeader {
+    magic: i32,
+    n_atoms: usize,
+    step: i32,
+    time: f32,
+    box_vectors: [[f32; 3]; 3],
+}
+
+fn read_header<R: Read>(reader: &mut R) -> io::Result<FrameHeader> {
+    let magic = read_i32(reader)?;
+    if magic != XTC_MAGIC && magic != XTC_MAGIC_LARGE {
+        return Err(invalid_data(&format!("bad XTC magic number {}", magic)));
+    }
+
+    let n_atoms = read_i32(reader)?;
+    let step = read_i32(reader)?;
+    let time = read_f32(reader)?;
+
+    let mut box_vectors = [[0.0; 3]; 3];
+    for vector in &mut box_vectors {
+        for value in vector.iter_mut() {
+            *value = read_f32(reader)?;
+        }
+    }
+
+    if n_atoms < 0 || read_i32(reader)? != n_atoms {
+        return Err(invalid_data("inconsistent atom count in frame header"));
+    }
+
+    Ok(FrameHeader {
+        magic,
+        n_atoms: n_atoms as usize,
+        step,
+        time,
+        box_vectors,
+    })
+}
+
+// Size in bytes of the coordinate block following a header, leaving the
+// reader positioned somewhere inside it
+fn payload_size<R: Read + Seek>(reader: &mut R, header: &FrameHeader) -> io::Result<u64> {
+    if header.n_atoms <= MAX_UNCOMPRESSED_ATOMS {
+        return Ok(12 * header.n_atoms as u64);
+    }
+
+    // precision, minint[3], maxint[3], smallidx
+    reader.seek(SeekFrom::Current(4 * 8))?;
+    let (count_size, byte_count) = read_byte_count(reader, header.magic)?;
+
+    padded_size(byte_count)?
+        .checked_add(4 * 8 + count_size)
+        .ok_or_else(|| invalid_data("XTC compressed size overflows"))
+}
+
+// Compressed data is padded to whole 4-byte XDR words
+fn padded_size(byte_count: u64) -> io::Result<u64> {
+    byte_count.div_ceil(4).checked_mul(4).ok_or_else(|| invalid_data("XTC compressed size overflows"))
+}
+
+fn read_byte_count<R: Read>(reader: &mut R, magic: i32) -> io::Result<(u64, u64)> {
+    if magic == XTC_MAGIC_LARGE {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok((8, u64::from_be_bytes(bytes)))
+    } else {
+        let count = read_i32(reader)?;
+        if count < 0 {
+            return Err(invalid_data("negative compressed size"));
+        }
+        Ok((4, count as u64))
+    }
+}
+
+// Port of the xdrfile coordinate decompression used by GROMACS. `remaining`
+// is how many bytes of the file follow the frame header.
+fn decompress_coordinates<R: Read>(reader: &mut R, header: &FrameHeader, remaining: u64) -> io::Result<Vec<f32>> {
+    let precision = read_f32(reader)?;
+    if precision <= 0.0 {
+        return Err(invalid_data("invalid XTC precision"));
+    }
+    let mut min_int = [0i32; 3];
+    let mut max_int = [0i32; 3];
+    for value in &mut min_int {
+        *value = read_i32(reader)?;
+    }
+    for value in &mut max_int {
+        *value = read_i32(reader)?;
+    }
+    let mut small_index = read_i32(reader)?;
+    if small_index < 0 || small_index as usize >= MAGIC_INTS.len() {
+        return Err(invalid_data("invalid XTC small index"));
+    }
+
+    let (_, byte_count) = read_byte_count(reader, header.magic)?;
+    let size = padded_size(byte_count)?;
+    if size > remaining {
+        return Err(invalid_data("XTC compressed size exceeds the file"));
+    }
+    let mut data = vec![0u8; size as usize];
+    reader.read_exact(&mut data)?;
+
+    let sizes: [u32; 3] = std::array::from_fn(|i| (max_int[i] as i64 - min_int[i] as i64 + 1) as u32);
+
+    // Very large ranges are packed per component instead of as a combined integer
+    let large_sizes = (sizes[0] | sizes[1] | sizes[2]) > 0xff_ffff;
+    let bit_sizes = sizes.map(bit_length);
+    let combined_bits = if large_sizes { 0 } else { bit_length_u128(sizes.iter().map(|&s| s as u128).product()) };
+
+    let magic_int = |index: i32| MAGIC_INTS[index.clamp(0, MAGIC_INTS.len() as i32 - 1) as usize];
+    let mut smaller = magic_int((FIRST_INDEX as i32).max(small_index - 1)) / 2;
+    let mut small_num = magic_int(small_index) / 2;
+    let mut small_sizes = [magic_int(small_index) as u32; 3];
+
+    let inverse_precision = 1.0 / precision;
+    let mut bits = BitReader::new(&data);
+    let mut coordinates = Vec::with_capacity(header.n_atoms * 3);
+    let mut run = 0;
+    let mut atom = 0;
+
+    while atom < header.n_atoms {
+        let mut this_coord = if large_sizes {
+            [
+                bits.read(bit_sizes[0])? as i32,
+                bits.read(bit_sizes[1])? as i32,
+                bits.read(bit_sizes[2])? as i32,
+            ]
+        } else {
+            bits.read_ints(combined_bits, sizes)?
+        };
+        atom += 1;
+        for (value, min) in this_coord.iter_mut().zip(min_int) {
+            *value += min;
+        }
+        let mut prev_coord = this_coord;
+
+        // A set flag announces a run of small displacements, and whether the
+        // small-integer range grows or shrinks afterwards. Otherwise the
+        // previous run length is reused.
+        let mut is_smaller = 0;
+        if bits.read(1)? == 1 {
+            run = bits.read(5)? as i32;
+            is_smaller = run % 3;
+            run -= is_smaller;
+            is_smaller -= 1;
+        }
+
+        if run > 0 {
+            for k in (0..run).step_by(3) {
+                if atom >= header.n_atoms {
+                    return Err(invalid_data("compressed run extends past the last atom"));
+                }
+                let mut small = bits.read_ints(small_index as u32, small_sizes)?;
+                atom += 1;
+                for (value, prev) in small.iter_mut().zip(prev_coord) {
+                    *value += prev - small_num;
+                }
+                if k == 0 {
+                    // The first two atoms of a run are swapped so water
+                    // molecules compress better
+                    std::mem::swap(&mut small, &mut prev_coord);
+                    coordinates.extend(prev_coord.map(|c| c as f32 * inverse_precision));
+                } else {
+                    prev_coord = small;
+                }
+                coordinates.extend(small.map(|c| c as f32 * inverse_precision));
+            }
+        } else {
+            coordinates.extend(this_coord.map(|c| c as f32 * inverse_precision));
+        }
+
+        small_index += is_smaller;
+        if small_index < 0 || small_index as usize >= MAGIC_INTS.len() {
+            return Err(invalid_data("invalid XTC small index"));
+        }
+        if is_smaller < 0 {
+            small_num = smaller;
+            smaller = if small_index as usize > FIRST_INDEX { magic_int(small_index - 1) / 2 } else { 0 };
+        } else if is_smaller > 0 {
+            smaller = small_num;
+            small_num = magic_int(small_index) / 2;
+        }
+        small_sizes = [magic_int(small_index) as u32; 3];
+    }
+
+    Ok(coordinates)
+}
+
+// Most-significant-bit-first reader over the compressed payload
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read(&mut self, count: u32) -> io::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = *self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| invalid_data("compressed coordinates are truncated"))?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    // Three integers packed as one number in mixed radix `sizes`, stored
+    // least significant byte first
+    fn read_ints(&mut self, bit_count: u32, sizes: [u32; 3]) -> io::Result<[i32; 3]> {
+        let mut packed = 0u128;
+        let mut shift = 0;
+        let mut remaining = bit_count;
+        while remaining > 0 {
+            let chunk = remaining.min(8);
+            packed |= (self.read(chunk)? as u128) << shift;
+            shift += 8;
+            remaining -= chunk;
+        }
+
+        let mut values = [0i32; 3];
+        for i in (1..3).rev() {
+            let size = sizes[i].max(1) as u128;
+            values[i] = (packed % size) as i32;
+            packed /= size;
+        }
+        values[0] = packed as i32;
+
+        Ok(values)
+    }
+}
+
+fn bit_length(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
+
+fn bit_length_u128(value: u128) -> u32 {
+    128 - value.leading_zeros()
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_be_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_be_bytes(bytes))
+}
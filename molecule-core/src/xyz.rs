@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::structure::{AtomIndex, Element, Molecule};
+use crate::writer::selected_blocks;
+
+/// Writer for XYZ files, as read by most quantum chemistry codes.
+///
+/// Each model becomes one frame with its atom count, a comment line holding
+/// the molecule title, and one `symbol x y z` line per atom in Å.
+pub struct XyzWriter {
+    selection: Option<Vec<AtomIndex>>,
+}
+
+impl Default for XyzWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XyzWriter {
+    pub fn new() -> Self {
+        Self { selection: None }
+    }
+
+    /// Restricts output to these atoms, e.g. from `Molecule::select`.
+    pub fn with_selection(mut self, atoms: Vec<AtomIndex>) -> Self {
+        self.selection = Some(atoms);
+        self
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, molecule: &Molecule, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(molecule, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_string(&self, molecule: &Molecule) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.write(molecule, &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write<W: Write>(&self, molecule: &Molecule, mut writer: W) -> io::Result<()> {
+        let title = molecule.metadata.title.as_deref().or(molecule.metadata.id_code.as_deref()).unwrap_or("");
+
+        for (_, atoms) in selected_blocks(molecule, self.selection.as_deref())? {
+            writeln!(writer, "{}", atoms.len())?;
+            writeln!(writer, "{}", title.lines().next().unwrap_or(""))?;
+            for atom in atoms {
+                let symbol = if atom.element == Element::Unknown { &atom.name } else { atom.element.symbol() };
+                writeln!(
+                    writer,
+                    "{:<2} {:>14.6} {:>14.6} {:>14.6}",
+                    symbol, atom.position[0], atom.position[1], atom.position[2]
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
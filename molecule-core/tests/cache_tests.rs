@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::fs;
+
+use molecule_core::{
+    content_hash, AtomIndex, BinaryParser, BinaryWriter, ModelIndex, Molecule, PdbParser, PrmtopParser, StructureCache,
+};
+
+const PDB: &str = "\
+HEADER    OXIDOREDUCTASE                          12-MAR-99   1ABC
+TITLE     CACHE TEST
+CRYST1   50.000   60.000   70.000  90.00  95.00  90.00 P 1 21 1      2
+MODEL        1
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+HETATM    3 FE   HEM A 101      20.000  10.000   0.000  0.50 20.00          FE2+
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1      11.204   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.739   6.071  -5.147  1.00  0.00           C
+HETATM    3 FE   HEM A 101      20.100  10.000   0.000  0.50 20.00          FE2+
+ENDMDL
+CONECT    3    2
+END
+";
+
+fn bond_set(molecule: &Molecule) -> HashSet<(usize, usize)> {
+    molecule.bonds.iter().map(|bond| (bond.atom1.0, bond.atom2.0)).collect()
+}
+
+fn assert_same_hierarchy(original: &Molecule, copy: &Molecule) {
+    assert_eq!(copy.models.len(), original.models.len());
+    for (a, b) in original.models.iter().zip(&copy.models) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.atoms, b.atoms);
+    }
+    assert_eq!(copy.chains.len(), original.chains.len());
+    for (a, b) in original.chains.iter().zip(&copy.chains) {
+        assert_eq!((a.id, a.model), (b.id, b.model));
+        assert_eq!(a.residues, b.residues);
+    }
+    assert_eq!(copy.residues.len(), original.residues.len());
+    for (a, b) in original.residues.iter().zip(&copy.residues) {
+        assert_eq!((a.id, &a.name, a.ins_code, a.chain), (b.id, &b.name, b.ins_code, b.chain));
+        assert_eq!(a.atoms, b.atoms);
+    }
+    for (a, b) in original.atoms.iter().zip(&copy.atoms) {
+        assert_eq!(a.residue, b.residue);
+    }
+}
+
+#[test]
+fn test_binary_round_trip() {
+    let molecule = PdbParser::new().parse_string(PDB).unwrap();
+    let bytes = BinaryWriter::new().write_bytes(&molecule).unwrap();
+    assert_eq!(&bytes[..4], b"MOLB");
+    assert_eq!(bytes.len() % 4, 0);
+
+    let copy = BinaryParser::new().parse_bytes(&bytes).unwrap();
+    assert_eq!(copy.atoms.len(), molecule.atoms.len());
+    for (a, b) in molecule.atoms.iter().zip(&copy.atoms) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.element, b.element);
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.residue_name, b.residue_name);
+        assert_eq!(a.chain_id, b.chain_id);
+        assert_eq!(a.occupancy, b.occupancy);
+        assert_eq!(a.is_hetatm, b.is_hetatm);
+        assert_eq!(a.formal_charge, b.formal_charge);
+    }
+    assert_eq!(bond_set(&copy), bond_set(&molecule));
+    assert_same_hierarchy(&molecule, &copy);
+    assert_eq!(copy.unit_cell, molecule.unit_cell);
+    assert_eq!(copy.metadata, molecule.metadata);
+}
+
+#[test]
+fn test_binary_keeps_edited_hierarchy() {
+    // An atom added to the first model after the second leaves that model's
+    // atoms in two runs, which replaying the atoms in file order would not reproduce
+    let mut molecule = PdbParser::new().parse_string(PDB).unwrap();
+    let atom = molecule.atoms[0].clone();
+    molecule.current_model = Some(ModelIndex(0));
+    molecule.add_atom(atom);
+
+    let bytes = BinaryWriter::new().write_bytes(&molecule).unwrap();
+    let copy = BinaryParser::new().parse_bytes(&bytes).unwrap();
+    assert_eq!(copy.models[0].atoms, [0, 1, 2, 6].map(AtomIndex));
+    assert_same_hierarchy(&molecule, &copy);
+}
+
+#[test]
+fn test_binary_keeps_force_field_fields() {
+    let prmtop = "\
+%VERSION  VERSION_STAMP = V0001.000
+%FLAG POINTERS
+%FORMAT(10I8)
+       2       1       1       1       0       0       0       0       0       0
+       0       1       0       0       0       0       0       0       0       0
+%FLAG ATOM_NAME
+%FORMAT(20a4)
+O   H1  
+%FLAG CHARGE
+%FORMAT(5E16.8)
+ -1.51973982E+01  7.59869910E+00
+%FLAG MASS
+%FORMAT(5E16.8)
+  1.60000000E+01  1.00800000E+00
+%FLAG AMBER_ATOM_TYPE
+%FORMAT(20a4)
+OW  HW  
+%FLAG RESIDUE_LABEL
+%FORMAT(20a4)
+WAT 
+%FLAG RESIDUE_POINTER
+%FORMAT(10I8)
+       1
+%FLAG BONDS_INC_HYDROGEN
+%FORMAT(10I8)
+       0       3       1
+%FLAG BONDS_WITHOUT_HYDROGEN
+%FORMAT(10I8)
+
+";
+    let molecule = PrmtopParser::new().parse_string(prmtop).unwrap();
+    let bytes = BinaryWriter::new().write_bytes(&molecule).unwrap();
+    let copy = BinaryParser::new().parse_bytes(&bytes).unwrap();
+
+    assert_eq!(copy.atoms[0].charge, molecule.atoms[0].charge);
+    assert_eq!(copy.atoms[1].mass, Some(1.008));
+    assert_eq!(copy.atoms[1].atom_type.as_deref(), Some("HW"));
+    assert_eq!(copy.atoms[0].velocity, None);
+    assert_eq!(copy.atoms[0].radius, None);
+    assert_eq!(copy.bonds.len(), 1);
+}
+
+#[test]
+fn test_binary_rejects_bad_input() {
+    let molecule = PdbParser::new().parse_string(PDB).unwrap();
+    let mut bytes = BinaryWriter::new().write_bytes(&molecule).unwrap();
+
+    assert!(BinaryParser::new().parse_bytes(b"ATOM  ").is_err());
+    assert!(BinaryParser::new().parse_bytes(&bytes[..bytes.len() / 2]).is_err());
+
+    // A different format version is refused rather than misread
+    bytes[4] = 99;
+    assert!(BinaryParser::new().parse_bytes(&bytes).is_err());
+}
+
+#[test]
+fn test_structure_cache() {
+    let directory = std::env::temp_dir().join(format!("molecule-cache-test-{}", std::process::id()));
+    let cache = StructureCache::new(&directory);
+    let source = PDB.as_bytes();
+
+    assert_ne!(content_hash(source), content_hash(b"something else"));
+    assert!(cache.load("pdb", source).is_none());
+
+    let parsed = cache
+        .load_or_parse("pdb", source, |bytes| PdbParser::new().parse_string(std::str::from_utf8(bytes).unwrap()))
+        .unwrap();
+    assert!(cache.path_for("pdb", source).exists());
+
+    // The second load comes from the snapshot, not the parser
+    let cached = cache.load_or_parse("pdb", source, |_| panic!("should not re-parse")).unwrap();
+    assert_eq!(cached.atoms.len(), parsed.atoms.len());
+    assert_eq!(bond_set(&cached), bond_set(&parsed));
+
+    // Another parser or set of options gets its own snapshot of the same source
+    assert_ne!(cache.path_for("pdb", source), cache.path_for("pdb+templates", source));
+    assert!(cache.load("pdb+templates", source).is_none());
+    let mut reparsed = false;
+    cache
+        .load_or_parse("pdb+templates", source, |bytes| {
+            reparsed = true;
+            PdbParser::new().parse_string(std::str::from_utf8(bytes).unwrap())
+        })
+        .unwrap();
+    assert!(reparsed);
+    assert_ne!(cache.path_for("ab", b"c"), cache.path_for("a", b"bc"));
+
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,156 @@
+use molecule_core::{AtomIndex, BondOrder, CcdParser, Element, PdbParser};
+
+const COMPONENTS_CIF: &str = "\
+data_ACT
+#
+_chem_comp.id                                    ACT
+_chem_comp.name                                  'ACETATE ION'
+_chem_comp.type                                  NON-POLYMER
+#
+loop_
+_chem_comp_atom.comp_id
+_chem_comp_atom.atom_id
+_chem_comp_atom.type_symbol
+_chem_comp_atom.charge
+_chem_comp_atom.pdbx_leaving_atom_flag
+ACT C   C 0  N
+ACT O   O 0  N
+ACT OXT O -1 N
+ACT CH3 C 0  N
+ACT H1  H 0  N
+#
+loop_
+_chem_comp_bond.comp_id
+_chem_comp_bond.atom_id_1
+_chem_comp_bond.atom_id_2
+_chem_comp_bond.value_order
+_chem_comp_bond.pdbx_aromatic_flag
+ACT C   O   DOUB N
+ACT C   OXT SING N
+ACT C   CH3 SING N
+ACT CH3 H1  SING N
+#
+data_OXY
+_chem_comp.id OXY
+_chem_comp.name
+;OXYGEN MOLECULE
+;
+loop_
+_chem_comp_atom.comp_id
+_chem_comp_atom.atom_id
+_chem_comp_atom.type_symbol
+_chem_comp_atom.charge
+OXY O1 O 0
+OXY O2 O 0
+_chem_comp_bond.comp_id     OXY
+_chem_comp_bond.atom_id_1   O1
+_chem_comp_bond.atom_id_2   O2
+_chem_comp_bond.value_order DOUB
+";
+
+const LIGANDS_PDB: &str = "\
+HETATM    1  C   ACT A 301       0.000   0.000   0.000  1.00 20.00           C
+HETATM    2  O   ACT A 301       1.250   0.000   0.000  1.00 20.00           O
+HETATM    3  OXT ACT A 301       0.620   1.080   0.000  1.00 20.00           O
+HETATM    4  CH3 ACT A 301      -0.750  -1.300   0.000  1.00 20.00           C
+HETATM    5  XX  ACT A 301       5.000   5.000   5.000  1.00 20.00           C
+HETATM    6  O1  OXY A 302      10.000   0.000   0.000  1.00 20.00           O
+HETATM    7  O2  OXY A 302      11.210   0.000   0.000  1.00 20.00           O
+END
+";
+
+#[test]
+fn test_parse_components() {
+    let dictionary = CcdParser::new().parse_string(COMPONENTS_CIF).unwrap();
+    assert_eq!(dictionary.len(), 2);
+
+    let acetate = dictionary.get("ACT").unwrap();
+    assert_eq!(acetate.name, "ACETATE ION");
+    assert_eq!(acetate.atoms.len(), 5);
+    assert_eq!(acetate.atoms[2].formal_charge, -1);
+    assert_eq!(acetate.atoms[3].element, Element::C);
+    assert_eq!(acetate.bonds[0].order, BondOrder::Double);
+
+    // A single bond written as plain items rather than a loop
+    let oxygen = dictionary.get("OXY").unwrap();
+    assert_eq!(oxygen.name, "OXYGEN MOLECULE");
+    assert_eq!(oxygen.bonds.len(), 1);
+    assert_eq!(oxygen.bonds[0].order, BondOrder::Double);
+
+    assert!(CcdParser::new().parse_string("data_BAD\nloop_\n_chem_comp_atom.atom_id\n_chem_comp_atom.type_symbol\nC\n").is_err());
+}
+
+#[test]
+fn test_apply_components_assigns_bonds_charges_and_reports() {
+    let dictionary = CcdParser::new().parse_string(COMPONENTS_CIF).unwrap();
+    let mut molecule = PdbParser::new().parse_string(LIGANDS_PDB).unwrap();
+
+    let reports = molecule.apply_components(&dictionary);
+    assert_eq!(reports.len(), 2);
+    // The unresolved hydrogen is reported apart from missing heavy atoms
+    assert!(reports[0].missing.is_empty());
+    assert_eq!(reports[0].missing_hydrogens, vec!["H1"]);
+    assert_eq!(reports[0].unknown, vec![AtomIndex(4)]);
+    assert!(reports[1].missing.is_empty());
+
+    assert_eq!(molecule[AtomIndex(2)].formal_charge, -1);
+    let orders: Vec<(usize, usize, BondOrder)> =
+        molecule.bonds.iter().map(|bond| (bond.atom1.0, bond.atom2.0, bond.order)).collect();
+    assert!(orders.contains(&(0, 1, BondOrder::Double)));
+    assert!(orders.contains(&(0, 2, BondOrder::Single)));
+    assert!(orders.contains(&(5, 6, BondOrder::Double)));
+    // The O-OXT pair is closer than the distance cutoff but not bonded
+    assert!(!orders.iter().any(|&(a, b, _)| (a, b) == (1, 2)));
+    assert_eq!(molecule.bonds.len(), 4);
+
+    // Dropping a heavy atom shows up in `missing`
+    let without_oxt: String = LIGANDS_PDB.lines().filter(|line| !line.contains("OXT")).map(|line| format!("{}\n", line)).collect();
+    let mut molecule = PdbParser::new().parse_string(&without_oxt).unwrap();
+    let reports = molecule.apply_components(&dictionary);
+    assert_eq!(reports[0].missing, vec!["OXT"]);
+}
+
+#[test]
+fn test_parse_components_from_reader() {
+    // Blocks are parsed as they are read; an error in a later block still fails
+    let reader = std::io::BufReader::with_capacity(16, COMPONENTS_CIF.as_bytes());
+    assert_eq!(CcdParser::new().parse_reader(reader).unwrap().len(), 2);
+
+    let broken = format!("{}data_BAD\n_chem_comp.id\n", COMPONENTS_CIF);
+    assert!(CcdParser::new().parse_reader(broken.as_bytes()).is_err());
+    assert!(CcdParser::new().parse_string("_chem_comp.id ACT\n").is_err());
+}
+
+#[test]
+fn test_parse_directory() {
+    let directory = std::env::temp_dir().join(format!("molecule-ccd-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let (acetate, oxygen) = COMPONENTS_CIF.split_at(COMPONENTS_CIF.find("data_OXY").unwrap());
+    std::fs::write(directory.join("ACT.cif"), acetate).unwrap();
+    std::fs::write(directory.join("OXY.cif"), oxygen).unwrap();
+    std::fs::write(directory.join("README"), "not a component").unwrap();
+
+    let dictionary = CcdParser::new().parse_directory(&directory).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(dictionary.len(), 2);
+    assert!(dictionary.get("OXY").is_some());
+}
+
+#[test]
+fn test_apply_components_to_many_residues() {
+    let dictionary = CcdParser::new().parse_string(COMPONENTS_CIF).unwrap();
+    let mut pdb = String::new();
+    for i in 0..2_000 {
+        let x = (i % 40) as f32 * 5.0;
+        let y = (i / 40) as f32 * 5.0;
+        pdb.push_str(&format!("HETATM{:>5}  O1  OXY A{:>4}    {:8.3}{:8.3}   0.000  1.00 20.00           O\n", 2 * i + 1, i + 1, x, y));
+        pdb.push_str(&format!("HETATM{:>5}  O2  OXY A{:>4}    {:8.3}{:8.3}   0.000  1.00 20.00           O\n", 2 * i + 2, i + 1, x + 1.21, y));
+    }
+    // A link between two components is not theirs to replace
+    pdb.push_str("CONECT    2    3\n");
+    let mut molecule = PdbParser::new().parse_string(&pdb).unwrap();
+
+    assert_eq!(molecule.apply_components(&dictionary).len(), 2_000);
+    assert_eq!(molecule.bonds.len(), 2_001);
+    assert!(molecule.bonds.iter().all(|bond| bond.order == BondOrder::Double || (bond.atom1.0, bond.atom2.0) == (1, 2)));
+}
@@ -0,0 +1,50 @@
+use molecule_core::{classify_residue, ChainIndex, PdbParser, ResidueKind};
+
+const COMPLEX_PDB: &str = "\
+ATOM      1  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      2  CA  GLY A   2      15.175   5.850  -3.830  1.00  0.00           C
+HETATM    3 ZN    ZN A 101      20.000  10.000   0.000  1.00 20.00          ZN
+HETATM    4  O   HOH A 201      30.000  30.000  30.000  1.00 35.00           O
+ATOM      5  CA  ALA B   1      21.639   6.071  -5.147  1.00  0.00           C
+ATOM      6  CA  GLY B   2      25.175   5.850  -3.830  1.00  0.00           C
+ATOM      7  P    DA C   1      31.639   6.071  -5.147  1.00  0.00           P
+ATOM      8  P    DT C   2      35.175   5.850  -3.830  1.00  0.00           P
+HETATM    9  C1  NAG D   1      40.000  10.000   0.000  1.00 20.00           C
+HETATM   10  C1  HEM E   1      50.000  10.000   0.000  1.00 20.00           C
+END
+";
+
+#[test]
+fn test_residue_classification() {
+    assert_eq!(classify_residue("ALA"), ResidueKind::Protein);
+    assert_eq!(classify_residue("MSE"), ResidueKind::Protein);
+    assert_eq!(classify_residue("DG"), ResidueKind::Dna);
+    assert_eq!(classify_residue("U"), ResidueKind::Rna);
+    assert_eq!(classify_residue("WAT"), ResidueKind::Water);
+    assert_eq!(classify_residue("CA"), ResidueKind::Ion);
+    assert_eq!(classify_residue("NAG"), ResidueKind::Carbohydrate);
+    assert_eq!(classify_residue("HEM"), ResidueKind::Ligand);
+
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let kinds: Vec<ResidueKind> = molecule.residues().map(|residue| residue.kind()).collect();
+    assert_eq!(kinds[..4], [ResidueKind::Protein, ResidueKind::Protein, ResidueKind::Ion, ResidueKind::Water]);
+}
+
+#[test]
+fn test_chain_kinds_and_entities() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+
+    // The zinc and water under chain A do not change its kind or sequence
+    let kinds: Vec<ResidueKind> = molecule.chains().map(|chain| chain.kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![ResidueKind::Protein, ResidueKind::Protein, ResidueKind::Dna, ResidueKind::Carbohydrate, ResidueKind::Ligand]
+    );
+    assert_eq!(molecule.chain(ChainIndex(0)).sequence(), vec!["ALA", "GLY"]);
+
+    let entities = molecule.entities();
+    assert_eq!(entities.len(), 4);
+    assert_eq!(entities[0].chains, vec![ChainIndex(0), ChainIndex(1)]);
+    assert_eq!(entities[1].sequence, vec!["DA", "DT"]);
+    assert_eq!(entities[3].kind, ResidueKind::Ligand);
+}
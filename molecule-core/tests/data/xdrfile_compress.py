@@ -0,0 +1,242 @@
+#!/usr/bin/env python3
+"""Writes reference.xtc for xtc_tests.rs.
+
+The compressor is a line-by-line port of xdrfile_compress_coord_float from
+GROMACS' xdrfile.c (sendbits, sendints, sizeofint, sizeofints included), kept
+separate from the Rust reader so the two do not share a misreading. Coordinates
+are integers in units of 1/precision nm, as listed by `frames()`.
+"""
+
+import struct
+
+MAGICINTS = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64,
+    80, 101, 128, 161, 203, 256, 322, 406, 512, 645, 812, 1024, 1290,
+    1625, 2048, 2580, 3250, 4096, 5060, 6501, 8192, 10321, 13003,
+    16384, 20642, 26007, 32768, 41285, 52015, 65536, 82570, 104031,
+    131072, 165140, 208063, 262144, 330280, 416127, 524287, 660561,
+    832255, 1048576, 1321122, 1664510, 2097152, 2642245, 3329021,
+    4194304, 5284491, 6658042, 8388607, 10568983, 13316085, 16777216,
+]
+FIRSTIDX = 9
+LASTIDX = len(MAGICINTS)
+
+# Which branches of the compressor each frame went through
+paths = set()
+
+
+class Bits:
+    def __init__(self):
+        self.out = bytearray()
+        self.lastbits = 0
+        self.lastbyte = 0
+
+    def sendbits(self, nbits, num):
+        while nbits >= 8:
+            self.lastbyte = ((self.lastbyte << 8) | ((num >> (nbits - 8)) & 0xff)) & 0xffffffff
+            self.out.append((self.lastbyte >> self.lastbits) & 0xff)
+            nbits -= 8
+        if nbits > 0:
+            self.lastbyte = ((self.lastbyte << nbits) | (num & ((1 << nbits) - 1))) & 0xffffffff
+            self.lastbits += nbits
+            if self.lastbits >= 8:
+                self.lastbits -= 8
+                self.out.append((self.lastbyte >> self.lastbits) & 0xff)
+
+    def flush(self):
+        data = bytes(self.out)
+        if self.lastbits:
+            data += bytes([(self.lastbyte << (8 - self.lastbits)) & 0xff])
+        return data
+
+
+def sizeofint(size):
+    num, bits = 1, 0
+    while size >= num and bits < 32:
+        bits += 1
+        num <<= 1
+    return bits
+
+
+def sizeofints(sizes):
+    product = 1
+    for size in sizes:
+        product *= size
+    bits = 0
+    while product >= (1 << bits):
+        bits += 1
+    return bits
+
+
+def sendints(bits, num_of_bits, sizes, nums):
+    tmp = nums[0]
+    data = []
+    while True:
+        data.append(tmp & 0xff)
+        tmp >>= 8
+        if tmp == 0:
+            break
+    for i in range(1, 3):
+        assert nums[i] < sizes[i]
+        tmp = nums[i]
+        for k in range(len(data)):
+            tmp = data[k] * sizes[i] + tmp
+            data[k] = tmp & 0xff
+            tmp >>= 8
+        while tmp != 0:
+            data.append(tmp & 0xff)
+            tmp >>= 8
+    if num_of_bits >= len(data) * 8:
+        for byte in data:
+            bits.sendbits(8, byte)
+        bits.sendbits(num_of_bits - len(data) * 8, 0)
+    else:
+        for byte in data[:-1]:
+            bits.sendbits(8, byte)
+        bits.sendbits(num_of_bits - (len(data) - 1) * 8, data[-1])
+
+
+def compress(ints, precision):
+    size = len(ints)
+    out = struct.pack(">i", size)
+    if size <= 9:
+        return out + b"".join(struct.pack(">f", c / precision) for atom in ints for c in atom)
+
+    ip = [c for atom in ints for c in atom]
+    minint = [min(a[d] for a in ints) for d in range(3)]
+    maxint = [max(a[d] for a in ints) for d in range(3)]
+    mindiff = 2**31 - 1
+    for i in range(1, size):
+        diff = sum(abs(ints[i][d] - ints[i - 1][d]) for d in range(3))
+        mindiff = min(mindiff, diff)
+
+    out += struct.pack(">f", precision)
+    out += struct.pack(">3i", *minint) + struct.pack(">3i", *maxint)
+    sizeint = [maxint[d] - minint[d] + 1 for d in range(3)]
+    if (sizeint[0] | sizeint[1] | sizeint[2]) > 0xffffff:
+        bitsizeint = [sizeofint(s) for s in sizeint]
+        bitsize = 0
+        paths.add("large ints")
+    else:
+        bitsize = sizeofints(sizeint)
+
+    smallidx = FIRSTIDX
+    while smallidx < LASTIDX and MAGICINTS[smallidx] < mindiff:
+        smallidx += 1
+    out += struct.pack(">i", smallidx)
+
+    maxidx = min(LASTIDX, smallidx + 8)
+    minidx = maxidx - 8
+    smaller = MAGICINTS[max(FIRSTIDX, smallidx - 1)] // 2
+    smallnum = MAGICINTS[smallidx] // 2
+    sizesmall = [MAGICINTS[smallidx]] * 3
+    larger = MAGICINTS[maxidx] // 2
+
+    bits = Bits()
+    prevcoord = [0, 0, 0]
+    prevrun = -1
+    i = 0
+    while i < size:
+        is_small = 0
+        t = i * 3
+        if smallidx < maxidx and i >= 1 and all(abs(ip[t + d] - prevcoord[d]) < larger for d in range(3)):
+            is_smaller = 1
+        elif smallidx > minidx:
+            is_smaller = -1
+        else:
+            is_smaller = 0
+        if i + 1 < size and all(abs(ip[t + d] - ip[t + 3 + d]) < smallnum for d in range(3)):
+            # Interchange first with second atom for better compression of water
+            for d in range(3):
+                ip[t + d], ip[t + 3 + d] = ip[t + 3 + d], ip[t + d]
+            is_small = 1
+        tmpcoord = [ip[t + d] - minint[d] for d in range(3)]
+        if bitsize == 0:
+            for d in range(3):
+                bits.sendbits(bitsizeint[d], tmpcoord[d])
+        else:
+            sendints(bits, bitsize, sizeint, tmpcoord)
+        prevcoord = ip[t:t + 3]
+        t += 3
+        i += 1
+
+        run = 0
+        if is_small == 0 and is_smaller == -1:
+            is_smaller = 0
+        tmpcoord = []
+        while is_small and run < 8 * 3:
+            if is_smaller == -1 and sum((ip[t + d] - prevcoord[d]) ** 2 for d in range(3)) >= smaller * smaller:
+                is_smaller = 0
+            tmpcoord += [ip[t + d] - prevcoord[d] + smallnum for d in range(3)]
+            run += 3
+            prevcoord = ip[t:t + 3]
+            i += 1
+            t += 3
+            is_small = 0
+            if i < size and all(abs(ip[t + d] - prevcoord[d]) < smallnum for d in range(3)):
+                is_small = 1
+
+        if run != prevrun or is_smaller != 0:
+            prevrun = run
+            bits.sendbits(1, 1)
+            bits.sendbits(5, run + is_smaller + 1)
+        else:
+            bits.sendbits(1, 0)
+            if run > 0:
+                paths.add("run reuse")
+        for k in range(0, run, 3):
+            sendints(bits, smallidx, sizesmall, tmpcoord[k:k + 3])
+        if is_smaller != 0:
+            paths.add("smaller" if is_smaller < 0 else "larger")
+            smallidx += is_smaller
+            if is_smaller < 0:
+                smallnum = smaller
+                smaller = MAGICINTS[smallidx - 1] // 2
+            else:
+                smaller = smallnum
+                smallnum = MAGICINTS[smallidx] // 2
+            sizesmall = [MAGICINTS[smallidx]] * 3
+
+    data = bits.flush()
+    out += struct.pack(">i", len(data)) + data + b"\0" * (-len(data) % 4)
+    return out
+
+
+def frames():
+    # A tight peptide-like chain, then waters, then a few far-apart atoms
+    packed = []
+    for k in range(12):
+        packed.append([1000 + 38 * k, 1500 + (k % 3) * 21, 2000 + (k % 2) * 17])
+    for w in range(6):
+        o = [600 + 310 * w, 700 + 45 * w, 900 + 120 * (w % 3)]
+        packed += [o, [o[0] + 96, o[1], o[2]], [o[0] - 24, o[1] + 93, o[2]]]
+    for k in range(8):
+        packed.append([300 + 900 * k, 2800 - 250 * k, 400 + 333 * (k % 4)])
+    # Evenly spaced atoms widen the small-integer range, tight triples narrow it
+    for k in range(10):
+        packed.append([5000 + 150 * k, 5000 + (k % 2) * 90, 5000])
+    for w in range(4):
+        o = [5200 + 400 * w, 6000, 6000]
+        packed += [o, [o[0] + 9, o[1], o[2]], [o[0], o[1] + 8, o[2]]]
+
+    # At a precision of 1e6 a 20 nm span needs more than 24 bits per axis,
+    # which forces full-width integers
+    spread = [[1000 * c for c in atom] for atom in packed]
+    spread[3] = [20000000, 0, 5000]
+    return [(0, 0.0, 5.0, 1000.0, packed), (250, 0.5, 21.0, 1e6, spread)]
+
+
+def main():
+    out = b""
+    for step, time, box, precision, ints in frames():
+        out += struct.pack(">iiif", 1995, len(ints), step, time)
+        for row in range(3):
+            out += struct.pack(">3f", *[box if row == col else 0.0 for col in range(3)])
+        out += compress([list(a) for a in ints], precision)
+    with open("reference.xtc", "wb") as f:
+        f.write(out)
+    assert paths == {"large ints", "larger", "run reuse", "smaller"}, paths
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,186 @@
+use std::io::Cursor;
+
+use molecule_core::{DcdReader, PdbParser};
+
+struct DcdBuilder {
+    big_endian: bool,
+    unit_cell: Option<[f64; 6]>,
+    free_atoms: Option<Vec<i32>>,
+}
+
+impl DcdBuilder {
+    fn int(&self, value: i32) -> [u8; 4] {
+        if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+
+    fn record(&self, out: &mut Vec<u8>, payload: &[u8]) {
+        out.extend(self.int(payload.len() as i32));
+        out.extend(payload);
+        out.extend(self.int(payload.len() as i32));
+    }
+
+    fn floats(&self, values: impl Iterator<Item = f32>) -> Vec<u8> {
+        values
+            .flat_map(|v| if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() })
+            .collect()
+    }
+
+    fn build(&self, n_atoms: usize, frames: &[Vec<[f32; 3]>]) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        let mut header = b"CORD".to_vec();
+        let fixed = self.free_atoms.as_ref().map_or(0, |free| n_atoms - free.len());
+        let mut control = [0i32; 20];
+        control[0] = frames.len() as i32;
+        control[1] = 100;
+        control[2] = 10;
+        control[8] = fixed as i32;
+        control[10] = self.unit_cell.is_some() as i32;
+        control[19] = 24;
+        for (i, value) in control.iter().enumerate() {
+            if i == 9 {
+                header.extend(self.floats(std::iter::once(2.0)));
+            } else {
+                header.extend(self.int(*value));
+            }
+        }
+        self.record(&mut out, &header);
+
+        let mut title = self.int(1).to_vec();
+        title.extend(format!("{:<80}", "REMARKS test trajectory").as_bytes());
+        self.record(&mut out, &title);
+        self.record(&mut out, &self.int(n_atoms as i32));
+
+        if let Some(free_atoms) = &self.free_atoms {
+            let payload: Vec<u8> = free_atoms.iter().flat_map(|&i| self.int(i)).collect();
+            self.record(&mut out, &payload);
+        }
+
+        for frame in frames {
+            if let Some(cell) = self.unit_cell {
+                let payload: Vec<u8> = cell
+                    .iter()
+                    .flat_map(|v| if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() })
+                    .collect();
+                self.record(&mut out, &payload);
+            }
+            for dim in 0..3 {
+                let payload = self.floats(frame.iter().map(|p| p[dim]));
+                self.record(&mut out, &payload);
+            }
+        }
+
+        out
+    }
+}
+
+const TOPOLOGY: &str = "
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  ALA A   1       1.000   0.000   0.000  1.00  0.00           C
+ATOM      3  C   ALA A   1       2.000   0.000   0.000  1.00  0.00           C
+";
+
+fn frames() -> Vec<Vec<[f32; 3]>> {
+    (0..4)
+        .map(|f| (0..3).map(|a| [a as f32, f as f32, 0.5 * f as f32]).collect())
+        .collect()
+}
+
+#[test]
+fn test_dcd_both_endiannesses() {
+    for big_endian in [false, true] {
+        let builder = DcdBuilder { big_endian, unit_cell: None, free_atoms: None };
+        let mut reader = DcdReader::new(Cursor::new(builder.build(3, &frames()))).unwrap();
+
+        assert_eq!(reader.n_atoms(), 3);
+        assert_eq!(reader.n_frames(), 4);
+        assert_eq!(reader.title()[0], "REMARKS test trajectory");
+
+        let frame = reader.read_frame(2).unwrap();
+        assert_eq!(frame.positions[1], [1.0, 2.0, 1.0]);
+        assert_eq!(frame.step, Some(120));
+        assert!((frame.time.unwrap() - 120.0 * 2.0 * 0.04888821).abs() < 1e-3);
+        assert!(frame.unit_cell.is_none());
+    }
+}
+
+#[test]
+fn test_dcd_random_access_onto_topology() {
+    let builder = DcdBuilder {
+        big_endian: false,
+        unit_cell: Some([30.0, 90.0, 40.0, 90.0, 90.0, 50.0]),
+        free_atoms: None,
+    };
+    let mut reader = DcdReader::new(Cursor::new(builder.build(3, &frames()))).unwrap();
+    let mut molecule = PdbParser::new().parse_string(TOPOLOGY).unwrap();
+
+    let frame = reader.read_frame(3).unwrap();
+    frame.apply_to(&mut molecule).unwrap();
+    assert_eq!(molecule.atoms.len(), 3);
+    assert_eq!(molecule.atoms[2].position, [2.0, 3.0, 1.5]);
+    assert_eq!(molecule.atoms[2].name, "C");
+
+    let lengths = molecule.unit_cell.unwrap().lengths();
+    assert!((lengths[0] - 30.0).abs() < 1e-4);
+    assert!((lengths[1] - 40.0).abs() < 1e-4);
+    assert!((lengths[2] - 50.0).abs() < 1e-4);
+
+    let frame = reader.read_frame(0).unwrap();
+    frame.apply_to(&mut molecule).unwrap();
+    assert_eq!(molecule.atoms[2].position, [2.0, 0.0, 0.0]);
+
+    assert!(reader.read_frame(4).is_err());
+    assert_eq!(reader.frames().count(), 4);
+}
+
+#[test]
+fn test_dcd_fixed_atoms() {
+    let builder = DcdBuilder { big_endian: true, unit_cell: None, free_atoms: Some(vec![2]) };
+    let full = frames();
+    let mut stored = vec![full[0].clone()];
+    stored.extend(full[1..].iter().map(|frame| vec![frame[1]]));
+
+    let mut reader = DcdReader::new(Cursor::new(builder.build(3, &stored))).unwrap();
+    assert_eq!(reader.n_frames(), 4);
+    assert_eq!(reader.fixed_atom_count(), 2);
+
+    let frame = reader.read_frame(2).unwrap();
+    assert_eq!(frame.positions, vec![[0.0, 0.0, 0.0], [1.0, 2.0, 1.0], [2.0, 0.0, 0.0]]);
+}
+
+#[test]
+fn test_dcd_atom_count_mismatch() {
+    let builder = DcdBuilder { big_endian: false, unit_cell: None, free_atoms: None };
+    let positions = vec![vec![[0.0; 3]; 2]];
+    let mut reader = DcdReader::new(Cursor::new(builder.build(2, &positions))).unwrap();
+    let mut molecule = PdbParser::new().parse_string(TOPOLOGY).unwrap();
+
+    let frame = reader.read_frame(0).unwrap();
+    assert!(frame.apply_to(&mut molecule).is_err());
+}
+
+#[test]
+fn test_dcd_corrupt_record_length() {
+    let builder = DcdBuilder { big_endian: false, unit_cell: None, free_atoms: None };
+    let mut bytes = builder.build(3, &frames());
+
+    // A title record claiming ~2 GB must fail cleanly instead of allocating
+    let title_marker = 4 + 84 + 4;
+    bytes[title_marker..title_marker + 4].copy_from_slice(&0x7fff_fff0i32.to_le_bytes());
+    let error = DcdReader::new(Cursor::new(bytes)).err().expect("corrupt header should be rejected");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_dcd_huge_free_atom_record() {
+    let builder = DcdBuilder { big_endian: false, unit_cell: None, free_atoms: Some(vec![2]) };
+    let mut bytes = builder.build(3, &frames()[..1]);
+
+    // A header atom count of ~500M must not license a ~2 GB free-atom record
+    let atom_count = 4 + 84 + 4 + 4 + 84 + 4 + 4;
+    bytes[atom_count..atom_count + 4].copy_from_slice(&0x1fff_ffffi32.to_le_bytes());
+    let free_marker = atom_count + 8;
+    bytes[free_marker..free_marker + 4].copy_from_slice(&0x7fff_fff0i32.to_le_bytes());
+    let error = DcdReader::new(Cursor::new(bytes)).err().expect("oversized record should be rejected");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,145 @@
+use molecule_core::{
+    AtomIndex, ChainIndex, Element, GroParser, GroWriter, MmcifWriter, ModelIndex, Molecule, PdbParser, PdbWriter,
+    PqrParser, PqrWriter, ResidueIndex, XyzWriter,
+};
+
+const DIPEPTIDE_PDB: &str = "\
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  N   GLY B   2      13.730   5.933  -3.944  1.00  0.00           N
+ATOM      4  CA  GLY B   2      15.175   5.850  -3.830  1.00  0.00           C
+HETATM    5 FE   HEM B 101      20.000  10.000   0.000  1.00 20.00          FE
+HETATM    6  NA  HEM B 101      21.900  10.000   0.000  1.00 20.00           N
+CONECT    5    6
+END
+";
+
+// Every index stored in the hierarchy points back at its owner
+fn assert_consistent(molecule: &Molecule) {
+    for (index, atom) in molecule.atoms.iter().enumerate() {
+        let residue = &molecule[atom.residue];
+        assert!(residue.atoms.contains(&AtomIndex(index)));
+        assert_eq!(residue.id, atom.residue_id);
+        assert_eq!(residue.name, atom.residue_name);
+        assert_eq!(molecule[residue.chain].id, atom.chain_id);
+    }
+    for (index, chain) in molecule.chains.iter().enumerate() {
+        assert!(!chain.residues.is_empty());
+        assert!(chain.residues.iter().all(|&residue| molecule[residue].chain == ChainIndex(index)));
+    }
+    for bond in &molecule.bonds {
+        assert!(bond.atom1.0 < molecule.atoms.len() && bond.atom2.0 < molecule.atoms.len());
+    }
+}
+
+#[test]
+fn test_remove_atoms_updates_bonds_residues_and_chains() {
+    let mut molecule = PdbParser::new().parse_string(DIPEPTIDE_PDB).unwrap();
+    let bonds = molecule.bonds.len();
+
+    let glycine = molecule.select("resn GLY").unwrap();
+    molecule.remove_atoms(&glycine).unwrap();
+    assert_consistent(&molecule);
+    assert_eq!(molecule.atoms.len(), 4);
+    assert_eq!(molecule.residues.len(), 2);
+    assert!(molecule.bonds.len() < bonds);
+    assert!(molecule.bonds.iter().any(|bond| molecule[bond.atom1].name == "FE" && molecule[bond.atom2].name == "NA"));
+
+    // Removing the heme empties chain B, which disappears
+    molecule.remove_atoms(&[AtomIndex(2), AtomIndex(3)]).unwrap();
+    assert_consistent(&molecule);
+    assert_eq!(molecule.chains.len(), 1);
+    assert!(molecule.bonds.iter().all(|bond| bond.atom1.0 < 2 && bond.atom2.0 < 2));
+    assert!(molecule.remove_atoms(&[AtomIndex(2)]).is_err());
+}
+
+#[test]
+fn test_rename_renumber_and_atom_edits() {
+    let mut molecule = PdbParser::new().parse_string(DIPEPTIDE_PDB).unwrap();
+
+    molecule.rename_chain(ChainIndex(1), 'C').unwrap();
+    molecule.rename_residue(ResidueIndex(2), "HEC");
+    molecule.renumber_chain(ChainIndex(1), 10);
+    molecule.set_element(AtomIndex(4), Element::Co);
+    molecule.set_position(AtomIndex(4), [1.0, 2.0, 3.0]);
+    assert_consistent(&molecule);
+
+    assert_eq!(molecule.select("chain C").unwrap().len(), 4);
+    assert_eq!(molecule.select("resi 10-11").unwrap().len(), 4);
+    assert_eq!(molecule[AtomIndex(5)].residue_name, "HEC");
+    assert_eq!(molecule[AtomIndex(4)].element, Element::Co);
+    assert_eq!(molecule[AtomIndex(4)].position, [1.0, 2.0, 3.0]);
+
+    // Two chains of one model cannot share an identifier
+    assert!(molecule.rename_chain(ChainIndex(0), 'C').is_err());
+    assert_eq!(molecule[ChainIndex(0)].id, 'A');
+    molecule.rename_chain(ChainIndex(1), 'C').unwrap();
+}
+
+#[test]
+fn test_merge_resolves_chain_conflicts() {
+    let mut molecule = PdbParser::new().parse_string(DIPEPTIDE_PDB).unwrap();
+    let other = PdbParser::new().parse_string(DIPEPTIDE_PDB).unwrap();
+
+    let renames = molecule.merge(&other).unwrap();
+    assert_consistent(&molecule);
+    assert_eq!(renames, vec![('A', 'C'), ('B', 'D')]);
+    assert_eq!(molecule.atoms.len(), 12);
+    assert_eq!(molecule.bonds.len(), 2 * other.bonds.len());
+    assert_eq!(molecule.select("chain D and resn HEM").unwrap(), vec![AtomIndex(10), AtomIndex(11)]);
+    assert_eq!(molecule.chains.len(), 4);
+
+    // Models are matched by serial number
+    let mut first = PdbParser::new().parse_string(&format!("MODEL        1\n{}ENDMDL\n", DIPEPTIDE_PDB.replace("END\n", ""))).unwrap();
+    let second = first.clone();
+    first.merge(&second).unwrap();
+    assert_eq!(first.models.len(), 1);
+    assert_eq!(first[ModelIndex(0)].atoms.len(), 12);
+    assert_consistent(&first);
+}
+
+#[test]
+fn test_merged_models_round_trip_through_writers() {
+    // Two-model inputs; after merging, each model's atoms come in two runs
+    let atoms = DIPEPTIDE_PDB.replace("END\n", "");
+    let shifted = atoms.replace("  6.134", " 16.134");
+    let pdb = format!("MODEL        1\n{}ENDMDL\nMODEL        2\n{}ENDMDL\n", atoms, shifted);
+    let mut molecule = PdbParser::new().parse_string(&pdb).unwrap();
+    molecule.merge(&PdbParser::new().parse_string(&pdb).unwrap()).unwrap();
+    assert_eq!(molecule[ModelIndex(0)].atoms[6], AtomIndex(12));
+
+    let expected: Vec<Vec<[f32; 3]>> = molecule
+        .models
+        .iter()
+        .map(|model| model.atoms.iter().map(|&index| molecule[index].position).collect())
+        .collect();
+    let model_positions = |copy: &Molecule| -> Vec<Vec<[f32; 3]>> {
+        copy.models.iter().map(|model| model.atoms.iter().map(|&index| copy[index].position).collect()).collect()
+    };
+
+    let pdb = PdbWriter::new().write_string(&molecule).unwrap();
+    assert_eq!(model_positions(&PdbParser::new().parse_string(&pdb).unwrap()), expected);
+
+    let pqr = PqrWriter::new().write_string(&molecule).unwrap();
+    assert_eq!(model_positions(&PqrParser::new().parse_string(&pqr).unwrap()), expected);
+
+    let gro = GroWriter::new().write_string(&molecule).unwrap();
+    let reparsed = GroParser::new().parse_string(&gro).unwrap();
+    assert_eq!(reparsed.models.iter().map(|model| model.atoms.len()).collect::<Vec<_>>(), vec![12, 12]);
+    let position = reparsed[reparsed[ModelIndex(1)].atoms[6]].position;
+    assert!((0..3).all(|d| (position[d] - expected[1][6][d]).abs() < 0.01));
+
+    let xyz = XyzWriter::new().write_string(&molecule).unwrap();
+    let lines: Vec<&str> = xyz.lines().collect();
+    assert_eq!((lines[0], lines[14]), ("12", "12"));
+    let y = |line: &str| line.split_whitespace().nth(2).unwrap().parse::<f32>().unwrap();
+    assert_eq!((y(lines[2 + 6]), y(lines[14 + 2 + 6])), (6.134, 16.134));
+
+    let cif = MmcifWriter::new().write_string(&molecule).unwrap();
+    let model_numbers: Vec<&str> = cif
+        .lines()
+        .filter(|line| line.starts_with("ATOM") || line.starts_with("HETATM"))
+        .map(|line| line.split_whitespace().last().unwrap())
+        .collect();
+    assert_eq!(model_numbers, [vec!["1"; 12], vec!["2"; 12]].concat());
+}
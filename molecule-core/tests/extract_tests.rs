@@ -0,0 +1,94 @@
+use molecule_core::{AtomIndex, ExtractOptions, ModelIndex, PdbParser, PdbWriter};
+
+const COMPLEX_PDB: &str = "\
+HEADER    OXIDOREDUCTASE                          12-MAR-99   1ABC
+CRYST1   50.000   60.000   70.000  90.00  95.00  90.00 P 1 21 1      2
+MODEL        1
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  N   GLY B   7      13.730   5.933  -3.944  1.00  0.00           N
+ATOM      4  CA  GLY B   7      15.175   5.850  -3.830  1.00  0.00           C
+HETATM    5 FE   HEM B 101      20.000  10.000   0.000  1.00 20.00          FE
+HETATM    6  NA  HEM B 101      21.900  10.000   0.000  1.00 20.00           N
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1      11.204   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.739   6.071  -5.147  1.00  0.00           C
+ATOM      3  N   GLY B   7      13.830   5.933  -3.944  1.00  0.00           N
+ATOM      4  CA  GLY B   7      15.275   5.850  -3.830  1.00  0.00           C
+HETATM    5 FE   HEM B 101      20.100  10.000   0.000  1.00 20.00          FE
+HETATM    6  NA  HEM B 101      22.000  10.000   0.000  1.00 20.00           N
+ENDMDL
+CONECT    5    6
+END
+";
+
+#[test]
+fn test_extract_keeps_hierarchy_bonds_and_metadata() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let chain_b = molecule.select("chain B").unwrap();
+    let extracted = molecule.extract(&chain_b, ExtractOptions::new()).unwrap();
+
+    assert_eq!(extracted.atoms.len(), 8);
+    assert_eq!(extracted.models.len(), 2);
+    assert_eq!(extracted.models().map(|model| model.id()).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+    assert_eq!(extracted.chains.len(), 2);
+    assert_eq!(extracted.residues.len(), 4);
+    assert_eq!(extracted[ModelIndex(1)].atoms.len(), 4);
+    assert_eq!(extracted.metadata.id_code, molecule.metadata.id_code);
+    assert!(extracted.unit_cell.is_some());
+
+    // Original numbering is kept by default
+    assert_eq!(extracted[AtomIndex(0)].id, 3);
+    assert_eq!(extracted[AtomIndex(0)].residue_id, 7);
+
+    // Only bonds inside the selection survive, remapped to the new indices
+    assert!(!extracted.bonds.is_empty());
+    assert!(extracted.bonds.iter().all(|bond| bond.atom1.0 < 8 && bond.atom2.0 < 8));
+    assert!(extracted.bonds.iter().any(|bond| {
+        extracted[bond.atom1].name == "FE" && extracted[bond.atom2].name == "NA"
+    }));
+
+    let pdb = PdbWriter::new().write_string(&extracted).unwrap();
+    assert_eq!(PdbParser::new().parse_string(&pdb).unwrap().atoms.len(), 8);
+}
+
+#[test]
+fn test_extract_renumbering_and_invalid_indices() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let site = molecule.select("resn HEM or resi 7").unwrap();
+    let options = ExtractOptions::new().renumber_atoms(true).renumber_residues(true);
+    let extracted = molecule.extract(&site, options).unwrap();
+
+    let numbering: Vec<(usize, usize)> = extracted.atoms.iter().map(|atom| (atom.id, atom.residue_id)).collect();
+    assert_eq!(numbering, vec![(1, 1), (2, 1), (3, 2), (4, 2), (1, 1), (2, 1), (3, 2), (4, 2)]);
+    let residues: Vec<(usize, &str)> = extracted.residues.iter().map(|residue| (residue.id, residue.name.as_str())).collect();
+    assert_eq!(residues, vec![(1, "GLY"), (2, "HEM"), (1, "GLY"), (2, "HEM")]);
+
+    // Duplicates collapse; out-of-range indices are rejected
+    let twice = molecule.extract(&[AtomIndex(1), AtomIndex(1), AtomIndex(0)], ExtractOptions::default()).unwrap();
+    assert_eq!(twice.atoms.len(), 2);
+    assert_eq!(twice.residues.len(), 1);
+    assert!(molecule.extract(&[AtomIndex(12)], ExtractOptions::default()).is_err());
+}
+
+#[test]
+fn test_extract_resumes_interleaved_models() {
+    // Atoms added to the first model after the second leave its atoms in two runs
+    let mut molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    molecule.current_model = Some(ModelIndex(0));
+    for index in 0..6 {
+        let mut atom = molecule[AtomIndex(index)].clone();
+        atom.id += 6;
+        molecule.add_atom(atom);
+    }
+    molecule.end_model();
+    let all: Vec<AtomIndex> = (0..molecule.atoms.len()).map(AtomIndex).collect();
+    let extracted = molecule.extract(&all, ExtractOptions::new().renumber_atoms(true)).unwrap();
+
+    assert_eq!(extracted.models.iter().map(|model| model.id).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(extracted[ModelIndex(0)].atoms.len(), 12);
+    assert_eq!(extracted[ModelIndex(1)].atoms.len(), 6);
+    assert_eq!(extracted[AtomIndex(12)].id, 7);
+    assert_eq!(extracted.current_model, None);
+}
@@ -0,0 +1,95 @@
+use molecule_core::{Atom, AtomIndex, BondOrder, Element, Molecule};
+
+// Atoms without coordinates, connected only by the given bonds
+fn molecule(count: usize, bonds: &[(usize, usize)]) -> Molecule {
+    let mut molecule = Molecule::new();
+    for i in 0..count {
+        molecule.add_atom(Atom {
+            id: i + 1,
+            name: format!("C{}", i + 1),
+            element: Element::C,
+            residue_name: "LIG".to_string(),
+            is_hetatm: true,
+            ..Atom::default()
+        });
+    }
+    for &(atom1, atom2) in bonds {
+        molecule.add_bond(AtomIndex(atom1), AtomIndex(atom2), BondOrder::Single);
+    }
+    molecule
+}
+
+fn ring_sizes(molecule: &Molecule) -> Vec<usize> {
+    molecule.graph().rings().iter().map(Vec::len).collect()
+}
+
+#[test]
+fn test_neighbors_and_fragments() {
+    // Heavy atoms of ethanol next to a water with its hydrogens
+    let mixture = molecule(6, &[(0, 1), (1, 2), (3, 4), (3, 5)]);
+    let graph = mixture.graph();
+
+    assert_eq!(graph.len(), 6);
+    assert_eq!(graph.neighbors(AtomIndex(1)).collect::<Vec<_>>(), [AtomIndex(0), AtomIndex(2)]);
+    assert_eq!(graph.degree(AtomIndex(3)), 2);
+    assert_eq!(graph.bond_between(AtomIndex(3), AtomIndex(5)).map(|bond| mixture[bond].atom2), Some(AtomIndex(5)));
+    assert_eq!(graph.bond_between(AtomIndex(0), AtomIndex(2)), None);
+
+    let fragments = graph.fragments();
+    assert_eq!(
+        fragments,
+        [
+            vec![AtomIndex(0), AtomIndex(1), AtomIndex(2)],
+            vec![AtomIndex(3), AtomIndex(4), AtomIndex(5)],
+        ]
+    );
+
+    let single = molecule(1, &[]).graph().fragments();
+    assert_eq!(single, [vec![AtomIndex(0)]]);
+}
+
+#[test]
+fn test_topological_distance() {
+    // Cyclohexane with a two-atom tail on atom 0, plus an unbonded atom
+    let molecule = molecule(9, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0), (0, 6), (6, 7)]);
+    let graph = molecule.graph();
+
+    assert_eq!(graph.distance(AtomIndex(7), AtomIndex(3)), Some(5));
+    assert_eq!(graph.distance(AtomIndex(2), AtomIndex(2)), Some(0));
+    assert_eq!(graph.distance(AtomIndex(0), AtomIndex(8)), None);
+
+    let path = graph.shortest_path(AtomIndex(7), AtomIndex(2)).unwrap();
+    assert_eq!(path, [AtomIndex(7), AtomIndex(6), AtomIndex(0), AtomIndex(1), AtomIndex(2)]);
+    assert_eq!(graph.shortest_path(AtomIndex(4), AtomIndex(8)), None);
+}
+
+#[test]
+fn test_smallest_set_of_smallest_rings() {
+    let naphthalene = molecule(
+        10,
+        &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0), (4, 6), (6, 7), (7, 8), (8, 9), (9, 5)],
+    );
+    assert_eq!(ring_sizes(&naphthalene), [6, 6]);
+
+    // Every ring is listed in bond order around the ring
+    let graph = naphthalene.graph();
+    for ring in graph.rings() {
+        for (i, &atom) in ring.iter().enumerate() {
+            assert!(graph.bond_between(atom, ring[(i + 1) % ring.len()]).is_some());
+        }
+    }
+
+    let cubane = molecule(
+        8,
+        &[(0, 1), (1, 2), (2, 3), (3, 0), (4, 5), (5, 6), (6, 7), (7, 4), (0, 4), (1, 5), (2, 6), (3, 7)],
+    );
+    assert_eq!(ring_sizes(&cubane), [4, 4, 4, 4, 4]);
+
+    // Biphenyl: the bridge between rings is not itself in a ring
+    let biphenyl = molecule(
+        12,
+        &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0), (0, 6), (6, 7), (7, 8), (8, 9), (9, 10), (10, 11), (11, 6)],
+    );
+    assert_eq!(ring_sizes(&biphenyl), [6, 6]);
+    assert!(ring_sizes(&molecule(4, &[(0, 1), (1, 2), (2, 3)])).is_empty());
+}
@@ -0,0 +1,133 @@
+use molecule_core::{AtomIndex, Element, GroParser, GroWriter};
+
+#[test]
+fn test_parse_gro_with_velocities() {
+    let gro_content = "\
+Water in a box t=   0.00000 step= 0
+    3
+    1SOL     OW    1   0.126   1.624   1.679  0.1227 -0.0580  0.0434
+    1SOL    HW1    2   0.190   1.661   1.747  0.8085  0.3191 -0.7791
+    1SOL    HW2    3   0.177   1.568   1.613 -0.9045 -2.6469  1.3180
+   1.86206   1.86206   1.86206
+";
+
+    let parser = GroParser::new();
+    let molecule = parser.parse_string(gro_content).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 3);
+    assert_eq!(molecule.models.len(), 0);
+    assert_eq!(molecule.metadata.title.as_deref(), Some("Water in a box t=   0.00000 step= 0"));
+
+    let oxygen = &molecule.atoms[0];
+    assert_eq!(oxygen.id, 1);
+    assert_eq!(oxygen.name, "OW");
+    assert_eq!(oxygen.element, Element::O);
+    assert_eq!(oxygen.residue_id, 1);
+    assert_eq!(oxygen.residue_name, "SOL");
+
+    // nm are converted to Å
+    assert!((oxygen.position[0] - 1.26).abs() < 1e-4);
+    assert!((oxygen.position[2] - 16.79).abs() < 1e-4);
+
+    let velocity = oxygen.velocity.expect("velocities should be kept");
+    assert!((velocity[0] - 1.227).abs() < 1e-4);
+    assert!((velocity[1] + 0.580).abs() < 1e-4);
+
+    assert_eq!(molecule.atoms[1].element, Element::H);
+    assert_eq!(molecule.bonds.len(), 3);
+
+    let cell = molecule.unit_cell.expect("box should be stored");
+    assert!(cell.is_orthorhombic());
+    assert!((cell.lengths()[0] - 18.6206).abs() < 1e-3);
+}
+
+#[test]
+fn test_parse_gro_triclinic_box_and_frames() {
+    let gro_content = "\
+Frame 1
+    1
+    1ALA      N    1   1.000   2.000   3.000
+   5.00000   5.00000   3.53553   0.00000   0.00000   0.00000   0.00000   2.50000   2.50000
+Frame 2
+    1
+    1ALA      N    1   1.100   2.100   3.100
+   5.00000   5.00000   3.53553   0.00000   0.00000   0.00000   0.00000   2.50000   2.50000
+";
+
+    let parser = GroParser::new();
+    let molecule = parser.parse_string(gro_content).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 2);
+    assert_eq!(molecule.models.len(), 2);
+    assert_eq!(molecule.atoms[1].id, 2);
+    assert!(molecule.atoms[0].velocity.is_none());
+
+    let cell = molecule.unit_cell.unwrap();
+    assert_eq!(cell.vectors[2], [25.0, 25.0, 35.3553]);
+    let angles = cell.angles();
+    assert!((angles[0] - 60.0).abs() < 0.01);
+    assert!((angles[1] - 60.0).abs() < 0.01);
+    assert!((angles[2] - 90.0).abs() < 0.01);
+}
+
+#[test]
+fn test_parse_gro_truncated() {
+    let gro_content = "\
+Broken
+    2
+    1SOL     OW    1   0.126   1.624   1.679
+";
+
+    let parser = GroParser::new();
+    assert!(parser.parse_string(gro_content).is_err());
+
+    // A multi-byte character across a column boundary is an error, not a panic
+    let non_ascii = "Broken\n    1\n    1SOL      ÅW    1   0.126   1.624   1.679\n   1.0   1.0   1.0\n";
+    assert!(parser.parse_string(non_ascii).is_err());
+}
+
+#[test]
+fn test_parse_gro_with_empty_title() {
+    let gro_content = "
+    1
+    1SOL     OW    1   0.126   1.624   1.679
+   1.86206   1.86206   1.86206
+
+";
+    let molecule = GroParser::new().parse_string(gro_content).unwrap();
+    assert_eq!(molecule.atoms.len(), 1);
+    assert_eq!(molecule.metadata.title, None);
+    assert!(molecule.unit_cell.is_some());
+}
+
+#[test]
+fn test_write_gro_round_trip_with_selection() {
+    let gro_content = "\
+Two waters
+    6
+    1SOL     OW    1   0.126   1.624   1.679  0.1227 -0.0580  0.0434
+    1SOL    HW1    2   0.190   1.661   1.747  0.8085  0.3191 -0.7791
+    1SOL    HW2    3   0.177   1.568   1.613 -0.9045 -2.6469  1.3180
+    2SOL     OW    4   1.126   0.624   0.679  0.0000  0.0000  0.0000
+    2SOL    HW1    5   1.190   0.661   0.747  0.0000  0.0000  0.0000
+    2SOL    HW2    6   1.177   0.568   0.613  0.0000  0.0000  0.0000
+   1.86206   1.86206   1.86206   0.00000   0.00000   0.93103   0.00000   0.93103   0.93103
+";
+    let molecule = GroParser::new().parse_string(gro_content).unwrap();
+    assert_eq!(molecule.metadata.title.as_deref(), Some("Two waters"));
+
+    let written = GroWriter::new().write_string(&molecule).unwrap();
+    assert_eq!(written, gro_content);
+
+    let selected = GroWriter::new().with_selection((3..6).map(AtomIndex).collect()).write_string(&molecule).unwrap();
+    let lines: Vec<&str> = selected.lines().collect();
+    assert_eq!(lines[1], "    3");
+    assert_eq!(lines[2], "    2SOL     OW    1   1.126   0.624   0.679  0.0000  0.0000  0.0000");
+    assert_eq!(lines.len(), 6);
+
+    let reparsed = GroParser::new().parse_string(&selected).unwrap();
+    assert_eq!(reparsed.atoms.len(), 3);
+    assert_eq!(reparsed.unit_cell, molecule.unit_cell);
+
+    assert!(GroWriter::new().with_selection(vec![AtomIndex(6)]).write_string(&molecule).is_err());
+}
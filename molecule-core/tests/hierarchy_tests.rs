@@ -0,0 +1,120 @@
+use molecule_core::{AtomIndex, ChainIndex, ModelIndex, PdbParser, ResidueIndex};
+
+const TWO_CHAINS_PDB: &str = "\
+MODEL        1
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  N   GLY B   1      21.104   6.134  -6.504  1.00  0.00           N
+ATOM      4  CA  GLY B   1      21.639   6.071  -5.147  1.00  0.00           C
+HETATM    5 ZN    ZN A 101      30.000   0.000   0.000  1.00 20.00          ZN
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1      11.204   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.739   6.071  -5.147  1.00  0.00           C
+ATOM      3  N   GLY B   1      21.204   6.134  -6.504  1.00  0.00           N
+ATOM      4  CA  GLY B   1      21.739   6.071  -5.147  1.00  0.00           C
+HETATM    5 ZN    ZN A 101      30.100   0.000   0.000  1.00 20.00          ZN
+ENDMDL
+END
+";
+
+#[test]
+fn test_residues_are_per_chain_and_model() {
+    let molecule = PdbParser::new().parse_string(TWO_CHAINS_PDB).unwrap();
+
+    // Residue 1 of chain A and residue 1 of chain B are different residues
+    assert_eq!(molecule.residues.len(), 6);
+    assert_eq!(molecule.chains.len(), 4);
+    assert_eq!(molecule[ResidueIndex(0)].name, "ALA");
+    assert_eq!(molecule[ResidueIndex(1)].name, "GLY");
+
+    // The zinc listed after chain B joins chain A of the same model
+    let chain_a = &molecule[ChainIndex(0)];
+    assert_eq!(chain_a.id, 'A');
+    assert_eq!(chain_a.model, Some(ModelIndex(0)));
+    assert_eq!(chain_a.residues, vec![ResidueIndex(0), ResidueIndex(2)]);
+
+    let second_model = &molecule[ModelIndex(1)];
+    assert_eq!(second_model.id, 2);
+    assert_eq!(second_model.atoms.first(), Some(&AtomIndex(5)));
+    assert_eq!(molecule[ChainIndex(2)].model, Some(ModelIndex(1)));
+}
+
+#[test]
+fn test_atoms_point_to_their_residue() {
+    let molecule = PdbParser::new().parse_string(TWO_CHAINS_PDB).unwrap();
+
+    for (index, atom) in molecule.atoms.iter().enumerate() {
+        let residue = &molecule[atom.residue];
+        assert!(residue.atoms.contains(&AtomIndex(index)));
+        assert_eq!(residue.id, atom.residue_id);
+        assert_eq!(molecule[residue.chain].id, atom.chain_id);
+    }
+
+    // Serial numbers repeat across models; indices do not
+    assert_eq!(molecule.find_atom(3), Some(AtomIndex(2)));
+    assert_eq!(molecule[AtomIndex(7)].id, 3);
+    assert_eq!(molecule.find_atom(42), None);
+    assert!(molecule.bonds.iter().all(|bond| bond.atom1.0 < 10 && bond.atom2.0 < 10));
+}
+
+#[test]
+fn test_traversal_follows_file_order() {
+    let molecule = PdbParser::new().parse_string(TWO_CHAINS_PDB).unwrap();
+
+    let models: Vec<_> = molecule.models().map(|model| model.id()).collect();
+    assert_eq!(models, vec![Some(1), Some(2)]);
+
+    let first = molecule.models().next().unwrap();
+    let chains: Vec<char> = first.chains().map(|chain| chain.id).collect();
+    assert_eq!(chains, vec!['A', 'B']);
+
+    let chain_a = first.chains().next().unwrap();
+    let residues: Vec<&str> = chain_a.residues().map(|residue| residue.get().name.as_str()).collect();
+    assert_eq!(residues, vec!["ALA", "ZN"]);
+
+    let names: Vec<&str> = chain_a.residues().next().unwrap().atoms().map(|atom| atom.get().name.as_str()).collect();
+    assert_eq!(names, vec!["N", "CA"]);
+    // Chains group the zinc with chain A; the model lists atoms in file order
+    let by_chain: Vec<usize> = first.chains().flat_map(|chain| chain.atoms()).map(|atom| atom.get().id).collect();
+    assert_eq!(by_chain, vec![1, 2, 5, 3, 4]);
+    let in_file_order: Vec<usize> = first.atoms().map(|atom| atom.get().id).collect();
+    assert_eq!(in_file_order, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_parent_lookups_and_implicit_model() {
+    let molecule = PdbParser::new().parse_string(TWO_CHAINS_PDB).unwrap();
+
+    let zinc = molecule.atom(AtomIndex(9));
+    assert_eq!(zinc.residue().name, "ZN");
+    assert_eq!(zinc.chain().id, 'A');
+    assert_eq!(zinc.chain().index(), ChainIndex(2));
+    assert_eq!(zinc.model().id(), Some(2));
+
+    // Without MODEL records every chain belongs to one implicit model
+    let first_model = TWO_CHAINS_PDB.split("ENDMDL").next().unwrap();
+    let single = PdbParser::new().parse_string(first_model.trim_start_matches("MODEL        1\n")).unwrap();
+    let models: Vec<_> = single.models().collect();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].index(), None);
+    assert_eq!(models[0].chains().count(), 2);
+    assert_eq!(single.atom(AtomIndex(0)).model().index(), None);
+    let indices: Vec<AtomIndex> = models[0].atoms().map(|atom| atom.index()).collect();
+    assert_eq!(indices, (0..5).map(AtomIndex).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_loose_atoms_keep_their_place_among_models() {
+    let water = "HETATM    1  O   HOH W   1       0.000   0.000   0.000  1.00 30.00           O\n";
+
+    let before = PdbParser::new().parse_string(&format!("{}{}", water, TWO_CHAINS_PDB)).unwrap();
+    let models: Vec<_> = before.models().map(|model| model.id()).collect();
+    assert_eq!(models, vec![None, Some(1), Some(2)]);
+    assert_eq!(before.models().next().unwrap().atoms().count(), 1);
+
+    let between = TWO_CHAINS_PDB.replacen("ENDMDL\n", &format!("ENDMDL\n{}", water), 1);
+    let between = PdbParser::new().parse_string(&between).unwrap();
+    let models: Vec<_> = between.models().map(|model| model.id()).collect();
+    assert_eq!(models, vec![Some(1), None, Some(2)]);
+}
@@ -0,0 +1,142 @@
+use molecule_core::{Element, ModelIndex, Molecule, PdbParser};
+
+// First two residues of crambin (1CRN)
+const DIPEPTIDE: &str = "\
+ATOM      1  N   THR A   1      17.047  14.099   3.625  1.00 13.79           N
+ATOM      2  CA  THR A   1      16.967  12.784   4.338  1.00 10.80           C
+ATOM      3  C   THR A   1      15.685  12.755   5.133  1.00  9.19           C
+ATOM      4  O   THR A   1      15.268  13.825   5.594  1.00  9.85           O
+ATOM      5  CB  THR A   1      18.170  12.703   5.337  1.00 13.02           C
+ATOM      6  OG1 THR A   1      19.334  12.829   4.463  1.00 15.06           O
+ATOM      7  CG2 THR A   1      18.150  11.546   6.304  1.00 10.28           C
+ATOM      8  N   THR A   2      15.115  11.555   5.265  1.00  7.81           N
+ATOM      9  CA  THR A   2      13.856  11.469   6.066  1.00  8.31           C
+ATOM     10  C   THR A   2      14.164  10.785   7.379  1.00  5.80           C
+ATOM     11  O   THR A   2      14.993   9.862   7.443  1.00  6.94           O
+ATOM     12  CB  THR A   2      12.732  10.711   5.261  1.00 10.32           C
+ATOM     13  OG1 THR A   2      13.308   9.439   4.926  1.00 12.81           O
+ATOM     14  CG2 THR A   2      11.446  10.623   6.066  1.00 11.90           C
+";
+
+fn names(molecule: &Molecule, residue_id: usize) -> Vec<&str> {
+    molecule
+        .atoms
+        .iter()
+        .filter(|atom| atom.residue_id == residue_id && atom.element == Element::H)
+        .map(|atom| atom.name.as_str())
+        .collect()
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+#[test]
+fn test_add_hydrogens_to_peptide() {
+    let mut molecule = PdbParser::new().parse_string(DIPEPTIDE).unwrap();
+    let heavy_bonds = molecule.bonds.len();
+
+    assert_eq!(molecule.add_hydrogens(7.0), 16);
+    assert_eq!(molecule.atoms.len(), 30);
+    assert_eq!(molecule.bonds.len(), heavy_bonds + 16);
+    assert_eq!(names(&molecule, 1), ["H1", "H2", "H3", "HA", "HB", "HG1", "HG21", "HG22", "HG23"]);
+    assert_eq!(names(&molecule, 2), ["H", "HA", "HB", "HG1", "HG21", "HG22", "HG23"]);
+
+    // Hydrogens follow their residue and serials run in file order
+    assert!(molecule.atoms.iter().enumerate().all(|(i, atom)| atom.id == i + 1));
+    assert_eq!(molecule.atoms[16].name, "N");
+    assert_eq!(molecule.atoms[16].residue_id, 2);
+
+    let n_terminus = molecule.atoms.iter().position(|atom| atom.name == "N" && atom.residue_id == 1).unwrap();
+    assert_eq!(molecule.atoms[n_terminus].formal_charge, 1);
+
+    for bond in &molecule.bonds {
+        let (atom1, atom2) = (&molecule[bond.atom1], &molecule[bond.atom2]);
+        let expected = match (atom1.element, atom2.element) {
+            (Element::H, Element::C) | (Element::C, Element::H) => 1.09,
+            (Element::H, Element::N) | (Element::N, Element::H) => 1.01,
+            (Element::H, Element::O) | (Element::O, Element::H) => 0.96,
+            _ => continue,
+        };
+        assert!((distance(atom1.position, atom2.position) - expected).abs() < 1e-3);
+    }
+
+    // Already complete, so nothing more is added
+    assert_eq!(molecule.add_hydrogens(7.0), 0);
+}
+
+#[test]
+fn test_protonation_follows_ph() {
+    let mut molecule = PdbParser::new().parse_string(DIPEPTIDE).unwrap();
+    assert_eq!(molecule.add_hydrogens(9.0), 15);
+    assert_eq!(names(&molecule, 1)[..2], ["H1", "H2"]);
+
+    let n_terminus = molecule.atoms.iter().position(|atom| atom.name == "N" && atom.residue_id == 1).unwrap();
+    assert_eq!(molecule.atoms[n_terminus].formal_charge, 0);
+}
+
+#[test]
+fn test_water_hydrogens() {
+    let content = "HETATM    1  O   HOH A 101       1.000   2.000   3.000  1.00  0.00           O\n";
+    let mut molecule = PdbParser::new().parse_string(content).unwrap();
+
+    assert_eq!(molecule.add_hydrogens(7.0), 2);
+    assert_eq!(names(&molecule, 101), ["H1", "H2"]);
+    assert!(molecule.atoms[1].is_hetatm);
+
+    let [oxygen, h1, h2] = [0, 1, 2].map(|i| molecule.atoms[i].position);
+    assert!((distance(oxygen, h1) - 0.96).abs() < 1e-3);
+    let angle = ((distance(h1, h2) / 2.0) / 0.96).asin().to_degrees() * 2.0;
+    assert!((angle - 104.5).abs() < 0.5);
+}
+
+#[test]
+fn test_disulfide_cysteines_keep_no_thiol_hydrogen() {
+    let content = "\
+ATOM      1  N   CYS A   3      -0.530   1.370   0.000  1.00  0.00           N
+ATOM      2  CA  CYS A   3       0.000   0.000   0.000  1.00  0.00           C
+ATOM      3  C   CYS A   3      -0.550  -0.730   1.220  1.00  0.00           C
+ATOM      4  O   CYS A   3      -1.100  -0.120   2.130  1.00  0.00           O
+ATOM      5  CB  CYS A   3       1.530   0.000   0.000  1.00  0.00           C
+ATOM      6  SG  CYS A   3       2.150   1.700   0.000  1.00  0.00           S
+ATOM      7  N   CYS B  40       4.999   2.494   1.979  1.00  0.00           N
+ATOM      8  CA  CYS B  40       4.469   3.864   1.979  1.00  0.00           C
+ATOM      9  C   CYS B  40       5.019   4.594   0.759  1.00  0.00           C
+ATOM     10  O   CYS B  40       5.569   3.984  -0.151  1.00  0.00           O
+ATOM     11  CB  CYS B  40       2.939   3.864   1.979  1.00  0.00           C
+ATOM     12  SG  CYS B  40       2.319   2.164   1.979  1.00  0.00           S
+";
+    let mut molecule = PdbParser::new().parse_string(content).unwrap();
+    let bridged = molecule.bonds.iter().any(|bond| {
+        let (atom1, atom2) = (&molecule[bond.atom1], &molecule[bond.atom2]);
+        atom1.name == "SG" && atom2.name == "SG"
+    });
+    assert!(bridged);
+
+    molecule.add_hydrogens(7.0);
+    assert!(!names(&molecule, 3).contains(&"HG"));
+    assert!(!names(&molecule, 40).contains(&"HG"));
+
+    // A free cysteine is still protonated at the same pH
+    let free: String = content.lines().take(6).map(|line| format!("{line}\n")).collect();
+    let mut molecule = PdbParser::new().parse_string(&free).unwrap();
+    molecule.add_hydrogens(7.0);
+    assert!(names(&molecule, 3).contains(&"HG"));
+}
+
+#[test]
+fn test_hydrogens_keep_interleaved_models() {
+    let pdb = format!("MODEL        1\n{}ENDMDL\nMODEL        2\n{}ENDMDL\n", DIPEPTIDE, DIPEPTIDE);
+    let mut molecule = PdbParser::new().parse_string(&pdb).unwrap();
+    molecule.merge(&PdbParser::new().parse_string(&pdb).unwrap()).unwrap();
+    molecule.current_model = Some(ModelIndex(1));
+
+    assert_eq!(molecule.add_hydrogens(7.0), 64);
+    assert_eq!(molecule.models.iter().map(|model| model.id).collect::<Vec<_>>(), vec![1, 2]);
+    assert!(molecule.models.iter().all(|model| model.atoms.len() == 60));
+    assert_eq!(molecule.current_model, Some(ModelIndex(1)));
+
+    // Serials continue through each model's second run
+    let serials: Vec<usize> = molecule[ModelIndex(0)].atoms.iter().map(|&index| molecule[index].id).collect();
+    assert_eq!(serials, (1..=60).collect::<Vec<_>>());
+}
@@ -0,0 +1,140 @@
+use molecule_core::{Atom, Element, MmcifWriter, Molecule, PdbParser};
+
+const COMPLEX_PDB: &str = "\
+HEADER    OXIDOREDUCTASE                          12-MAR-99   1ABC
+TITLE     A SMALL TEST COMPLEX
+CRYST1   50.000   60.000   70.000  90.00  95.00  90.00 P 1 21 1      2
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  C   ALA A   1      13.149   5.994  -5.142  1.00  0.00           C
+ATOM      4  O   ALA A   1      13.766   5.985  -6.208  1.00  0.00           O
+ATOM      5  N   GLY A   2      13.730   5.933  -3.944  1.00  0.00           N
+ATOM      6  CA  GLY A   2      15.175   5.850  -3.830  1.00  0.00           C
+ATOM      7  N   ALA B   1      31.104   6.134  -6.504  1.00  0.00           N
+ATOM      8  CA  ALA B   1      31.639   6.071  -5.147  1.00  0.00           C
+ATOM      9  C   ALA B   1      33.149   5.994  -5.142  1.00  0.00           C
+ATOM     10  O   ALA B   1      33.766   5.985  -6.208  1.00  0.00           O
+ATOM     11  N   GLY B   2      33.730   5.933  -3.944  1.00  0.00           N
+ATOM     12  CA  GLY B   2      35.175   5.850  -3.830  1.00  0.00           C
+HETATM   13 ZN    ZN A 101      13.000   8.000  -4.000  1.00 20.00          ZN2+
+HETATM   14  O   HOH A 201      30.000  30.000  30.000  1.00 35.00           O
+HETATM   15  O   HOH B 201      40.000  30.000  30.000  1.00 35.00           O
+CONECT   13    4
+END
+";
+
+fn loop_rows<'a>(cif: &'a str, category: &str) -> Vec<Vec<&'a str>> {
+    let lines: Vec<&str> = cif.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with(category))
+        .expect("category present");
+    lines[start..]
+        .iter()
+        .skip_while(|line| line.starts_with('_'))
+        .take_while(|line| !line.starts_with('#'))
+        .map(|line| line.split_whitespace().collect())
+        .collect()
+}
+
+#[test]
+fn test_mmcif_entities_and_atom_site() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let cif = MmcifWriter::new().write_string(&molecule).unwrap();
+
+    assert!(cif.starts_with("data_1ABC\n"));
+    assert!(cif.contains("_struct.title 'A SMALL TEST COMPLEX'"));
+    assert!(cif.contains("_symmetry.space_group_name_H-M 'P 1 21 1'"));
+    assert!(cif.contains("_cell.length_a 50.000"));
+
+    // Both chains share one polymer entity; the zinc and the waters get their own
+    let entities = loop_rows(&cif, "_entity.");
+    assert_eq!(
+        entities,
+        vec![vec!["1", "polymer", "?"], vec!["2", "non-polymer", "ZN"], vec!["3", "water", "HOH"]]
+    );
+
+    let atoms = loop_rows(&cif, "_atom_site.");
+    assert_eq!(atoms.len(), 15);
+    assert_eq!(
+        atoms[4],
+        vec![
+            "ATOM", "5", "N", "N", ".", "GLY", "A", "1", "2", "?", "13.730", "5.933", "-3.944", "1.00", "0.00", "0",
+            "2", "GLY", "A", "N", "1"
+        ]
+    );
+    // Ligands and waters are separate instances with no sequence position
+    assert_eq!(atoms[12][..9], ["HETATM", "13", "ZN", "ZN", ".", "ZN", "C", "2", "."]);
+    assert_eq!(atoms[12][15], "2");
+    assert_eq!(atoms[13][6], "D");
+    assert_eq!(atoms[14][6], "E");
+    assert_eq!(atoms[14][18], "B");
+}
+
+#[test]
+fn test_mmcif_struct_conn() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let cif = MmcifWriter::new().write_string(&molecule).unwrap();
+
+    // Only the zinc coordination; peptide and intra-residue bonds are implied
+    let connections = loop_rows(&cif, "_struct_conn.");
+    assert_eq!(connections.len(), 1);
+    let connection = &connections[0];
+    assert_eq!(connection[0], "metalc1");
+    assert_eq!(connection[1], "metalc");
+    assert!(connection.contains(&"ZN"));
+    assert!(connection.contains(&"ALA"));
+}
+
+#[test]
+fn test_mmcif_quoting_and_models() {
+    let mut molecule = Molecule::new();
+    for model_id in 1..=2 {
+        molecule.start_model(model_id);
+        molecule.add_atom(Atom {
+            id: 1,
+            name: "O5'".to_string(),
+            element: Element::O,
+            residue_id: 1,
+            residue_name: "DA".to_string(),
+            position: [model_id as f32, 0.0, 0.0],
+            ..Atom::default()
+        });
+        molecule.end_model();
+    }
+
+    let cif = MmcifWriter::new().write_string(&molecule).unwrap();
+    assert!(cif.starts_with("data_molecule\n"));
+
+    let atoms = loop_rows(&cif, "_atom_site.");
+    assert_eq!(atoms.len(), 2);
+    assert_eq!(atoms[0][3], "\"O5'\"");
+    // Blank chains fall back to the generated asym id
+    assert_eq!(atoms[0][18], "A");
+    assert_eq!(atoms[0][20], "1");
+    assert_eq!(atoms[1][20], "2");
+    assert_eq!(atoms[1][10], "2.000");
+}
+
+#[test]
+fn test_mmcif_large_structure() {
+    let mut molecule = Molecule::new();
+    for i in 0..12_000 {
+        molecule.add_atom(Atom {
+            id: 100_000 + i,
+            name: "C".to_string(),
+            element: Element::C,
+            residue_id: 10_000 + i,
+            residue_name: "LIG".to_string(),
+            is_hetatm: true,
+            chain_id: 'A',
+            ..Atom::default()
+        });
+    }
+
+    let cif = MmcifWriter::new().write_string(&molecule).unwrap();
+    let atoms = loop_rows(&cif, "_atom_site.");
+    assert_eq!(atoms.len(), 12_000);
+    assert_eq!(atoms[11_999][1], "111999");
+    assert_eq!(atoms[11_999][16], "21999");
+}
@@ -31,15 +31,15 @@ ATOM      5  CB  ALA A   1       0.676   0.095  -2.060  1.00  0.00           C
     for (i, bond) in molecule.bonds.iter().enumerate() {
         println!(
             "Bond {}: atom1={}, atom2={}, order={:?}",
-            i, bond.atom1_id, bond.atom2_id, bond.order
+            i, bond.atom1.0, bond.atom2.0, bond.order
         );
     }
     
     println!("\n=== RESIDUES ===");
-    for (key, residue) in molecule.residues.iter() {
+    for residue in molecule.residues.iter() {
         println!(
             "Residue {}({}): name={}, atoms={:?}",
-            key.0, key.1, residue.name, residue.atoms
+            residue.id, residue.ins_code, residue.name, residue.atoms
         );
     }
     
@@ -72,8 +72,8 @@ ATOM      2  CA  ALA A   1       1.000   0.000   0.000  1.00  0.00           C
     
     if !molecule.bonds.is_empty() {
         println!("Bond found between atoms {} and {}", 
-            molecule.bonds[0].atom1_id, 
-            molecule.bonds[0].atom2_id
+            molecule.bonds[0].atom1.0, 
+            molecule.bonds[0].atom2.0
         );
         
         // Calculate and print the distance
@@ -170,10 +170,10 @@ ATOM      5  N   GLY A   1A      5.000   5.000   5.000  1.00  0.00           N
         );
     }
     
-    for ((res_id, ins_code), residue) in molecule.residues.iter() {
+    for residue in molecule.residues.iter() {
         println!(
-            "Residue {}({}): name={}, atoms={:?}, ins_code={}",
-            res_id, ins_code, residue.name, residue.atoms, residue.ins_code
+            "Residue {}({}): name={}, atoms={:?}",
+            residue.id, residue.ins_code, residue.name, residue.atoms
         );
     }
     
@@ -0,0 +1,136 @@
+use molecule_core::{Atom, BondOrder, Element, Molecule, PdbParser};
+
+fn molecule(atoms: &[(Element, [f32; 3])]) -> Molecule {
+    let mut molecule = Molecule::new();
+    for (i, &(element, position)) in atoms.iter().enumerate() {
+        molecule.add_atom(Atom {
+            id: i + 1,
+            name: format!("{}{}", element.symbol(), i + 1),
+            element,
+            position,
+            residue_name: "LIG".to_string(),
+            is_hetatm: true,
+            ..Atom::default()
+        });
+    }
+    molecule.calculate_bonds();
+    molecule.perceive_bond_orders();
+    molecule
+}
+
+// Regular hexagon in the xy plane, with `pucker` Å alternating along z
+fn ring(elements: [Element; 6], pucker: f32) -> Vec<(Element, [f32; 3])> {
+    (0..6)
+        .map(|i| {
+            let angle = (i as f32 * 60.0).to_radians();
+            let z = if i % 2 == 0 { pucker } else { -pucker };
+            (elements[i], [1.39 * angle.cos(), 1.39 * angle.sin(), z])
+        })
+        .collect()
+}
+
+fn count(molecule: &Molecule, order: BondOrder) -> usize {
+    molecule.bonds.iter().filter(|bond| bond.order == order).count()
+}
+
+#[test]
+fn test_aromatic_rings() {
+    use Element::{C, N};
+
+    let benzene = molecule(&ring([C; 6], 0.0));
+    assert_eq!(count(&benzene, BondOrder::Aromatic), 6);
+
+    let pyridine = molecule(&ring([N, C, C, C, C, C], 0.0));
+    assert_eq!(count(&pyridine, BondOrder::Aromatic), 6);
+
+    // A puckered ring of sp3 carbons stays saturated
+    let mut cyclohexane = ring([C; 6], 0.25);
+    for atom in &mut cyclohexane {
+        atom.1 = [atom.1[0] * 1.1, atom.1[1] * 1.1, atom.1[2]];
+    }
+    let cyclohexane = molecule(&cyclohexane);
+    assert_eq!(cyclohexane.bonds.len(), 6);
+    assert_eq!(count(&cyclohexane, BondOrder::Single), 6);
+}
+
+#[test]
+fn test_chain_multiple_bonds_from_geometry() {
+    use Element::{C, N, O};
+
+    // Acetonitrile: linear C-C#N
+    let nitrile = molecule(&[(C, [0.0, 0.0, 0.0]), (C, [1.46, 0.0, 0.0]), (N, [2.62, 0.0, 0.0])]);
+    assert_eq!(nitrile.bonds.iter().map(|bond| bond.order).collect::<Vec<_>>(), vec![BondOrder::Single, BondOrder::Triple]);
+
+    // Acetone: trigonal carbonyl carbon with C=O
+    let acetone = molecule(&[
+        (C, [0.0, 0.0, 0.0]),
+        (O, [0.0, 1.21, 0.0]),
+        (C, [1.31, -0.76, 0.0]),
+        (C, [-1.31, -0.76, 0.0]),
+    ]);
+    assert_eq!(count(&acetone, BondOrder::Double), 1);
+    assert_eq!(count(&acetone, BondOrder::Single), 2);
+    assert!(acetone.bonds.iter().any(|bond| bond.order == BondOrder::Double
+        && [bond.atom1.0, bond.atom2.0].contains(&1)));
+}
+
+#[test]
+fn test_huckel_rejects_antiaromatic_and_keeps_carbonyls() {
+    use Element::{C, N, O};
+
+    // Uracil-like ring: two exocyclic C=O, still 6 π electrons
+    let mut uracil = ring([N, C, N, C, C, C], 0.0);
+    uracil.push((O, [2.60 * 60f32.to_radians().cos(), 2.60 * 60f32.to_radians().sin(), 0.0]));
+    uracil.push((O, [2.60 * 180f32.to_radians().cos(), 2.60 * 180f32.to_radians().sin(), 0.0]));
+    // Give the ring nitrogens their hydrogens, so they donate lone pairs
+    uracil.push((Element::H, [2.40, 0.0, 0.0]));
+    uracil.push((Element::H, [-1.20, 2.08, 0.0]));
+    let uracil = molecule(&uracil);
+    assert_eq!(count(&uracil, BondOrder::Aromatic), 6);
+    assert_eq!(count(&uracil, BondOrder::Double), 2);
+
+    // Planar 1,4-benzoquinone: 4 π electrons in the ring, so not aromatic
+    let mut quinone = ring([C; 6], 0.0);
+    quinone.push((O, [2.60, 0.0, 0.0]));
+    quinone.push((O, [-2.60, 0.0, 0.0]));
+    let quinone = molecule(&quinone);
+    assert_eq!(count(&quinone, BondOrder::Aromatic), 0);
+    assert_eq!(count(&quinone, BondOrder::Double), 4);
+}
+
+#[test]
+fn test_truncated_aromatic_residue_does_not_block_ligands() {
+    // PHE without CZ cannot be kekulized; the benzene ligand must not care
+    let mut pdb = String::from("\
+ATOM      1  N   PHE A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  PHE A   1       1.458   0.000   0.000  1.00  0.00           C
+ATOM      3  C   PHE A   1       2.009   1.420   0.000  1.00  0.00           C
+ATOM      4  O   PHE A   1       1.251   2.390   0.000  1.00  0.00           O
+ATOM      5  CB  PHE A   1       1.988  -0.773  -1.199  1.00  0.00           C
+ATOM      6  CG  PHE A   1       3.486  -0.847  -1.280  1.00  0.00           C
+ATOM      7  CD1 PHE A   1       4.196   0.144  -1.951  1.00  0.00           C
+ATOM      8  CD2 PHE A   1       4.184  -1.910  -0.713  1.00  0.00           C
+ATOM      9  CE1 PHE A   1       5.583   0.072  -2.031  1.00  0.00           C
+ATOM     10  CE2 PHE A   1       5.568  -1.987  -0.788  1.00  0.00           C
+");
+    for (i, (_, [x, y, z])) in ring([Element::C; 6], 0.0).into_iter().enumerate() {
+        pdb.push_str(&format!(
+            "HETATM{:>5}  C{}  BNZ B 101    {:>8.3}{:>8.3}{:>8.3}  1.00  0.00           C\n",
+            11 + i,
+            i + 1,
+            x + 20.0,
+            y,
+            z
+        ));
+    }
+    let mut molecule = PdbParser::new().parse_string(&pdb).unwrap();
+    molecule.perceive_bond_orders();
+
+    let ligand_orders: Vec<BondOrder> = molecule
+        .bonds
+        .iter()
+        .filter(|bond| bond.atom1.0 >= 10 && bond.atom2.0 >= 10)
+        .map(|bond| bond.order)
+        .collect();
+    assert_eq!(ligand_orders, vec![BondOrder::Aromatic; 6]);
+}
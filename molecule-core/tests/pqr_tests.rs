@@ -0,0 +1,94 @@
+use molecule_core::{Element, PqrParser, PqrWriter};
+
+#[test]
+fn test_parse_pqr_charges_and_radii() {
+    let pqr_content = "\
+REMARK   1 PQR file generated by PDB2PQR
+ATOM      1  N   ALA A   1      -0.677  -1.230  -0.491 -0.3000 1.8240
+ATOM      2  CA  ALA A   1       0.381  -0.207  -0.608  0.0337 1.9080
+ATOM      3  C   ALA A   1       1.594  -0.775   0.115  0.5973 1.9080
+ATOM      4  O   ALA A   1       1.592  -1.926   0.545 -0.5679 1.6612
+HETATM    5 ZN    ZN B   2      10.000  10.000  10.000  2.0000 1.1000
+END
+";
+
+    let parser = PqrParser::new();
+    let molecule = parser.parse_string(pqr_content).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 5);
+
+    let nitrogen = &molecule.atoms[0];
+    assert_eq!(nitrogen.name, "N");
+    assert_eq!(nitrogen.residue_name, "ALA");
+    assert_eq!(nitrogen.chain_id, 'A');
+    assert_eq!(nitrogen.charge, Some(-0.3));
+    assert_eq!(nitrogen.radius, Some(1.824));
+    assert_eq!(nitrogen.occupancy, 1.0);
+
+    let zinc = &molecule.atoms[4];
+    assert!(zinc.is_hetatm);
+    assert_eq!(zinc.charge, Some(2.0));
+    assert_eq!(zinc.radius, Some(1.1));
+    assert_eq!(zinc.element, Element::Zn);
+}
+
+#[test]
+fn test_parse_pqr_ion_elements() {
+    // Element columns when present, otherwise ions named after their residue
+    let pqr_content = "\
+HETATM    1 ZN    ZN A 101      10.000  10.000  10.000  2.0000 1.1000
+HETATM    2 CL    CL A 102      12.000  10.000  10.000 -1.0000 1.9370
+HETATM    3 CA    CA A 103      14.000  10.000  10.000  2.0000 1.7130
+ATOM      4  CA  ALA A   1       0.381  -0.207  -0.608  0.0337 1.9080
+HETATM    5 NA   SOD A 104      16.000  10.000  10.000  1.0000 1.3690       NA
+HETATM 6 CL CL 105 18.000 10.000 10.000 -1.0000 1.9370
+";
+
+    let molecule = PqrParser::new().parse_string(pqr_content).unwrap();
+    let elements: Vec<Element> = molecule.atoms.iter().map(|atom| atom.element).collect();
+    assert_eq!(elements, [Element::Zn, Element::Cl, Element::Ca, Element::C, Element::Na, Element::Cl]);
+}
+
+#[test]
+fn test_parse_pqr_free_format() {
+    let pqr_content = "\
+ATOM 1 N ALA 1 -0.677 -1.230 -0.491 -0.3000 1.8240
+ATOM 2 CA ALA B 1 0.381 -0.207 -0.608 0.0337 1.9080
+";
+
+    let parser = PqrParser::new();
+    let molecule = parser.parse_string(pqr_content).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 2);
+    assert_eq!(molecule.atoms[0].chain_id, ' ');
+    assert_eq!(molecule.atoms[0].element, Element::N);
+    assert_eq!(molecule.atoms[1].chain_id, 'B');
+    assert_eq!(molecule.atoms[1].position, [0.381, -0.207, -0.608]);
+    assert_eq!(molecule.atoms[1].radius, Some(1.908));
+}
+
+#[test]
+fn test_pqr_round_trip() {
+    let pqr_content = "\
+ATOM      1  N   ALA A   1      -0.677  -1.230  -0.491 -0.3000 1.8240
+ATOM      2  CA  ALA A   1       0.381  -0.207  -0.608  0.0337 1.9080
+ATOM      3  N   GLY B   1A      5.000   5.000   5.000 -0.4157 1.8240
+";
+
+    let molecule = PqrParser::new().parse_string(pqr_content).unwrap();
+    let written = PqrWriter::new().write_string(&molecule).unwrap();
+
+    assert!(written.starts_with("ATOM      1  N   ALA A   1      -0.677  -1.230  -0.491 -0.3000 1.8240\n"));
+    assert_eq!(written.matches("TER").count(), 2);
+
+    let reparsed = PqrParser::new().parse_string(&written).unwrap();
+    assert_eq!(reparsed.atoms.len(), molecule.atoms.len());
+    for (original, copy) in molecule.atoms.iter().zip(&reparsed.atoms) {
+        assert_eq!(original.name, copy.name);
+        assert_eq!(original.chain_id, copy.chain_id);
+        assert_eq!(original.ins_code, copy.ins_code);
+        assert_eq!(original.position, copy.position);
+        assert_eq!(original.charge, copy.charge);
+        assert_eq!(original.radius, copy.radius);
+    }
+}
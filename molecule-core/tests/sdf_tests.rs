@@ -0,0 +1,105 @@
+use molecule_core::{Atom, AtomIndex, BondOrder, Element, Molecule, SdfWriter};
+
+fn ring(elements: &[Element]) -> Molecule {
+    let mut molecule = Molecule::new();
+    for (i, &element) in elements.iter().enumerate() {
+        let angle = i as f32 * std::f32::consts::TAU / elements.len() as f32;
+        molecule.add_atom(Atom {
+            id: i + 1,
+            name: format!("{}{}", element.symbol(), i + 1),
+            element,
+            position: [1.4 * angle.cos(), 1.4 * angle.sin(), 0.0],
+            residue_name: "LIG".to_string(),
+            is_hetatm: true,
+            ..Atom::default()
+        });
+    }
+    for i in 0..elements.len() {
+        molecule.add_bond(AtomIndex(i), AtomIndex((i + 1) % elements.len()), BondOrder::Aromatic);
+    }
+    molecule
+}
+
+// (atom1, atom2, order) for every line of the bond block
+fn bond_block(record: &str) -> Vec<(usize, usize, usize)> {
+    let lines: Vec<&str> = record.lines().collect();
+    let atom_count: usize = lines[3][0..3].trim().parse().unwrap();
+    let bond_count: usize = lines[3][3..6].trim().parse().unwrap();
+    lines[4 + atom_count..4 + atom_count + bond_count]
+        .iter()
+        .map(|line| {
+            (
+                line[0..3].trim().parse().unwrap(),
+                line[3..6].trim().parse().unwrap(),
+                line[6..9].trim().parse().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_sdf_kekulizes_benzene() {
+    let benzene = ring(&[Element::C; 6]);
+    let sdf = SdfWriter::new().write_string(&benzene).unwrap();
+
+    let lines: Vec<&str> = sdf.lines().collect();
+    assert_eq!(lines[3], "  6  6  0  0  0  0  0  0  0  0999 V2000");
+    assert_eq!(lines[4].len(), 69);
+    assert_eq!(&lines[4][31..34], "C  ");
+
+    let bonds = bond_block(&sdf);
+    assert_eq!(bonds.iter().filter(|bond| bond.2 == 2).count(), 3);
+    assert!(bonds.iter().all(|bond| bond.2 == 1 || bond.2 == 2));
+    // Every atom carries exactly one double bond
+    for atom in 1..=6 {
+        let doubles = bonds.iter().filter(|bond| bond.2 == 2 && (bond.0 == atom || bond.1 == atom)).count();
+        assert_eq!(doubles, 1);
+    }
+    assert!(sdf.ends_with("M  END\n$$$$\n"));
+}
+
+#[test]
+fn test_sdf_kekulizes_pyrrole() {
+    let pyrrole = ring(&[Element::N, Element::C, Element::C, Element::C, Element::C]);
+    let sdf = SdfWriter::new().write_string(&pyrrole).unwrap();
+
+    // The nitrogen donates its lone pair, so both double bonds are C=C
+    let bonds = bond_block(&sdf);
+    let doubles: Vec<_> = bonds.iter().filter(|bond| bond.2 == 2).collect();
+    assert_eq!(doubles.len(), 2);
+    assert!(doubles.iter().all(|bond| bond.0 != 1 && bond.1 != 1));
+}
+
+#[test]
+fn test_sdf_charges_and_data_tags() {
+    let mut molecule = Molecule::new();
+    let nitrogen = molecule.add_atom(Atom { id: 10, name: "N1".to_string(), element: Element::N, formal_charge: 1, ..Atom::default() });
+    let oxygen = molecule.add_atom(Atom { id: 11, name: "O1".to_string(), element: Element::O, formal_charge: -1, ..Atom::default() });
+    molecule.add_bond(nitrogen, oxygen, BondOrder::Single);
+    molecule.metadata.title = Some("zwitterion".to_string());
+    molecule.metadata.properties.push(("ID".to_string(), "LIG-1".to_string()));
+    molecule.metadata.properties.push(("Score".to_string(), "-7.5".to_string()));
+
+    let sdf = SdfWriter::new().write_string(&molecule).unwrap();
+    let lines: Vec<&str> = sdf.lines().collect();
+    assert_eq!(lines[0], "zwitterion");
+    assert_eq!(&lines[4][36..39], "  3");
+    assert_eq!(&lines[5][36..39], "  5");
+    assert_eq!(lines[6], "  1  2  1  0");
+    assert_eq!(lines[7], "M  CHG  2   1   1   2  -1");
+    assert_eq!(lines[8..], ["M  END", ">  <ID>", "LIG-1", "", ">  <Score>", "-7.5", "", "$$$$"]);
+}
+
+#[test]
+fn test_sdf_multiple_records() {
+    let molecules = vec![ring(&[Element::C; 6]), ring(&[Element::N, Element::C, Element::C, Element::C, Element::C])];
+
+    let mut buffer = Vec::new();
+    SdfWriter::new().write_all(&molecules, &mut buffer).unwrap();
+    let sdf = String::from_utf8(buffer).unwrap();
+    assert_eq!(sdf.matches("$$$$\n").count(), 2);
+
+    let mut broken = ring(&[Element::C; 6]);
+    broken.add_bond(AtomIndex(0), AtomIndex(99), BondOrder::Single);
+    assert!(SdfWriter::new().write_string(&broken).is_err());
+}
@@ -0,0 +1,93 @@
+use std::io::ErrorKind;
+
+use molecule_core::{Atom, AtomIndex, Molecule, PdbParser, Selection};
+
+const COMPLEX_PDB: &str = "\
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  C   ALA A   1      13.149   5.994  -5.142  0.50 12.30           C
+ATOM      4  O   ALA A   1      13.766   5.985  -6.208  1.00  0.00           O
+ATOM      5 1HB  ALA A   1      11.400   7.500  -4.500  1.00  0.00           H
+ATOM      6  N   GLY B   2      13.730   5.933  -3.944  1.00  0.00           N
+ATOM      7  CA  GLY B   2      15.175   5.850  -3.830  1.00  0.00           C
+HETATM    8 FE   HEM B 101      20.000  10.000   0.000  1.00 20.00          FE
+HETATM    9  NA  HEM B 101      21.900  10.000   0.000  1.00 20.00           N
+HETATM   10  O   HOH B 201      30.000  30.000  30.000  1.00 35.00           O
+END
+";
+
+fn serials(molecule: &molecule_core::Molecule, expression: &str) -> Vec<usize> {
+    molecule.select(expression).unwrap().into_iter().map(|index| molecule[index].id).collect()
+}
+
+#[test]
+fn test_boolean_logic_and_properties() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+
+    assert_eq!(serials(&molecule, "chain A and resi 1-5 and not hydrogen"), vec![1, 2, 3, 4]);
+    assert_eq!(serials(&molecule, "name CA or (hetatm and element Fe)"), vec![2, 7, 8]);
+    assert_eq!(serials(&molecule, "resn H* and not resn HOH"), vec![8, 9]);
+    assert_eq!(serials(&molecule, "b>=20 and occupancy = 1"), vec![8, 9, 10]);
+    assert_eq!(serials(&molecule, "occupancy < 1"), vec![3]);
+    assert_eq!(serials(&molecule, "serial 6:7 9"), vec![6, 7, 9]);
+    assert_eq!(molecule.select("all").unwrap().len(), 10);
+    assert!(molecule.select("none").unwrap().is_empty());
+}
+
+#[test]
+fn test_distance_selections() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+
+    assert_eq!(serials(&molecule, "within 2 of name FE"), vec![8, 9]);
+    assert_eq!(serials(&molecule, "around 2 of name FE"), vec![9]);
+    assert_eq!(serials(&molecule, "within 8 of resn HEM and chain B and not hetatm"), vec![7]);
+
+    let selection = Selection::parse("around 100 of resn HOH").unwrap();
+    assert_eq!(selection.evaluate(&molecule).first(), Some(&AtomIndex(0)));
+    assert_eq!(selection.evaluate(&molecule).len(), 9);
+}
+
+#[test]
+fn test_distance_selections_match_brute_force() {
+    // A lattice straddling the origin, so cells on both sides of zero are used
+    let mut molecule = Molecule::new();
+    for i in 0..343 {
+        let position = [(i % 7) as f32 * 1.7 - 5.0, (i / 7 % 7) as f32 * 1.3 - 4.0, (i / 49) as f32 * 2.1 - 6.0];
+        let residue_name = if i % 50 == 0 { "LIG" } else { "HOH" };
+        molecule.add_atom(Atom { id: i + 1, name: "O".to_string(), residue_id: i + 1, residue_name: residue_name.to_string(), position, ..Atom::default() });
+    }
+
+    for cutoff in [0.0, 1.3, 2.5, 4.0] {
+        let centers: Vec<[f32; 3]> = molecule.atoms.iter().filter(|atom| atom.residue_name == "LIG").map(|atom| atom.position).collect();
+        let expected: Vec<AtomIndex> = (0..molecule.atoms.len())
+            .filter(|&i| {
+                let position = molecule.atoms[i].position;
+                centers.iter().any(|center| {
+                    (0..3).map(|d| (position[d] - center[d]).powi(2)).sum::<f32>() <= cutoff * cutoff
+                })
+            })
+            .map(AtomIndex)
+            .collect();
+        assert_eq!(molecule.select(&format!("within {} of resn LIG", cutoff)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_distance_selections_stay_in_their_model() {
+    // Model 2 repeats model 1 in place, but with the heme renamed
+    let atoms = COMPLEX_PDB.replace("END\n", "");
+    let pdb = format!("MODEL        1\n{}ENDMDL\nMODEL        2\n{}ENDMDL\n", atoms, atoms.replace("HEM", "LIG"));
+    let molecule = PdbParser::new().parse_string(&pdb).unwrap();
+
+    let selected = molecule.select("within 2 of resn HEM").unwrap();
+    assert_eq!(selected, vec![AtomIndex(7), AtomIndex(8)]);
+    assert_eq!(molecule.select("around 8 of resn LIG").unwrap(), vec![AtomIndex(16)]);
+}
+
+#[test]
+fn test_invalid_selections_are_rejected() {
+    for expression in ["", "chain", "name CA and", "(chain A", "resi A-B", "b ~ 3", "within x of all", "within -1 of all", "around NaN of all", "chain AB", "bogus"] {
+        let err = Selection::parse(expression).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{expression}");
+    }
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "serde")]
+
+use molecule_core::{AtomIndex, Element, Molecule, PdbParser, ResidueIndex};
+
+const PDB: &str = "\
+HEADER    TRANSPORT PROTEIN                       01-JAN-00   1XYZ
+CRYST1   20.000   20.000   20.000  90.00  90.00  90.00 P 1           1
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  C   ALA A   1A     13.149   5.994  -5.142  1.00  0.00           C
+HETATM    4 ZN    ZN B 101      20.000  10.000   0.000  1.00 20.00          ZN2+
+END
+";
+
+#[test]
+fn test_element_serializes_as_symbol() {
+    assert_eq!(serde_json::to_string(&Element::Zn).unwrap(), "\"Zn\"");
+    assert_eq!(serde_json::to_string(&Element::Unknown).unwrap(), "\"X\"");
+    assert_eq!(serde_json::from_str::<Element>("\"Cl\"").unwrap(), Element::Cl);
+    assert_eq!(serde_json::from_str::<Element>("\"Xx\"").unwrap(), Element::Unknown);
+}
+
+#[test]
+fn test_molecule_json_round_trip() {
+    let molecule = PdbParser::new().parse_string(PDB).unwrap();
+
+    let json = serde_json::to_string(&molecule).unwrap();
+    let copy: Molecule = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(copy.atoms.len(), molecule.atoms.len());
+    for (a, b) in molecule.atoms.iter().zip(&copy.atoms) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.element, b.element);
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.chain_id, b.chain_id);
+        assert_eq!(a.ins_code, b.ins_code);
+        assert_eq!(a.formal_charge, b.formal_charge);
+    }
+    assert_eq!(copy.bonds.len(), molecule.bonds.len());
+    assert_eq!(copy.residues.len(), molecule.residues.len());
+    assert_eq!(copy.residues[1].ins_code, 'A');
+    assert_eq!(copy.residues[1].atoms, vec![AtomIndex(2)]);
+    assert_eq!(copy.chains[1].id, 'B');
+    assert_eq!(copy.chains[1].residues, vec![ResidueIndex(2)]);
+    assert_eq!(copy.bonds[0].atom1, molecule.bonds[0].atom1);
+    assert_eq!(copy.unit_cell, molecule.unit_cell);
+    assert_eq!(copy.metadata, molecule.metadata);
+}
+
+#[test]
+fn test_atom_json_fields() {
+    let molecule = PdbParser::new().parse_string(PDB).unwrap();
+    let value = serde_json::to_value(&molecule.atoms[3]).unwrap();
+
+    assert_eq!(value["element"], "Zn");
+    assert_eq!(value["chain_id"], "B");
+    assert_eq!(value["is_hetatm"], true);
+    assert_eq!(value["formal_charge"], 2);
+}
@@ -0,0 +1,96 @@
+use std::io::ErrorKind;
+
+use molecule_core::{AtomIndex, Molecule, PdbParser, SmartsPattern, SmilesParser};
+
+// Threonines 1 and 2 of crambin (1CRN), heavy atoms only
+const DIPEPTIDE: &str = "\
+ATOM      1  N   THR A   1      17.047  14.099   3.625  1.00 13.79           N
+ATOM      2  CA  THR A   1      16.967  12.784   4.338  1.00 10.80           C
+ATOM      3  C   THR A   1      15.685  12.755   5.133  1.00  9.19           C
+ATOM      4  O   THR A   1      15.268  13.825   5.594  1.00  9.85           O
+ATOM      5  CB  THR A   1      18.170  12.703   5.337  1.00 13.02           C
+ATOM      6  OG1 THR A   1      19.334  12.829   4.463  1.00 15.06           O
+ATOM      7  CG2 THR A   1      18.150  11.546   6.304  1.00 10.28           C
+ATOM      8  N   THR A   2      15.115  11.555   5.265  1.00  7.81           N
+ATOM      9  CA  THR A   2      13.856  11.469   6.066  1.00  8.31           C
+ATOM     10  C   THR A   2      14.164  10.785   7.379  1.00  5.80           C
+ATOM     11  O   THR A   2      14.993   9.862   7.443  1.00  6.94           O
+ATOM     12  CB  THR A   2      12.732  10.711   5.261  1.00 10.32           C
+ATOM     13  OG1 THR A   2      13.308   9.439   4.926  1.00 12.81           O
+ATOM     14  CG2 THR A   2      11.446  10.623   6.066  1.00 11.90           C
+";
+
+fn parse(smiles: &str) -> Molecule {
+    SmilesParser::new().parse_string(smiles).unwrap()
+}
+
+fn count(smiles: &str, smarts: &str) -> usize {
+    SmartsPattern::parse(smarts).unwrap().unique_matches(&parse(smiles)).len()
+}
+
+#[test]
+fn test_functional_groups_in_ligands() {
+    let carboxyl = "[CX3](=O)[OX1H0-,OX2H1]";
+    assert_eq!(count("CC(=O)O", carboxyl), 1);
+    assert_eq!(count("OC(=O)CCC(=O)[O-]", carboxyl), 2);
+    assert_eq!(count("CC(=O)OC", carboxyl), 0);
+
+    // Catechol, but not resorcinol; Kekulé input is perceived as aromatic
+    let catechol = "c([OH])c[OH]";
+    assert_eq!(count("Oc1ccccc1O", catechol), 1);
+    assert_eq!(count("OC1=CC=CC=C1O", catechol), 1);
+    assert_eq!(count("Oc1cccc(O)c1", catechol), 0);
+
+    // Recursive SMARTS keeps amide nitrogens out
+    let amine = "[NX3;H2,H1;!$(NC=O)]";
+    assert_eq!(count("NCCC(=O)NC", amine), 1);
+
+    // Mappings follow pattern atom order
+    let acid = parse("CC(=O)O");
+    let matches = SmartsPattern::parse("O=C[OH]").unwrap().matches(&acid);
+    assert_eq!(matches, [vec![AtomIndex(2), AtomIndex(1), AtomIndex(3)]]);
+}
+
+#[test]
+fn test_rings_logic_and_symmetry() {
+    assert_eq!(count("C1CC1CC", "[R]"), 3);
+    assert_eq!(count("C1CC1CC", "[C;R0]"), 2);
+    assert_eq!(count("C1CC1CC", "[r3]"), 3);
+    assert_eq!(count("c1ccc2ccccc2c1", "[cx3]"), 2);
+    assert_eq!(count("C1CC1CC", "[#6]!@[#6]"), 2);
+    assert_eq!(count("CCO.[Na+]", "[#6,#11;!+0,D1]"), 2);
+    assert_eq!(count("[13CH4]", "[13C]"), 1);
+    // An isotope before a lone H is deuterium or tritium, not a hydrogen count
+    assert_eq!(count("[2H]OC", "[2H]O"), 1);
+    assert_eq!(count("[2H]OC.[3H+]", "[3H+]"), 1);
+    assert_eq!(count("[2H]OC.[3H+]", "[2H+]"), 0);
+
+    // Benzene maps onto itself twelve ways, covering one set of atoms
+    let benzene = parse("c1ccccc1");
+    let pattern = SmartsPattern::parse("c1ccccc1").unwrap();
+    assert_eq!(pattern.len(), 6);
+    assert_eq!(pattern.matches(&benzene).len(), 12);
+    assert_eq!(pattern.unique_matches(&benzene).len(), 1);
+}
+
+#[test]
+fn test_search_protein_without_hydrogens() {
+    let molecule = PdbParser::new().parse_string(DIPEPTIDE).unwrap();
+
+    // Hydrogen counts come from valences when the model has no hydrogens
+    let hydroxyls = molecule.find_smarts("[CX4][OX2H1]").unwrap();
+    let names: Vec<(&str, usize)> =
+        hydroxyls.iter().map(|mapping| (molecule[mapping[1]].name.as_str(), molecule[mapping[1]].residue_id)).collect();
+    assert_eq!(names, [("OG1", 1), ("OG1", 2)]);
+
+    assert_eq!(molecule.find_smarts("[NX3H2]").unwrap(), [vec![AtomIndex(0)]]);
+    assert_eq!(molecule.find_smarts("C(=O)N").unwrap().len(), 1);
+}
+
+#[test]
+fn test_invalid_smarts() {
+    for smarts in ["", "C(", "C)", "C1CC", "[C", "C=", "[Zz]", "$(C)", "C.", "[$(C]", "C%1"] {
+        let result = SmartsPattern::parse(smarts);
+        assert_eq!(result.map(|_| ()).unwrap_err().kind(), ErrorKind::InvalidInput, "{} should not parse", smarts);
+    }
+}
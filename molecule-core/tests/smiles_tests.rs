@@ -0,0 +1,100 @@
+use molecule_core::{BondDirection, BondOrder, Chirality, Element, Molecule, SmilesParser};
+
+fn parse(smiles: &str) -> Molecule {
+    SmilesParser::new().parse_string(smiles).unwrap()
+}
+
+fn count(molecule: &Molecule, element: Element) -> usize {
+    molecule.atoms.iter().filter(|atom| atom.element == element).count()
+}
+
+fn orders(molecule: &Molecule, order: BondOrder) -> usize {
+    molecule.bonds.iter().filter(|bond| bond.order == order).count()
+}
+
+#[test]
+fn test_parse_smiles_graph() {
+    let acetic_acid = parse("CC(=O)O acetic acid");
+    assert_eq!(acetic_acid.atoms.len(), 8);
+    assert_eq!(acetic_acid.bonds.len(), 7);
+    assert_eq!(count(&acetic_acid, Element::H), 4);
+    assert_eq!(orders(&acetic_acid, BondOrder::Double), 1);
+    assert_eq!(acetic_acid.metadata.title.as_deref(), Some("acetic acid"));
+
+    // Heavy atoms keep their SMILES order, hydrogens come after
+    let names: Vec<&str> = acetic_acid.atoms.iter().map(|atom| atom.name.as_str()).collect();
+    assert_eq!(names, ["C1", "C2", "O1", "O2", "H1", "H2", "H3", "H4"]);
+    assert!(acetic_acid.atoms.iter().all(|atom| atom.residue_name == "LIG" && atom.position == [0.0; 3]));
+
+    // Two-digit ring closures and branches
+    let cyclohexane = parse("C%10CCCCC%10");
+    assert_eq!(count(&cyclohexane, Element::H), 12);
+    assert_eq!(cyclohexane.graph().rings().len(), 1);
+
+    let ethyne = parse("C#C.Cl");
+    assert_eq!(orders(&ethyne, BondOrder::Triple), 1);
+    assert_eq!(ethyne.graph().fragments().len(), 2);
+}
+
+#[test]
+fn test_aromatic_charges_and_isotopes() {
+    let benzene = parse("c1ccccc1");
+    assert_eq!(orders(&benzene, BondOrder::Aromatic), 6);
+    assert_eq!(count(&benzene, Element::H), 6);
+
+    let pyrrole = parse("c1cc[nH]c1");
+    assert_eq!(count(&pyrrole, Element::H), 5);
+    let thiophene = parse("c1ccsc1");
+    assert_eq!(count(&thiophene, Element::H), 4);
+
+    // Aromatic rings joined outside a ring are single-bonded
+    let biphenyl = parse("c1ccccc1c1ccccc1");
+    assert_eq!(orders(&biphenyl, BondOrder::Aromatic), 12);
+    assert_eq!(orders(&biphenyl, BondOrder::Single), 1 + 10);
+
+    let acetate = parse("CC(=O)[O-]");
+    assert_eq!(acetate.atoms[3].formal_charge, -1);
+    assert_eq!(count(&acetate, Element::H), 3);
+
+    let ammonium = parse("[NH4+]");
+    assert_eq!(ammonium.atoms[0].formal_charge, 1);
+    assert_eq!(ammonium.atoms.len(), 5);
+
+    let labelled = parse("[13CH4].[Fe+++].[2H][O-2:1]");
+    assert_eq!(labelled.atoms[0].isotope, Some(13));
+    assert_eq!(labelled.atoms[1].formal_charge, 3);
+    assert_eq!(labelled.atoms[2].isotope, Some(2));
+    assert_eq!(labelled.atoms[3].formal_charge, -2);
+    assert_eq!(labelled.atoms.len(), 8);
+}
+
+#[test]
+fn test_stereo_markers() {
+    // The same enantiomer written with two neighbors swapped: chirality is
+    // stored against bond order, which lists the methyl and carboxyl carbons
+    // in opposite order in the two molecules, so the flags differ
+    let first = parse("N[C@@H](C)C(=O)O");
+    let second = parse("N[C@H](C(=O)O)C");
+    assert_eq!(first.atoms[1].chirality, Chirality::Clockwise);
+    assert_eq!(second.atoms[1].chirality, Chirality::CounterClockwise);
+
+    // A ring bond opened on the chiral atom keeps its written position
+    let ring = parse("[C@@H]1(F)CCC1");
+    assert_eq!(ring.atoms[0].chirality, Chirality::CounterClockwise);
+
+    let trans = parse("F/C=C/F");
+    let directions: Vec<BondDirection> = trans.bonds.iter().map(|bond| bond.direction).take(3).collect();
+    assert_eq!(directions, [BondDirection::Up, BondDirection::None, BondDirection::Up]);
+
+    // Directions on a closing digit read from the closing atom
+    let closed = parse("C1=C/F.Cl/1");
+    assert_eq!(closed.bonds[2].direction, BondDirection::Down);
+}
+
+#[test]
+fn test_invalid_smiles() {
+    let parser = SmilesParser::new();
+    for smiles in ["", "C1CC", "C(C", "CC)", "C==C", "[Xx]", "C$C", "[C@SP1](F)(Cl)(Br)I", "(C)", "C11"] {
+        assert!(parser.parse_string(smiles).is_err(), "{} should not parse", smiles);
+    }
+}
@@ -0,0 +1,148 @@
+use molecule_core::{Atom, AtomIndex, BondOrder, Element, Molecule, PdbParser, SmilesParser, SmilesWriter};
+
+// Threonine 1 of crambin (1CRN) with the start of residue 2
+const THREONINE: &str = "\
+ATOM      1  N   THR A   1      17.047  14.099   3.625  1.00 13.79           N
+ATOM      2  CA  THR A   1      16.967  12.784   4.338  1.00 10.80           C
+ATOM      3  C   THR A   1      15.685  12.755   5.133  1.00  9.19           C
+ATOM      4  O   THR A   1      15.268  13.825   5.594  1.00  9.85           O
+ATOM      5  CB  THR A   1      18.170  12.703   5.337  1.00 13.02           C
+ATOM      6  OG1 THR A   1      19.334  12.829   4.463  1.00 15.06           O
+ATOM      7  CG2 THR A   1      18.150  11.546   6.304  1.00 10.28           C
+ATOM      8  N   THR A   2      15.115  11.555   5.265  1.00  7.81           N
+ATOM      9  CA  THR A   2      13.856  11.469   6.066  1.00  8.31           C
+";
+
+fn parse(smiles: &str) -> Molecule {
+    SmilesParser::new().parse_string(smiles).unwrap()
+}
+
+fn canonical(smiles: &str) -> String {
+    SmilesWriter::new().canonical(&parse(smiles)).unwrap()
+}
+
+#[test]
+fn test_canonical_smiles_ignores_input_order() {
+    assert_eq!(canonical("OC(=O)C"), "CC(=O)O");
+    assert_eq!(canonical("CC(O)=O"), canonical("OC(C)=O"));
+    assert_eq!(canonical("[Cl-].[NH4+]"), canonical("[NH4+].[Cl-]"));
+    assert_eq!(canonical("c1ccncc1CCN"), canonical("NCCc1cnccc1"));
+
+    // Kekulé and aromatic forms of the same ring give one string
+    assert_eq!(canonical("C1=CC=CC=C1"), "c1ccccc1");
+    assert_eq!(canonical("C1=CC2=CC=CC=C2C=C1"), canonical("c1ccc2ccccc2c1"));
+    assert_eq!(canonical("C1=CNC=C1"), "c1cc[nH]c1");
+    assert_eq!(canonical("O=C1C=CC=CN1"), canonical("O=c1cccc[nH]1"));
+}
+
+#[test]
+fn test_written_smiles_parses_back() {
+    for smiles in [
+        "CC(=O)O",
+        "c1ccc2ccccc2c1",
+        "[13CH4].[Fe+3]",
+        "C1CCCCCCCCCC1",
+        "OC[C@H]1OC(O)[C@H](O)[C@@H](O)[C@@H]1O",
+        "C[S@](=O)CC",
+        "F/C=C/C=C\\Cl",
+    ] {
+        let written = canonical(smiles);
+        assert_eq!(canonical(&written), written, "{} wrote {}", smiles, written);
+    }
+
+    let mut molecule = parse("CCO");
+    molecule.metadata.title = Some("ethanol".to_string());
+    assert_eq!(SmilesWriter::new().write_string(&molecule).unwrap(), "CCO ethanol\n");
+    assert!(SmilesWriter::new().canonical(&Molecule::new()).is_err());
+}
+
+#[test]
+fn test_stereo_from_tags() {
+    // L-alanine three ways, D-alanine once
+    let alanine = canonical("N[C@@H](C)C(=O)O");
+    assert_eq!(alanine, canonical("N[C@H](C(=O)O)C"));
+    assert_eq!(alanine, canonical("C[C@H](N)C(=O)O"));
+    assert_ne!(alanine, canonical("N[C@H](C)C(=O)O"));
+    assert_eq!(SmilesWriter::new().stereo(false).canonical(&parse("N[C@@H](C)C(=O)O")).unwrap(), "CC(C(=O)O)N");
+
+    assert_eq!(canonical("F/C=C/F"), canonical("F\\C=C\\F"));
+    assert_eq!(canonical("F/C=C\\F"), canonical("C(\\F)=C\\F"));
+    assert_ne!(canonical("F/C=C/F"), canonical("F/C=C\\F"));
+}
+
+#[test]
+fn test_stereo_from_coordinates() {
+    let molecule = PdbParser::new().parse_string(THREONINE).unwrap();
+    let residue: Vec<AtomIndex> = (0..7).map(AtomIndex).collect();
+    let smiles = SmilesWriter::new().with_selection(residue).canonical(&molecule).unwrap();
+
+    // (2S,3R)-threonine with the carboxyl cut to an aldehyde
+    assert_eq!(smiles, canonical("C[C@@H](O)[C@H](N)C=O"));
+    assert_ne!(smiles, canonical("C[C@H](O)[C@H](N)C=O"));
+}
+
+// 1,4-dimethylcyclohexane on a flat ring, the methyls up or down
+fn dimethylcyclohexane(first_up: bool, second_up: bool) -> Molecule {
+    let mut content = String::new();
+    let mut atom = |serial: usize, position: [f32; 3]| {
+        content.push_str(&format!(
+            "HETATM{:>5}  C{:<2} LIG A   1    {:8.3}{:8.3}{:8.3}  1.00  0.00           C\n",
+            serial, serial, position[0], position[1], position[2]
+        ));
+    };
+    for k in 0..6 {
+        let angle = (k as f32 * 60.0).to_radians();
+        atom(k + 1, [1.53 * angle.cos(), 1.53 * angle.sin(), 0.0]);
+    }
+    for (serial, x, up) in [(7, 1.0, first_up), (8, -1.0, second_up)] {
+        let z = if up { 1.3 } else { -1.3 };
+        atom(serial, [x * 2.33, 0.0, z]);
+    }
+    PdbParser::new().parse_string(&content).unwrap()
+}
+
+#[test]
+fn test_ring_cis_trans() {
+    let cis = canonical("C[C@H]1CC[C@@H](C)CC1");
+    let trans = canonical("C[C@H]1CC[C@H](C)CC1");
+    assert_ne!(cis, trans);
+    assert!(cis.contains('@') && trans.contains('@'));
+
+    // Flipping both centers, or starting elsewhere, is the same molecule
+    assert_eq!(cis, canonical("C[C@@H]1CC[C@H](C)CC1"));
+    assert_eq!(trans, canonical("C[C@@H]1CC[C@@H](C)CC1"));
+    assert_eq!(cis, canonical("C1C[C@H](C)CC[C@@H]1C"));
+    assert_eq!(trans, canonical("C1C[C@@H](C)CC[C@@H]1C"));
+    for written in [&cis, &trans] {
+        assert_eq!(&canonical(written), written);
+    }
+
+    let writer = SmilesWriter::new();
+    assert_eq!(writer.canonical(&dimethylcyclohexane(true, true)).unwrap(), cis);
+    assert_eq!(writer.canonical(&dimethylcyclohexane(false, false)).unwrap(), cis);
+    assert_eq!(writer.canonical(&dimethylcyclohexane(true, false)).unwrap(), trans);
+    assert_eq!(writer.canonical(&dimethylcyclohexane(false, true)).unwrap(), trans);
+}
+
+#[test]
+fn test_ring_bond_numbers_run_out_past_99() {
+    // Every atom bonded to every other, so ring bonds pile up along the walk
+    let complete = |count: usize| {
+        let mut molecule = Molecule::new();
+        let atoms: Vec<AtomIndex> = (0..count)
+            .map(|_| molecule.add_atom(Atom { element: Element::Unknown, ..Atom::default() }))
+            .collect();
+        for (i, &atom1) in atoms.iter().enumerate() {
+            for &atom2 in &atoms[i + 1..] {
+                molecule.add_bond(atom1, atom2, BondOrder::Single);
+            }
+        }
+        molecule
+    };
+
+    let written = SmilesWriter::new().canonical(&complete(12)).unwrap();
+    assert!(written.contains("%1"));
+    assert_eq!(parse(&written).bonds.len(), 66);
+
+    assert!(SmilesWriter::new().canonical(&complete(60)).is_err());
+}
@@ -0,0 +1,90 @@
+use molecule_core::{residue_template, BondOrder, Molecule, PdbParser, ResidueKind};
+
+// Atoms far apart unless placed explicitly, so only template bonds and
+// links can connect them
+fn pdb(atoms: &[(&str, &str, usize, [f32; 3])]) -> String {
+    let mut content = String::new();
+    for (serial, (name, residue, residue_id, position)) in atoms.iter().enumerate() {
+        let element = &name[..1];
+        content.push_str(&format!(
+            "ATOM  {:>5} {:<4} {:>3} A{:>4}    {:8.3}{:8.3}{:8.3}  1.00  0.00          {:>2}\n",
+            serial + 1,
+            name,
+            residue,
+            residue_id,
+            position[0],
+            position[1],
+            position[2],
+            element
+        ));
+    }
+    content
+}
+
+fn spaced(index: usize) -> [f32; 3] {
+    [index as f32 * 3.0, 0.0, 0.0]
+}
+
+fn bond_between(molecule: &Molecule, name1: &str, resid1: usize, name2: &str, resid2: usize) -> Option<BondOrder> {
+    let find = |name: &str, resid: usize| {
+        molecule.atoms.iter().position(|atom| atom.name == name && atom.residue_id == resid).map(molecule_core::AtomIndex)
+    };
+    let (atom1, atom2) = (find(name1, resid1)?, find(name2, resid2)?);
+    molecule
+        .bonds
+        .iter()
+        .find(|bond| (bond.atom1 == atom1 && bond.atom2 == atom2) || (bond.atom1 == atom2 && bond.atom2 == atom1))
+        .map(|bond| bond.order)
+}
+
+#[test]
+fn test_templates_cover_variants() {
+    let histidine = residue_template("HIE").unwrap();
+    assert_eq!(histidine.name, "HIS");
+    assert_eq!(histidine.kind, ResidueKind::Protein);
+    assert_eq!(histidine.bonds().filter(|bond| bond.2 == BondOrder::Aromatic).count(), 5);
+    assert_eq!(residue_template("DT3").unwrap().name, "DT");
+    assert_eq!(residue_template("U").unwrap().kind, ResidueKind::Rna);
+    assert!(residue_template("HEM").is_none());
+}
+
+#[test]
+fn test_peptide_bonds_and_orders() {
+    let names = ["N", "CA", "C", "O", "CB", "CG", "OD1", "OD2"];
+    let mut atoms: Vec<(&str, &str, usize, [f32; 3])> =
+        names.iter().enumerate().map(|(i, name)| (*name, "ASP", 1, spaced(i))).collect();
+    let carbon = spaced(2);
+    atoms.push(("N", "PHE", 2, [carbon[0], 1.33, 0.0]));
+    atoms.push(("H", "PHE", 2, [carbon[0], 2.33, 0.0]));
+    for (i, name) in ["CA", "C", "O", "CB", "CG", "CD1", "CD2", "CE1", "CE2", "CZ"].iter().enumerate() {
+        atoms.push((name, "PHE", 2, [i as f32 * 3.0, 10.0, 0.0]));
+    }
+
+    let molecule = PdbParser::new().parse_string(&pdb(&atoms)).unwrap();
+
+    assert_eq!(bond_between(&molecule, "CG", 1, "OD1", 1), Some(BondOrder::Double));
+    assert_eq!(bond_between(&molecule, "CG", 1, "OD2", 1), Some(BondOrder::Single));
+    assert_eq!(bond_between(&molecule, "C", 1, "O", 1), Some(BondOrder::Double));
+    assert_eq!(bond_between(&molecule, "C", 1, "N", 2), Some(BondOrder::Single));
+    assert_eq!(bond_between(&molecule, "N", 2, "H", 2), Some(BondOrder::Single));
+    assert_eq!(bond_between(&molecule, "CE1", 2, "CZ", 2), Some(BondOrder::Aromatic));
+
+    // 7 ASP + 1 link + 1 N-H + 11 PHE heavy-atom bonds; nothing from distances
+    assert_eq!(molecule.bonds.len(), 20);
+}
+
+#[test]
+fn test_phosphodiester_links_only_when_close() {
+    let sugar = ["P", "OP1", "OP2", "O5'", "C5'", "C4'", "O4'", "C3'", "O3'", "C2'", "C1'"];
+    let mut atoms: Vec<(&str, &str, usize, [f32; 3])> =
+        sugar.iter().enumerate().map(|(i, name)| (*name, "DA", 1, spaced(i))).collect();
+    let oxygen = spaced(8);
+    atoms.push(("P", "DC", 2, [oxygen[0], 1.6, 0.0]));
+    atoms.push(("P", "DG", 3, [oxygen[0], 30.0, 0.0]));
+
+    let molecule = PdbParser::new().parse_string(&pdb(&atoms)).unwrap();
+    assert_eq!(bond_between(&molecule, "P", 1, "OP1", 1), Some(BondOrder::Double));
+    assert_eq!(bond_between(&molecule, "O3'", 1, "P", 2), Some(BondOrder::Single));
+    assert_eq!(bond_between(&molecule, "C1'", 1, "O4'", 1), Some(BondOrder::Single));
+    assert_eq!(molecule.bonds.len(), 12);
+}
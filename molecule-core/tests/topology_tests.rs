@@ -0,0 +1,198 @@
+use molecule_core::{AtomIndex, Element, InpcrdParser, PrmtopParser, PsfParser};
+
+const WATER_PRMTOP: &str = "\
+%VERSION  VERSION_STAMP = V0001.000  DATE = 01/01/24  12:00:00
+%FLAG TITLE
+%FORMAT(20a4)
+WAT
+%FLAG POINTERS
+%FORMAT(10I8)
+       3       2       2       0       1       0       0       0       0       0
+       3       1       0       0       0       1       1       0       2       0
+       0       0       0       0       0       0       0       0       3       0
+       0
+%FLAG ATOM_NAME
+%FORMAT(20a4)
+O   H1  H2  
+%FLAG CHARGE
+%FORMAT(5E16.8)
+ -1.51973982E+01  7.59869910E+00  7.59869910E+00
+%FLAG ATOMIC_NUMBER
+%FORMAT(10I8)
+       8       1       1
+%FLAG MASS
+%FORMAT(5E16.8)
+  1.60000000E+01  1.00800000E+00  1.00800000E+00
+%FLAG ATOM_TYPE_INDEX
+%FORMAT(10I8)
+       1       2       2
+%FLAG NONBONDED_PARM_INDEX
+%FORMAT(10I8)
+       1       2       2       3
+%FLAG RESIDUE_LABEL
+%FORMAT(20a4)
+WAT 
+%FLAG RESIDUE_POINTER
+%FORMAT(10I8)
+       1
+%FLAG LENNARD_JONES_ACOEF
+%FORMAT(5E16.8)
+  5.82000000E+05  0.00000000E+00  0.00000000E+00
+%FLAG LENNARD_JONES_BCOEF
+%FORMAT(5E16.8)
+  5.95000000E+02  0.00000000E+00  0.00000000E+00
+%FLAG BONDS_INC_HYDROGEN
+%FORMAT(10I8)
+       0       3       1       0       6       1
+%FLAG BONDS_WITHOUT_HYDROGEN
+%FORMAT(10I8)
+
+%FLAG AMBER_ATOM_TYPE
+%FORMAT(20a4)
+OW  HW  HW  
+";
+
+const WATER_INPCRD: &str = "\
+WAT
+     3  0.1000000E+02
+   0.0000000   0.0000000   0.0000000   0.9572000   0.0000000   0.0000000
+  -0.2399872   0.9266272   0.0000000
+   0.0100000   0.0000000   0.0000000   0.0000000   0.0000000   0.0000000
+   0.0000000   0.0000000   0.0000000
+  30.0000000  30.0000000  30.0000000  90.0000000  90.0000000  90.0000000
+";
+
+#[test]
+fn test_prmtop_topology_and_parameters() {
+    let molecule = PrmtopParser::new().parse_string(WATER_PRMTOP).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 3);
+    assert_eq!(molecule.bonds.len(), 2);
+    assert_eq!((molecule.bonds[0].atom1, molecule.bonds[0].atom2), (AtomIndex(0), AtomIndex(1)));
+    assert_eq!((molecule.bonds[1].atom1, molecule.bonds[1].atom2), (AtomIndex(0), AtomIndex(2)));
+
+    let oxygen = &molecule.atoms[0];
+    assert_eq!(oxygen.name, "O");
+    assert_eq!(oxygen.element, Element::O);
+    assert_eq!(oxygen.residue_name, "WAT");
+    assert_eq!(oxygen.residue_id, 1);
+    assert_eq!(oxygen.atom_type.as_deref(), Some("OW"));
+    assert_eq!(oxygen.mass, Some(16.0));
+    assert!((oxygen.charge.unwrap() + 0.834).abs() < 1e-4);
+    assert!((oxygen.lj_sigma.unwrap() - 3.1506).abs() < 1e-3);
+    assert!((oxygen.lj_epsilon.unwrap() - 0.1521).abs() < 1e-3);
+
+    let hydrogen = &molecule.atoms[1];
+    assert_eq!(hydrogen.element, Element::H);
+    assert!((hydrogen.charge.unwrap() - 0.417).abs() < 1e-4);
+    assert_eq!(hydrogen.lj_epsilon, Some(0.0));
+}
+
+#[test]
+fn test_prmtop_rejects_bad_residue_pointers() {
+    // Residues must start at atom 1, increase and stay within the atom count
+    let pointers = "%FLAG RESIDUE_POINTER\n%FORMAT(10I8)\n       1\n";
+    for bad in ["       2\n", "       0\n", "       4\n"] {
+        let prmtop = WATER_PRMTOP.replace(pointers, &format!("%FLAG RESIDUE_POINTER\n%FORMAT(10I8)\n{}", bad));
+        assert!(PrmtopParser::new().parse_string(&prmtop).is_err(), "{:?}", bad);
+    }
+
+    // Two residues listed out of order would drop atoms
+    let two_residues = WATER_PRMTOP
+        .replace("       3       1       0       0", "       3       2       0       0")
+        .replace("WAT \n%FLAG RESIDUE_POINTER", "WAT WAT \n%FLAG RESIDUE_POINTER");
+    let ordered = two_residues.replace(pointers, "%FLAG RESIDUE_POINTER\n%FORMAT(10I8)\n       1       2\n");
+    assert_eq!(PrmtopParser::new().parse_string(&ordered).unwrap().residues.len(), 2);
+    let reversed = two_residues.replace(pointers, "%FLAG RESIDUE_POINTER\n%FORMAT(10I8)\n       2       1\n");
+    assert!(PrmtopParser::new().parse_string(&reversed).is_err());
+}
+
+#[test]
+fn test_inpcrd_onto_prmtop() {
+    let mut molecule = PrmtopParser::new().parse_string(WATER_PRMTOP).unwrap();
+    let frame = InpcrdParser::new().parse_string(WATER_INPCRD).unwrap();
+
+    assert_eq!(frame.time, Some(10.0));
+    frame.apply_to(&mut molecule).unwrap();
+
+    assert_eq!(molecule.atoms[1].position, [0.9572, 0.0, 0.0]);
+    let velocity = molecule.atoms[0].velocity.unwrap();
+    assert!((velocity[0] - 0.20455).abs() < 1e-5);
+    assert_eq!(molecule.unit_cell.unwrap().lengths(), [30.0, 30.0, 30.0]);
+}
+
+#[test]
+fn test_inpcrd_sections_from_line_counts() {
+    // One atom and a box: the box line must not be taken for velocities
+    let boxed = "ION\n     1\n   1.0000000   2.0000000   3.0000000\n  30.0000000  30.0000000  30.0000000  90.0000000  90.0000000  90.0000000\n";
+    let frame = InpcrdParser::new().parse_string(boxed).unwrap();
+    assert_eq!(frame.positions, vec![[1.0, 2.0, 3.0]]);
+    assert!(frame.velocities.is_none());
+    assert_eq!(frame.unit_cell.unwrap().lengths(), [30.0, 30.0, 30.0]);
+
+    // One atom with velocities and no box
+    let moving = "ION\n     1\n   1.0000000   2.0000000   3.0000000\n   0.0100000   0.0000000   0.0000000\n";
+    let frame = InpcrdParser::new().parse_string(moving).unwrap();
+    assert!(frame.velocities.is_some());
+    assert!(frame.unit_cell.is_none());
+
+    // Three atoms take two lines per section, so three lines are coordinates plus a box
+    let lines: Vec<&str> = WATER_INPCRD.lines().collect();
+    let boxed = [&lines[..4], &lines[6..]].concat().join("\n");
+    let frame = InpcrdParser::new().parse_string(&boxed).unwrap();
+    assert!(frame.velocities.is_none());
+    assert!(frame.unit_cell.is_some());
+
+    // A stray line that fits no layout is an error
+    let truncated = lines[..5].join("\n");
+    assert!(InpcrdParser::new().parse_string(&truncated).is_err());
+}
+
+const ALA_PSF: &str = "\
+PSF EXT
+
+         1 !NTITLE
+* ALANINE DIPEPTIDE FRAGMENT
+
+         5 !NATOM
+         1 PROA     1        ALA      N        NH3     -0.300000       14.0070           0
+         2 PROA     1        ALA      HT1      HC       0.330000        1.0080           0
+         3 PROA     1        ALA      CA       CT1      0.210000       12.0110           0
+         4 PROA     1        ALA      C        C        0.510000       12.0110           0
+         5 SOLV     2A       TIP3     OH2      OT      -0.834000       15.9994           0
+
+         3 !NBOND: bonds
+         1         2         1         3         3         4
+
+         0 !NTHETA: angles
+
+";
+
+#[test]
+fn test_psf_topology() {
+    let molecule = PsfParser::new().parse_string(ALA_PSF).unwrap();
+
+    assert_eq!(molecule.atoms.len(), 5);
+    assert_eq!(molecule.bonds.len(), 3);
+    assert_eq!((molecule.bonds[2].atom1, molecule.bonds[2].atom2), (AtomIndex(2), AtomIndex(3)));
+
+    let nitrogen = &molecule.atoms[0];
+    assert_eq!(nitrogen.name, "N");
+    assert_eq!(nitrogen.element, Element::N);
+    assert_eq!(nitrogen.chain_id, 'A');
+    assert_eq!(nitrogen.atom_type.as_deref(), Some("NH3"));
+    assert_eq!(nitrogen.charge, Some(-0.3));
+    assert_eq!(molecule.atoms[1].element, Element::H);
+
+    let water = &molecule.atoms[4];
+    assert_eq!(water.residue_id, 2);
+    assert_eq!(water.ins_code, 'A');
+    assert_eq!(water.chain_id, ' ');
+    assert_eq!(water.element, Element::O);
+}
+
+#[test]
+fn test_psf_rejects_bad_bond() {
+    let broken = ALA_PSF.replace("3         4\n", "3         9\n");
+    assert!(PsfParser::new().parse_string(&broken).is_err());
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use molecule_core::{Atom, Molecule, PdbParser, PdbWriter};
+
+const COMPLEX_PDB: &str = "\
+HEADER    OXIDOREDUCTASE                          12-MAR-99   1ABC
+TITLE     A SMALL TEST COMPLEX OF A DIPEPTIDE WITH A HEME-LIKE IRON SITE AND A
+TITLE    2 WATER MOLECULE
+CRYST1   50.000   60.000   70.000  90.00  95.00  90.00 P 1 21 1      2
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C
+ATOM      3  C   ALA A   1      13.149   5.994  -5.142  0.50 12.30           C
+ATOM      4  O   ALA A   1      13.766   5.985  -6.208  1.00  0.00           O
+ATOM      5 1HB  ALA A   1      11.400   7.500  -4.500  1.00  0.00           H
+TER       6      ALA A   1
+ATOM      7  N   GLY B   2      13.730   5.933  -3.944  1.00  0.00           N
+ATOM      8  CA  GLY B   2      15.175   5.850  -3.830  1.00  0.00           C
+TER       9      GLY B   2
+HETATM   10 FE   HEM B 101      20.000  10.000   0.000  1.00 20.00          FE2+
+HETATM   11  NA  HEM B 101      21.900  10.000   0.000  1.00 20.00           N
+HETATM   12  O   HOH B 201      30.000  30.000  30.000  1.00 35.00           O
+CONECT   10   11
+END
+";
+
+fn bond_set(molecule: &Molecule) -> HashSet<(usize, usize)> {
+    molecule
+        .bonds
+        .iter()
+        .map(|bond| (bond.atom1.0.min(bond.atom2.0), bond.atom1.0.max(bond.atom2.0)))
+        .collect()
+}
+
+fn assert_same_structure(original: &Molecule, copy: &Molecule) {
+    assert_eq!(original.atoms.len(), copy.atoms.len());
+    for (a, b) in original.atoms.iter().zip(&copy.atoms) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.element, b.element);
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.residue_id, b.residue_id);
+        assert_eq!(a.residue_name, b.residue_name);
+        assert_eq!(a.chain_id, b.chain_id);
+        assert_eq!(a.alt_loc, b.alt_loc);
+        assert_eq!(a.ins_code, b.ins_code);
+        assert_eq!(a.occupancy, b.occupancy);
+        assert_eq!(a.b_factor, b.b_factor);
+        assert_eq!(a.is_hetatm, b.is_hetatm);
+        assert_eq!(a.formal_charge, b.formal_charge);
+    }
+    assert_eq!(bond_set(original), bond_set(copy));
+    assert_eq!(original.metadata, copy.metadata);
+    assert_eq!(original.unit_cell, copy.unit_cell);
+    assert_eq!(original.models.len(), copy.models.len());
+    for (a, b) in original.models.iter().zip(&copy.models) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.atoms, b.atoms);
+    }
+}
+
+#[test]
+fn test_pdb_round_trip() {
+    let parser = PdbParser::new();
+    let molecule = parser.parse_string(COMPLEX_PDB).unwrap();
+
+    assert_eq!(molecule.metadata.id_code.as_deref(), Some("1ABC"));
+    assert_eq!(molecule.metadata.classification.as_deref(), Some("OXIDOREDUCTASE"));
+    assert_eq!(molecule.metadata.deposition_date.as_deref(), Some("12-MAR-99"));
+    assert_eq!(
+        molecule.metadata.title.as_deref(),
+        Some("A SMALL TEST COMPLEX OF A DIPEPTIDE WITH A HEME-LIKE IRON SITE AND A WATER MOLECULE")
+    );
+    assert_eq!(molecule.metadata.space_group.as_deref(), Some("P 1 21 1"));
+    assert_eq!(molecule.atoms[7].formal_charge, 2);
+    // The CONECT bond is longer than the distance cutoff
+    assert!(bond_set(&molecule).contains(&(7, 8)));
+
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+    let reparsed = parser.parse_string(&written).unwrap();
+    assert_same_structure(&molecule, &reparsed);
+
+    // Writing again gives identical text
+    assert_eq!(PdbWriter::new().write_string(&reparsed).unwrap(), written);
+}
+
+#[test]
+fn test_pdb_writer_columns() {
+    let molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+    let lines: Vec<&str> = written.lines().map(str::trim_end).collect();
+
+    assert_eq!(lines[0], "HEADER    OXIDOREDUCTASE                          12-MAR-99   1ABC");
+    assert!(lines.contains(&"CRYST1   50.000   60.000   70.000  90.00  95.00  90.00 P 1 21 1      1"));
+    assert!(lines.contains(&"ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C"));
+    assert!(lines.contains(&"ATOM      5 1HB  ALA A   1      11.400   7.500  -4.500  1.00  0.00           H"));
+    assert!(lines.contains(&"HETATM   10 FE   HEM B 101      20.000  10.000   0.000  1.00 20.00          FE2+"));
+
+    // One TER per chain, placed before the chain's HETATMs, taking the next serial
+    let ter_lines: Vec<&&str> = lines.iter().filter(|line| line.starts_with("TER")).collect();
+    assert_eq!(ter_lines, vec![&"TER       6      ALA A   1", &"TER       9      GLY B   2"]);
+
+    assert!(lines.contains(&"CONECT   10   11"));
+    assert!(lines.contains(&"CONECT   11   10"));
+    assert!(!lines.iter().any(|line| line.starts_with("CONECT    1")));
+    assert_eq!(lines.last(), Some(&"END"));
+}
+
+#[test]
+fn test_pdb_writer_models() {
+    let pdb_content = "
+MODEL        1
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  ALA A   1       1.000   0.000   0.000  1.00  0.00           C
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1       0.000   1.000   0.000  1.00  0.00           N
+ATOM      2  CA  ALA A   1       1.000   1.000   0.000  1.00  0.00           C
+ENDMDL
+";
+
+    let parser = PdbParser::new();
+    let molecule = parser.parse_string(pdb_content).unwrap();
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+
+    assert_eq!(written.matches("MODEL ").count(), 2);
+    assert_eq!(written.matches("ENDMDL").count(), 2);
+    assert_eq!(written.matches("TER ").count(), 2);
+
+    let reparsed = parser.parse_string(&written).unwrap();
+    assert_same_structure(&molecule, &reparsed);
+}
+
+#[test]
+fn test_pdb_writer_renumbers_serials() {
+    // Serials that would collide with TER records are renumbered
+    let pdb_content = "\
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  N   GLY B   1       3.000   0.000   0.000  1.00  0.00           N
+HETATM    3  O   HOH B 101       6.000   0.000   0.000  1.00  0.00           O
+";
+    let molecule = PdbParser::new().parse_string(pdb_content).unwrap();
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+    let serials: Vec<&str> = written.lines().map(|line| line.get(6..11).map_or("", str::trim)).collect();
+
+    assert_eq!(serials, ["1", "2", "3", "4", "5", ""]);
+    assert!(written.lines().nth(4).unwrap().starts_with("HETATM    5  O   HOH"));
+}
+
+#[test]
+fn test_pdb_writer_keeps_valid_serials() {
+    // Gaps are fine as long as every TER record has a free serial
+    let pdb_content = "\
+ATOM     10  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM     20  CA  ALA A   1       1.500   0.000   0.000  1.00  0.00           C
+HETATM   40 ZN    ZN A 101       5.000   0.000   0.000  1.00  0.00          ZN
+CONECT   40   20
+";
+    let molecule = PdbParser::new().parse_string(pdb_content).unwrap();
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+    let serials: Vec<&str> = written.lines().map(|line| line.get(6..11).map_or("", str::trim)).collect();
+
+    assert_eq!(serials, ["10", "20", "21", "40", "20", "40", ""]);
+    assert_same_structure(&molecule, &PdbParser::new().parse_string(&written).unwrap());
+}
+
+#[test]
+fn test_pdb_writer_keeps_models_next_to_loose_atoms() {
+    let pdb_content = "
+HETATM    1 ZN    ZN B 101       5.000   5.000   5.000  1.00  0.00          ZN
+MODEL        1
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1       0.000   1.000   0.000  1.00  0.00           N
+ENDMDL
+HETATM    2  O   HOH B 201       5.000   5.000   7.000  1.00  0.00           O
+CONECT    1    2
+";
+    let molecule = PdbParser::new().parse_string(pdb_content).unwrap();
+    let written = PdbWriter::new().write_string(&molecule).unwrap();
+    assert_eq!(written.matches("MODEL ").count(), 2);
+
+    // Loose serials collide with the models', so one count runs through all blocks
+    let records: Vec<(&str, &str)> = written
+        .lines()
+        .filter(|line| !line.starts_with("MODEL") && !line.starts_with("END"))
+        .map(|line| (&line[..6], line.get(6..11).map_or("", str::trim)))
+        .collect();
+    assert_eq!(
+        records,
+        [("HETATM", "1"), ("ATOM  ", "2"), ("TER   ", "3"), ("ATOM  ", "4"), ("TER   ", "5"), ("HETATM", "6"), ("CONECT", "1"), ("CONECT", "6")]
+    );
+    assert!(written.contains("CONECT    1    6\n"));
+
+    let reparsed = PdbParser::new().parse_string(&written).unwrap();
+    assert_eq!(reparsed.atoms.len(), 4);
+    assert_eq!(reparsed.models.iter().map(|model| model.atoms.len()).collect::<Vec<_>>(), vec![1, 1]);
+    assert!(reparsed.bonds.iter().any(|bond| reparsed[bond.atom1].name == "ZN" && reparsed[bond.atom2].name == "O"));
+}
+
+#[test]
+fn test_pdb_writer_rejects_oversized_serials() {
+    let mut molecule = Molecule::new();
+    for id in 1..=100_000 {
+        molecule.add_atom(Atom { id, name: "O".to_string(), residue_id: 1, ..Atom::default() });
+    }
+    assert!(PdbWriter::new().write_string(&molecule).is_err());
+
+    let mut molecule = PdbParser::new().parse_string(COMPLEX_PDB).unwrap();
+    molecule.atoms[0].residue_id = 10_000;
+    assert!(PdbWriter::new().write_string(&molecule).is_err());
+}
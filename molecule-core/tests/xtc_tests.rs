@@ -0,0 +1,266 @@
+use std::io::Cursor;
+
+use molecule_core::{PdbParser, XtcReader};
+
+const MAGIC_INTS: [i32; 25] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64, 80, 101, 128, 161, 203, 256,
+];
+const SMALL_INDEX: usize = 20;
+const PRECISION: f32 = 1000.0;
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_count: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, count: u32, value: u32) {
+        for i in (0..count).rev() {
+            if self.bit_count.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.bit_count % 8);
+            self.bit_count += 1;
+        }
+    }
+
+    fn write_ints(&mut self, bit_count: u32, sizes: [u32; 3], values: [i32; 3]) {
+        let packed = ((values[0] as u128 * sizes[1] as u128) + values[1] as u128) * sizes[2] as u128
+            + values[2] as u128;
+        let mut remaining = bit_count;
+        let mut shift = 0;
+        while remaining > 0 {
+            let chunk = remaining.min(8);
+            self.write(chunk, ((packed >> shift) & 0xff) as u32);
+            shift += 8;
+            remaining -= chunk;
+        }
+    }
+}
+
+// Minimal xdrfile-compatible compressor: water-style runs of small
+// displacements at a fixed small index, everything else as full integers.
+fn compress(positions: &[[f32; 3]]) -> Vec<u8> {
+    let ints: Vec<[i32; 3]> = positions
+        .iter()
+        .map(|p| p.map(|c| (c * PRECISION).round() as i32))
+        .collect();
+    let min_int: [i32; 3] = std::array::from_fn(|d| ints.iter().map(|c| c[d]).min().unwrap());
+    let max_int: [i32; 3] = std::array::from_fn(|d| ints.iter().map(|c| c[d]).max().unwrap());
+    let sizes: [u32; 3] = std::array::from_fn(|d| (max_int[d] - min_int[d] + 1) as u32);
+    let product: u128 = sizes.iter().map(|&s| s as u128).product();
+    let combined_bits = 128 - product.leading_zeros();
+
+    let small_size = MAGIC_INTS[SMALL_INDEX];
+    let small_num = small_size / 2;
+    let is_small = |a: [i32; 3], b: [i32; 3]| (0..3).all(|d| (a[d] - b[d] + small_num).clamp(0, small_size - 1) == a[d] - b[d] + small_num);
+
+    let mut bits = BitWriter::default();
+    let mut i = 0;
+    while i < ints.len() {
+        let mut run = vec![];
+        if i + 1 < ints.len() && is_small(ints[i], ints[i + 1]) {
+            run.push(i);
+            let mut previous = i;
+            let mut next = i + 2;
+            while next < ints.len() && run.len() < 10 && is_small(ints[next], ints[previous]) {
+                run.push(next);
+                previous = next;
+                next += 1;
+            }
+        }
+
+        let large = if run.is_empty() { i } else { i + 1 };
+        let relative: [i32; 3] = std::array::from_fn(|d| ints[large][d] - min_int[d]);
+        bits.write_ints(combined_bits, sizes, relative);
+        bits.write(1, 1);
+        bits.write(5, 3 * run.len() as u32 + 1);
+
+        let mut previous = ints[large];
+        for &atom in &run {
+            let delta: [i32; 3] = std::array::from_fn(|d| ints[atom][d] - previous[d] + small_num);
+            bits.write_ints(SMALL_INDEX as u32, [small_size as u32; 3], delta);
+            previous = ints[atom];
+        }
+
+        i += if run.is_empty() { 1 } else { run.len() + 1 };
+    }
+
+    let mut out = Vec::new();
+    out.extend(PRECISION.to_be_bytes());
+    min_int.iter().chain(&max_int).for_each(|v| out.extend(v.to_be_bytes()));
+    out.extend((SMALL_INDEX as i32).to_be_bytes());
+    out.extend((bits.bytes.len() as i32).to_be_bytes());
+    out.extend(&bits.bytes);
+    out.resize(out.len().div_ceil(4) * 4, 0);
+    out
+}
+
+fn write_frame(out: &mut Vec<u8>, step: i32, time: f32, box_nm: f32, positions: &[[f32; 3]]) {
+    out.extend(1995i32.to_be_bytes());
+    out.extend((positions.len() as i32).to_be_bytes());
+    out.extend(step.to_be_bytes());
+    out.extend(time.to_be_bytes());
+    for row in 0..3 {
+        for col in 0..3 {
+            out.extend(if row == col { box_nm } else { 0.0f32 }.to_be_bytes());
+        }
+    }
+    out.extend((positions.len() as i32).to_be_bytes());
+    if positions.len() <= 9 {
+        positions.iter().flatten().for_each(|c| out.extend(c.to_be_bytes()));
+    } else {
+        out.extend(compress(positions));
+    }
+}
+
+fn water_box(frame: usize) -> Vec<[f32; 3]> {
+    let shift = 0.01 * frame as f32;
+    let mut positions = vec![];
+    for w in 0..4 {
+        let origin = [0.3 * w as f32 + shift, 1.2, 2.5 - 0.4 * w as f32];
+        positions.push(origin);
+        positions.push([origin[0] + 0.0957, origin[1], origin[2]]);
+        positions.push([origin[0] - 0.024, origin[1] + 0.0927, origin[2]]);
+    }
+    // Isolated atoms that cannot be part of a run
+    positions.push([3.0, 0.1, 0.2]);
+    positions.push([0.5, 2.9, 1.1 + shift]);
+    positions
+}
+
+#[test]
+fn test_xtc_decompression_and_seeking() {
+    let mut data = Vec::new();
+    for frame in 0..3 {
+        write_frame(&mut data, frame as i32 * 500, frame as f32 * 1.5, 3.2, &water_box(frame));
+    }
+
+    let mut reader = XtcReader::new(Cursor::new(data)).unwrap();
+    assert_eq!(reader.n_atoms(), 14);
+    assert_eq!(reader.n_frames(), 3);
+    assert_eq!(reader.offsets()[0], 0);
+
+    for index in [2, 0, 1] {
+        let frame = reader.read_frame(index).unwrap();
+        assert_eq!(frame.index, index);
+        assert_eq!(frame.step, Some(index as i64 * 500));
+        assert_eq!(frame.time, Some(index as f32 * 1.5));
+        assert_eq!(frame.unit_cell.unwrap().lengths(), [32.0, 32.0, 32.0]);
+
+        let expected = water_box(index);
+        assert_eq!(frame.positions.len(), expected.len());
+        for (actual, expected) in frame.positions.iter().zip(&expected) {
+            for d in 0..3 {
+                // 1/1000 nm precision, in Å
+                assert!((actual[d] - expected[d] * 10.0).abs() < 0.006, "{:?} vs {:?}", actual, expected);
+            }
+        }
+    }
+
+    assert!(reader.read_frame(3).is_err());
+}
+
+#[test]
+fn test_xtc_uncompressed_frames_onto_topology() {
+    let topology = "
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  ALA A   1       1.000   0.000   0.000  1.00  0.00           C
+";
+    let mut molecule = PdbParser::new().parse_string(topology).unwrap();
+
+    let mut data = Vec::new();
+    write_frame(&mut data, 0, 0.0, 2.0, &[[0.1, 0.2, 0.3], [0.2, 0.2, 0.3]]);
+    write_frame(&mut data, 10, 0.02, 2.0, &[[0.15, 0.2, 0.3], [0.25, 0.2, 0.3]]);
+
+    let mut reader = XtcReader::new(Cursor::new(data)).unwrap();
+    let frames: Vec<_> = reader.frames().collect::<Result<_, _>>().unwrap();
+    assert_eq!(frames.len(), 2);
+
+    frames[1].apply_to(&mut molecule).unwrap();
+    assert_eq!(molecule.atoms[0].position, [1.5, 2.0, 3.0]);
+    assert_eq!(molecule.atoms[1].name, "CA");
+    assert!(molecule.unit_cell.unwrap().is_orthorhombic());
+}
+
+#[test]
+fn test_xtc_rejects_bad_magic() {
+    let data = vec![0u8; 64];
+    assert!(XtcReader::new(Cursor::new(data)).is_err());
+}
+
+// Coordinates of tests/data/reference.xtc in units of 1/precision nm; they
+// mirror `frames()` in tests/data/xdrfile_compress.py, which wrote the file
+fn reference_frames() -> Vec<(f32, Vec<[i64; 3]>)> {
+    let mut packed = Vec::new();
+    for k in 0..12 {
+        packed.push([1000 + 38 * k, 1500 + (k % 3) * 21, 2000 + (k % 2) * 17]);
+    }
+    for w in 0..6 {
+        let o = [600 + 310 * w, 700 + 45 * w, 900 + 120 * (w % 3)];
+        packed.extend([o, [o[0] + 96, o[1], o[2]], [o[0] - 24, o[1] + 93, o[2]]]);
+    }
+    for k in 0..8 {
+        packed.push([300 + 900 * k, 2800 - 250 * k, 400 + 333 * (k % 4)]);
+    }
+    for k in 0..10 {
+        packed.push([5000 + 150 * k, 5000 + (k % 2) * 90, 5000]);
+    }
+    for w in 0..4 {
+        let o = [5200 + 400 * w, 6000, 6000];
+        packed.extend([o, [o[0] + 9, o[1], o[2]], [o[0], o[1] + 8, o[2]]]);
+    }
+
+    let mut spread: Vec<[i64; 3]> = packed.iter().map(|atom| atom.map(|c| 1000 * c)).collect();
+    spread[3] = [20_000_000, 0, 5000];
+
+    vec![(1000.0, packed), (1e6, spread)]
+}
+
+#[test]
+fn test_xtc_reference_file() {
+    // Encoded by a port of xdrfile_compress_coord_float that does not share
+    // code with the reader. Frame 0 exercises runs, run-length reuse and both
+    // directions of the small-integer range; frame 1 needs full-width integers.
+    let data = include_bytes!("data/reference.xtc");
+    let mut reader = XtcReader::new(Cursor::new(&data[..])).unwrap();
+    assert_eq!(reader.n_atoms(), 60);
+    assert_eq!(reader.n_frames(), 2);
+    let frames: Vec<_> = reader.frames().collect::<Result<_, _>>().unwrap();
+    assert_eq!(frames.len(), 2);
+
+    assert_eq!(frames[1].step, Some(250));
+    assert_eq!(frames[1].time, Some(0.5));
+    assert_eq!(frames[1].unit_cell.unwrap().lengths(), [210.0, 210.0, 210.0]);
+
+    for (frame, (precision, expected)) in frames.iter().zip(reference_frames()) {
+        assert_eq!(frame.positions.len(), expected.len());
+        for (actual, expected) in frame.positions.iter().zip(&expected) {
+            for d in 0..3 {
+                let expected = expected[d] as f32 / precision * 10.0;
+                assert!((actual[d] - expected).abs() < 1e-3, "{:?} vs {:?}", actual, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_xtc_rejects_oversized_payloads() {
+    let positions = water_box(0);
+    let mut data = Vec::new();
+    write_frame(&mut data, 0, 0.0, 3.0, &positions);
+    assert!(XtcReader::new(Cursor::new(data.clone())).is_ok());
+
+    // A 64-bit byte count near u64::MAX must not overflow or allocate
+    let mut large = data.clone();
+    large[..4].copy_from_slice(&2023i32.to_be_bytes());
+    let count_at = 4 * 14 + 4 * 8;
+    large.splice(count_at..count_at + 4, u64::MAX.to_be_bytes());
+    assert!(XtcReader::new(Cursor::new(large)).is_err());
+
+    // A truncated payload is caught when indexing, not when reading
+    let truncated = data[..data.len() - 4].to_vec();
+    assert!(XtcReader::new(Cursor::new(truncated)).is_err());
+}
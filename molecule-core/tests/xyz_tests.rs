@@ -0,0 +1,38 @@
+use molecule_core::{AtomIndex, PdbParser, XyzWriter};
+
+const WATER_PDB: &str = "\
+ATOM      1  O   HOH A   1       0.000   0.000   0.000  1.00  0.00           O
+ATOM      2  H1  HOH A   1       0.957   0.000   0.000  1.00  0.00           H
+ATOM      3  H2  HOH A   1      -0.240   0.927   0.000  1.00  0.00           H
+END
+";
+
+#[test]
+fn test_write_xyz() {
+    let molecule = PdbParser::new().parse_string(WATER_PDB).unwrap();
+    let xyz = XyzWriter::new().write_string(&molecule).unwrap();
+
+    let lines: Vec<&str> = xyz.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "3");
+    assert_eq!(lines[1], "");
+    let fields: Vec<&str> = lines[3].split_whitespace().collect();
+    assert_eq!(fields, ["H", "0.957000", "0.000000", "0.000000"]);
+}
+
+#[test]
+fn test_write_xyz_selection_and_models() {
+    let atoms = WATER_PDB.trim_end_matches("END\n");
+    let models = format!("MODEL        1\n{}ENDMDL\nMODEL        2\n{}ENDMDL\n", atoms, atoms);
+    let molecule = PdbParser::new().parse_string(&models).unwrap();
+    assert_eq!(molecule.models.len(), 2);
+
+    // Oxygens of both models, one frame per model
+    let xyz = XyzWriter::new().with_selection(vec![AtomIndex(0), AtomIndex(3)]).write_string(&molecule).unwrap();
+    let lines: Vec<&str> = xyz.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "1");
+    assert!(lines[2].starts_with("O "));
+    assert_eq!(lines[3], "1");
+    assert!(lines[5].starts_with("O "));
+}
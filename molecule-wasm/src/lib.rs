@@ -1,6 +1,5 @@
 use wasm_bindgen::prelude::*;
-use molecule_core::{BondOrder, Molecule, PdbParser};
-use std::collections::HashMap;
+use molecule_core::{content_hash, BinaryParser, BinaryWriter, BondOrder, Entity, PdbParser, PdbWriter, ResidueKind};
 use serde::{Serialize, Deserialize};
 
 #[wasm_bindgen(start)]
@@ -8,35 +7,6 @@ pub fn init() {
     console_error_panic_hook::set_once();
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct JsAtom {
-    pub id: usize,
-    pub name: String,
-    pub element: String,
-    pub position: [f32; 3],
-    pub residue_id: usize,
-    pub chain_id: String,
-    pub b_factor: f32,
-    pub occupancy: f32,
-    pub residue_name: String,
-    pub alt_loc: String,
-    pub ins_code: String,
-    pub is_hetatm: bool,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct JsBond {
-    pub atom1_id: usize,
-    pub atom2_id: usize,
-    pub order: String,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct JsMolecule {
-    pub atoms: Vec<JsAtom>,
-    pub bonds: Vec<JsBond>,
-}
-
 #[derive(Serialize, Deserialize)]
 struct Atom3DMol {
     serial: i32,
@@ -60,49 +30,40 @@ struct Bond3DMol {
     order: i32,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 struct Molecule3DMol {
     atoms: Vec<Atom3DMol>,
     bonds: Vec<Bond3DMol>,
 }
 
-fn convert_molecule_to_js(molecule: &Molecule) -> JsMolecule {
-    let atoms = molecule.atoms.iter().map(|atom| {
-        JsAtom {
-            id: atom.id,
-            name: atom.name.clone(),
-            element: format!("{:?}", atom.element),
-            position: atom.position,
-            residue_id: atom.residue_id,
-            chain_id: atom.chain_id.to_string(),
-            b_factor: atom.b_factor,
-            occupancy: atom.occupancy,
-            residue_name: atom.residue_name.clone(),
-            alt_loc: atom.alt_loc.to_string(),
-            ins_code: atom.ins_code.to_string(),
-            is_hetatm: atom.is_hetatm,
-        }
-    }).collect();
-
-    let bonds = molecule.bonds.iter().map(|bond| {
-        JsBond {
-            atom1_id: bond.atom1_id,
-            atom2_id: bond.atom2_id,
-            order: format!("{:?}", bond.order),
-        }
-    }).collect();
-
-    JsMolecule { atoms, bonds }
+#[derive(Serialize)]
+struct ResidueClass {
+    chain: String,
+    resi: i32,
+    resn: String,
+    kind: ResidueKind,
+}
+
+#[derive(Serialize)]
+struct ChainClass {
+    chain: String,
+    model: Option<usize>,
+    kind: ResidueKind,
+}
+
+#[derive(Serialize)]
+struct Classification {
+    residues: Vec<ResidueClass>,
+    chains: Vec<ChainClass>,
+    entities: Vec<Entity>,
 }
 
 #[wasm_bindgen]
 pub fn parse_pdb(pdb_content: &str) -> Result<JsValue, JsValue> {
     let parser = PdbParser::new();
     match parser.parse_string(pdb_content) {
-        Ok(molecule) => {
-            let js_molecule = convert_molecule_to_js(&molecule);
-            Ok(serde_wasm_bindgen::to_value(&js_molecule)?)
-        },
+        Ok(molecule) => Ok(serde_wasm_bindgen::to_value(&molecule)?),
         Err(err) => Err(JsValue::from_str(&format!("Error parsing PDB: {}", err))),
     }
 }
@@ -143,15 +104,7 @@ pub fn get_3dmol_bonds(pdb_content: &str) -> Result<JsValue, JsValue> {
     let parser = PdbParser::new();
     match parser.parse_string(pdb_content) {
         Ok(molecule) => {
-            let atom_id_to_index: HashMap<_, _> = molecule.atoms.iter()
-                .enumerate()
-                .map(|(i, atom)| (atom.id, i as i32))
-                .collect();
-            
             let bonds: Vec<Bond3DMol> = molecule.bonds.iter().map(|bond| {
-                let atom1_index = atom_id_to_index.get(&bond.atom1_id).unwrap_or(&0);
-                let atom2_index = atom_id_to_index.get(&bond.atom2_id).unwrap_or(&0);
-                
                 let order = match bond.order {
                     BondOrder::Single => 1,
                     BondOrder::Double => 2,
@@ -160,8 +113,8 @@ pub fn get_3dmol_bonds(pdb_content: &str) -> Result<JsValue, JsValue> {
                 };
                 
                 Bond3DMol {
-                    from: *atom1_index,
-                    to: *atom2_index,
+                    from: bond.atom1.0 as i32,
+                    to: bond.atom2.0 as i32,
                     order,
                 }
             }).collect();
@@ -177,50 +130,65 @@ pub fn prepare_for_3dmol(pdb_content: &str) -> Result<JsValue, JsValue> {
     let parser = PdbParser::new();
     match parser.parse_string(pdb_content) {
         Ok(molecule) => {
-            let mut formatted_atoms = Vec::new();
-            
-            for atom in &molecule.atoms {
-                let element_str = format!("{:?}", atom.element);
-                let element = element_str.trim_matches(|c| c == '_' || c == ' ');
-                
-                let pdb_atom_line = format!(
-                    "{:<6}{:>5} {:<4}{:1}{:>3} {:1}{:>4}{:1}   {:8.3}{:8.3}{:8.3}{:6.2}{:6.2}           {:>2}",
-                    if atom.is_hetatm { "HETATM" } else { "ATOM" },
-                    atom.id,
-                    atom.name,
-                    atom.alt_loc,
-                    atom.residue_name,
-                    atom.chain_id,
-                    atom.residue_id,
-                    atom.ins_code,
-                    atom.position[0],
-                    atom.position[1],
-                    atom.position[2],
-                    atom.occupancy,
-                    atom.b_factor,
-                    element
-                );
-                
-                formatted_atoms.push(pdb_atom_line);
-            }
-            
-            if !molecule.atoms.is_empty() {
-                let last_atom = &molecule.atoms[molecule.atoms.len() - 1];
-                let ter_line = format!(
-                    "TER   {:>5}      {:>3} {:1}{:>4}{:1}",
-                    last_atom.id + 1,
-                    last_atom.residue_name,
-                    last_atom.chain_id,
-                    last_atom.residue_id,
-                    last_atom.ins_code
-                );
-                formatted_atoms.push(ter_line);
-            }
-            
-            let pdb_string = formatted_atoms.join("\n");
+            let pdb_string = PdbWriter::new()
+                .write_string(&molecule)
+                .map_err(|err| JsValue::from_str(&format!("Error writing PDB: {}", err)))?;
             
             Ok(JsValue::from_str(&pdb_string))
         },
         Err(err) => Err(JsValue::from_str(&format!("Error parsing PDB: {}", err))),
     }
-} 
\ No newline at end of file
+}
+
+/// Hex content hash of a source file, for keying cached binary snapshots.
+#[wasm_bindgen]
+pub fn source_hash(content: &[u8]) -> String {
+    format!("{:016x}", content_hash(content))
+}
+
+/// Parses a PDB file into a binary snapshot that `parse_binary` loads
+/// without re-parsing or re-bonding.
+#[wasm_bindgen]
+pub fn pdb_to_binary(pdb_content: &str) -> Result<Vec<u8>, JsValue> {
+    let molecule = PdbParser::new()
+        .parse_string(pdb_content)
+        .map_err(|err| JsValue::from_str(&format!("Error parsing PDB: {}", err)))?;
+
+    BinaryWriter::new()
+        .write_bytes(&molecule)
+        .map_err(|err| JsValue::from_str(&format!("Error writing binary snapshot: {}", err)))
+}
+
+#[wasm_bindgen]
+pub fn parse_binary(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    match BinaryParser::new().parse_bytes(bytes) {
+        Ok(molecule) => Ok(serde_wasm_bindgen::to_value(&molecule)?),
+        Err(err) => Err(JsValue::from_str(&format!("Error reading binary snapshot: {}", err))),
+    }
+}
+
+/// Kinds of every residue and chain (protein, dna, rna, water, ion,
+/// carbohydrate or ligand) and the chains grouped into entities.
+#[wasm_bindgen]
+pub fn classify_pdb(pdb_content: &str) -> Result<JsValue, JsValue> {
+    let molecule = PdbParser::new()
+        .parse_string(pdb_content)
+        .map_err(|err| JsValue::from_str(&format!("Error parsing PDB: {}", err)))?;
+
+    let classification = Classification {
+        residues: molecule.residues().map(|residue| ResidueClass {
+            chain: residue.chain().id.to_string(),
+            resi: residue.id as i32,
+            resn: residue.name.clone(),
+            kind: residue.kind(),
+        }).collect(),
+        chains: molecule.chains().map(|chain| ChainClass {
+            chain: chain.id.to_string(),
+            model: chain.model().id(),
+            kind: chain.kind(),
+        }).collect(),
+        entities: molecule.entities(),
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&classification)?)
+}